@@ -0,0 +1,47 @@
+# CRT Terminal - Fish semantic prompt integration
+# This script adds OSC 133 markers for command success/fail detection
+# and reports the working directory with OSC 7.
+# Loaded with `fish --init-command` after the user's config has been read.
+
+status is-interactive; or exit
+
+# Track whether a command has been executed (don't report exit code on startup)
+set -g __crt_cmd_executed 0
+
+# OSC 7: report the working directory as a percent-encoded file:// URI
+function __crt_report_cwd
+    printf '\e]7;file://%s%s\a' $hostname (string escape --style=url -- $PWD)
+end
+
+# Before each prompt is drawn
+function __crt_prompt_start --on-event fish_prompt
+    __crt_report_cwd
+    # A = prompt start
+    printf '\e]133;A\a'
+end
+
+# User pressed enter on a command line
+function __crt_preexec --on-event fish_preexec
+    set -g __crt_cmd_executed 1
+    # C = command output start
+    printf '\e]133;C\a'
+end
+
+# Command finished; $status is its exit code
+function __crt_postexec --on-event fish_postexec
+    set -l exit_code $status
+    if test $__crt_cmd_executed -eq 1
+        # D = command finished with exit code
+        printf '\e]133;D;%d\a' $exit_code
+        set -g __crt_cmd_executed 0
+    end
+end
+
+# B = input start, emitted at the end of the user's prompt
+if functions -q fish_prompt
+    functions -c fish_prompt __crt_user_fish_prompt
+    function fish_prompt
+        __crt_user_fish_prompt
+        printf '\e]133;B\a'
+    end
+end
@@ -2,8 +2,15 @@
 //!
 //! Run with: cargo bench -p crt-core
 
-use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
-use crt_core::{Size, Terminal};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use crt_core::{ParserWorker, PtyOutput, Size, Terminal};
 
 /// Generate realistic terminal output: mixed ASCII with ANSI escape sequences.
 fn generate_terminal_output(lines: usize, cols: usize) -> Vec<u8> {
@@ -141,11 +148,165 @@ fn bench_damage_check(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark ingesting a 100MB flood of mixed ANSI output in PTY-sized reads,
+/// parsed inline versus on a parser thread
+fn bench_ingest_flood(c: &mut Criterion) {
+    const FLOOD_BYTES: usize = 100 * 1024 * 1024;
+    const READ_BYTES: usize = 4096;
+
+    let page = generate_terminal_output(500, 200);
+    let data: Vec<u8> = page.iter().copied().cycle().take(FLOOD_BYTES).collect();
+    let size = Size::new(200, 50);
+
+    let mut group = c.benchmark_group("ingest_flood");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("inline", |b| {
+        b.iter(|| {
+            let mut terminal = Terminal::new(size);
+            for chunk in data.chunks(READ_BYTES) {
+                terminal.process_input(chunk);
+            }
+        });
+    });
+
+    group.bench_function("worker", |b| {
+        // Reads are built untimed, the way the PTY reader hands them over
+        b.iter_batched(
+            || {
+                data.chunks(READ_BYTES)
+                    .map(<[u8]>::to_vec)
+                    .collect::<Vec<_>>()
+            },
+            |reads| {
+                let terminal = Arc::new(Mutex::new(Terminal::new(size)));
+                let (tx, chunks) = mpsc::channel();
+                let (recycle, _) = mpsc::channel();
+                let worker = ParserWorker::spawn(PtyOutput { chunks, recycle }, &terminal, None);
+                for read in reads {
+                    tx.send(read).unwrap();
+                }
+                drop(tx);
+                worker.join();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
+/// Read the visible screen the way a frame does
+fn draw(terminal: &Terminal) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let mut hasher = DefaultHasher::new();
+    for cell in terminal.renderable_content().display_iter {
+        hasher.write_u32(cell.c as u32);
+    }
+    hasher.finish()
+}
+
+/// Benchmark how long a frame keeps the UI thread busy while a flood of
+/// mixed ANSI output is arriving, with output parsed inline versus on a
+/// parser thread
+///
+/// Inline, each frame first parses what arrived since the last one; with a
+/// worker, it only waits for the terminal lock, which the worker gives up
+/// every few milliseconds.
+fn bench_frame_during_flood(c: &mut Criterion) {
+    /// Output a flood delivers between two frames (~60 MiB/s at 60fps)
+    const FRAME_BYTES: usize = 1024 * 1024;
+    const FLOOD_BYTES: usize = 16 * FRAME_BYTES;
+    const READ_BYTES: usize = 4096;
+    /// Reads kept in flight to the worker
+    const QUEUED_READS: usize = 256;
+    /// Time between frames, so the worker gets the lock in between
+    const FRAME_GAP: Duration = Duration::from_millis(1);
+
+    let page = generate_terminal_output(500, 200);
+    let data: Vec<u8> = page.iter().copied().cycle().take(FLOOD_BYTES).collect();
+    let size = Size::new(200, 50);
+
+    let mut group = c.benchmark_group("frame_during_flood");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(2));
+
+    group.bench_function("inline", |b| {
+        b.iter_custom(|iters| {
+            let mut terminal = Terminal::new(size);
+            let mut frames = data.chunks(FRAME_BYTES).cycle();
+            let mut busy = Duration::ZERO;
+            for _ in 0..iters {
+                let output = frames.next().unwrap();
+                let start = Instant::now();
+                for read in output.chunks(READ_BYTES) {
+                    terminal.process_input(read);
+                }
+                black_box(draw(&terminal));
+                busy += start.elapsed();
+            }
+            busy
+        });
+    });
+
+    group.bench_function("worker", |b| {
+        b.iter_custom(|iters| {
+            let terminal = Arc::new(Mutex::new(Terminal::new(size)));
+            let (tx, chunks) = mpsc::channel();
+            let (recycle, recycled) = mpsc::channel();
+            let worker = ParserWorker::spawn(PtyOutput { chunks, recycle }, &terminal, None);
+
+            // Keep the worker flooded with the whole of `data`, refilling
+            // each read it's done with, so nothing is allocated while
+            // frames are timed
+            let mut reads = data.chunks(READ_BYTES).cycle();
+            for read in reads.by_ref().take(QUEUED_READS) {
+                tx.send(read.to_vec()).unwrap();
+            }
+            let stop = AtomicBool::new(false);
+            let busy = thread::scope(|scope| {
+                scope.spawn(|| {
+                    for mut read in recycled {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        read.clear();
+                        read.extend_from_slice(reads.next().unwrap());
+                        if tx.send(read).is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                let mut busy = Duration::ZERO;
+                for _ in 0..iters {
+                    thread::sleep(FRAME_GAP);
+                    let start = Instant::now();
+                    black_box(draw(&terminal.lock().unwrap()));
+                    busy += start.elapsed();
+                }
+                stop.store(true, Ordering::Relaxed);
+                busy
+            });
+            drop(tx);
+            worker.join();
+            busy
+        });
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_process_input,
     bench_all_lines_text,
     bench_content_hash,
     bench_damage_check,
+    bench_ingest_flood,
+    bench_frame_during_flood,
 );
 criterion_main!(benches);
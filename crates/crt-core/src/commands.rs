@@ -0,0 +1,290 @@
+//! Command history from OSC 133 marks
+//!
+//! A prompt (`A`), its input (`B`), the command's output (`C`) and its end
+//! (`D`) together describe one command. [`CommandLog`] follows the marks and
+//! keeps a record per command that ran, with its lines kept as absolute lines
+//! (see [`crate::scrollback`]) so records stay with their text as it scrolls.
+//! The terminal turns them into [`CommandRecord`]s in grid lines on request.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::FileUri;
+
+/// A command the shell ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandRecord {
+    /// Identifies the command for as long as it's kept, unlike its lines,
+    /// which move as output scrolls
+    pub id: u64,
+    /// Grid line of the prompt (negative for scrollback)
+    pub prompt_line: i32,
+    /// Command line as entered, from the input zone
+    pub command: String,
+    /// When the command started (OSC 133;C)
+    pub started: Instant,
+    /// When it finished (OSC 133;D), or `None` while it runs
+    pub finished: Option<Instant>,
+    /// Exit status, if the shell reported one
+    pub exit_code: Option<i32>,
+    /// Working directory reported at the prompt (OSC 7)
+    pub cwd: Option<FileUri>,
+    /// Grid lines of the output, end exclusive (through the cursor's line
+    /// while the command runs); just the summary row while it's folded
+    pub output: Range<i32>,
+    /// Whether the output is folded into a summary row
+    pub folded: bool,
+}
+
+impl CommandRecord {
+    /// How long the command ran, or has been running
+    pub fn duration(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started)
+    }
+
+    /// Check if the command is still running
+    pub fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+}
+
+/// A command, with absolute lines
+#[derive(Debug, Clone)]
+pub(crate) struct Command {
+    pub(crate) id: u64,
+    pub(crate) prompt: usize,
+    pub(crate) command: String,
+    pub(crate) started: Instant,
+    pub(crate) finished: Option<Instant>,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) cwd: Option<FileUri>,
+    pub(crate) output_start: usize,
+    /// End of the output (exclusive), once the command has finished
+    pub(crate) output_end: Option<usize>,
+}
+
+/// The prompt being edited, until its command runs
+#[derive(Debug)]
+struct Prompt {
+    line: usize,
+    /// Line and column where the input starts (OSC 133;B)
+    input: Option<(usize, usize)>,
+    cwd: Option<FileUri>,
+}
+
+/// Commands run in a terminal, oldest first
+#[derive(Debug, Default)]
+pub(crate) struct CommandLog {
+    commands: VecDeque<Command>,
+    prompt: Option<Prompt>,
+    /// Id of the next command to run, never reused
+    next_id: u64,
+}
+
+impl CommandLog {
+    /// A prompt starts (OSC 133;A)
+    pub(crate) fn prompt_start(&mut self, line: usize, cwd: Option<FileUri>, now: Instant) {
+        // A command the shell never reported the end of ends here
+        if let Some(command) = self.commands.back_mut()
+            && command.finished.is_none()
+        {
+            command.finished = Some(now);
+            command.output_end = Some(line.max(command.output_start));
+        }
+        self.prompt = Some(Prompt {
+            line,
+            input: None,
+            cwd,
+        });
+    }
+
+    /// The input starts (OSC 133;B)
+    pub(crate) fn input_start(&mut self, line: usize, column: usize) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.input = Some((line, column));
+        }
+    }
+
+    /// Where the pending prompt's input starts
+    pub(crate) fn input(&self) -> Option<(usize, usize)> {
+        self.prompt.as_ref()?.input
+    }
+
+    /// The command runs, its output starting on `line` (OSC 133;C)
+    pub(crate) fn output_start(&mut self, command: String, line: usize, now: Instant) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        self.next_id += 1;
+        self.commands.push_back(Command {
+            id: self.next_id,
+            prompt: prompt.line,
+            command,
+            started: now,
+            finished: None,
+            exit_code: None,
+            cwd: prompt.cwd,
+            output_start: line,
+            output_end: None,
+        });
+    }
+
+    /// The running command ends, its output ending before `line` (OSC 133;D)
+    pub(crate) fn command_end(&mut self, exit_code: Option<i32>, line: usize, now: Instant) {
+        if let Some(command) = self.commands.back_mut()
+            && command.finished.is_none()
+        {
+            command.finished = Some(now);
+            command.exit_code = exit_code;
+            command.output_end = Some(line.max(command.output_start));
+        }
+    }
+
+    /// Commands, oldest first
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &Command> {
+        self.commands.iter()
+    }
+
+    /// Lines of every prompt, oldest first, including one still being edited
+    pub(crate) fn prompts(&self) -> impl Iterator<Item = usize> + '_ {
+        let pending = self.prompt.as_ref().map(|prompt| prompt.line);
+        self.commands
+            .iter()
+            .map(|command| command.prompt)
+            .chain(pending)
+    }
+
+    /// Drop commands whose prompt has left history (before line `first`)
+    pub(crate) fn prune(&mut self, first: usize) {
+        while self
+            .commands
+            .front()
+            .is_some_and(|command| command.prompt < first)
+        {
+            self.commands.pop_front();
+        }
+        self.prompt.take_if(|prompt| prompt.line < first);
+    }
+
+    /// Drop every command, e.g. when the terminal is reset
+    pub(crate) fn clear(&mut self) {
+        self.commands.clear();
+        self.prompt = None;
+    }
+
+    /// Every line a record refers to
+    pub(crate) fn lines(&self) -> Vec<usize> {
+        let mut lines = Vec::new();
+        for command in &self.commands {
+            lines.extend([command.prompt, command.output_start]);
+            lines.extend(command.output_end);
+        }
+        if let Some(prompt) = &self.prompt {
+            lines.push(prompt.line);
+            lines.extend(prompt.input.map(|(line, _)| line));
+        }
+        lines
+    }
+
+    /// Move records to the lines a resize put them on
+    ///
+    /// Commands whose prompt was lost are dropped; lost output lines fall
+    /// back to the nearest line kept.
+    pub(crate) fn relocate(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        self.commands.retain_mut(|command| {
+            let Some(prompt) = moved(command.prompt) else {
+                return false;
+            };
+            command.prompt = prompt;
+            command.output_start = moved(command.output_start).unwrap_or(prompt + 1);
+            command.output_end = command
+                .output_end
+                .map(|end| moved(end).unwrap_or(command.output_start));
+            true
+        });
+        if let Some(prompt) = self.prompt.as_mut() {
+            match moved(prompt.line) {
+                Some(line) => {
+                    prompt.line = line;
+                    prompt.input = prompt
+                        .input
+                        .and_then(|(line, column)| Some((moved(line)?, column)));
+                }
+                None => self.prompt = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_each_command_that_runs() {
+        let mut log = CommandLog::default();
+        let start = Instant::now();
+        let cwd = FileUri::parse("file:///tmp");
+
+        log.prompt_start(0, cwd.clone(), start);
+        log.input_start(0, 2);
+        assert_eq!(log.input(), Some((0, 2)));
+        log.output_start("ls".into(), 1, start);
+        log.command_end(Some(0), 4, start + Duration::from_secs(2));
+
+        // An empty command line runs nothing
+        log.prompt_start(4, None, start);
+        log.input_start(4, 2);
+        log.prompt_start(5, None, start);
+
+        let commands: Vec<_> = log.iter().collect();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].id, 1);
+        assert_eq!(commands[0].command, "ls");
+        assert_eq!(commands[0].cwd, cwd);
+        assert_eq!(commands[0].exit_code, Some(0));
+        assert_eq!(
+            (commands[0].output_start, commands[0].output_end),
+            (1, Some(4))
+        );
+        assert_eq!(commands[0].finished, Some(start + Duration::from_secs(2)));
+        assert_eq!(log.prompts().collect::<Vec<_>>(), vec![0, 5]);
+    }
+
+    #[test]
+    fn unfinished_command_ends_at_next_prompt() {
+        let mut log = CommandLog::default();
+        let start = Instant::now();
+        log.prompt_start(0, None, start);
+        log.output_start("sleep 1".into(), 1, start);
+        assert!(log.iter().next().unwrap().finished.is_none());
+
+        log.prompt_start(3, None, start);
+        let command = log.iter().next().unwrap();
+        assert_eq!(command.output_end, Some(3));
+        assert_eq!(command.exit_code, None);
+    }
+
+    #[test]
+    fn prune_and_relocate_follow_history() {
+        let mut log = CommandLog::default();
+        let start = Instant::now();
+        for prompt in [0, 10] {
+            log.prompt_start(prompt, None, start);
+            log.output_start("make".into(), prompt + 1, start);
+            log.command_end(Some(2), prompt + 5, start);
+        }
+
+        log.prune(1);
+        assert_eq!(log.lines(), vec![10, 11, 15]);
+
+        // The resize lost the output's last line
+        log.relocate(|line| (line < 15).then(|| line + 3));
+        let command = log.iter().next().unwrap();
+        assert_eq!((command.prompt, command.output_start), (13, 14));
+        assert_eq!(command.output_end, Some(14));
+    }
+}
@@ -0,0 +1,266 @@
+//! Folded command output
+//!
+//! Folding a finished command takes its output rows out of the grid and puts
+//! one summary row in their place, so everything that reads the grid (scroll,
+//! selection, search, rendering) sees the folded block as that single row.
+//! [`FoldMap`] keeps the rows taken out, keyed by the absolute line of the
+//! summary row (see [`crate::scrollback`]), until the block is unfolded.
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::Range;
+
+use alacritty_terminal::grid::{Dimensions, Grid, Row};
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::cell::{Cell, Flags};
+use alacritty_terminal::vte::ansi::Color;
+
+use crate::SemanticZone;
+
+/// A folded block of output
+#[derive(Debug)]
+pub(crate) struct Fold {
+    /// The output rows, replaced by the summary row
+    pub(crate) rows: Vec<Row<Cell>>,
+    /// Zones on the rows after the first, by offset from the first
+    pub(crate) zones: Vec<(usize, SemanticZone)>,
+    /// Text of the summary row
+    pub(crate) summary: String,
+    /// The summary row as written, to check it's still there on unfolding
+    pub(crate) summary_row: Row<Cell>,
+}
+
+impl Fold {
+    /// Rows hidden by the fold
+    pub(crate) fn hidden(&self) -> usize {
+        self.rows.len() - 1
+    }
+
+    /// Text of the folded rows, with wrapped rows joined
+    pub(crate) fn text(&self) -> String {
+        let mut text = String::new();
+        for row in &self.rows {
+            let wraps = row[Column(row.len() - 1)].flags.contains(Flags::WRAPLINE);
+            let start = text.len();
+            text.extend(
+                row.into_iter()
+                    .filter(|cell| !cell.flags.contains(Flags::WIDE_CHAR_SPACER))
+                    .map(|cell| cell.c),
+            );
+            if !wraps {
+                text.truncate(start + text[start..].trim_end().len());
+                text.push('\n');
+            }
+        }
+        text.truncate(text.trim_end_matches('\n').len());
+        text
+    }
+}
+
+/// Folded blocks keyed by the absolute line of their summary row
+#[derive(Debug, Default)]
+pub(crate) struct FoldMap {
+    folds: BTreeMap<usize, Fold>,
+}
+
+impl FoldMap {
+    pub(crate) fn insert(&mut self, line: usize, fold: Fold) {
+        self.folds.insert(line, fold);
+    }
+
+    pub(crate) fn remove(&mut self, line: usize) -> Option<Fold> {
+        self.folds.remove(&line)
+    }
+
+    pub(crate) fn get(&self, line: usize) -> Option<&Fold> {
+        self.folds.get(&line)
+    }
+
+    pub(crate) fn contains(&self, line: usize) -> bool {
+        self.folds.contains_key(&line)
+    }
+
+    /// Rows hidden by every fold
+    pub(crate) fn hidden(&self) -> usize {
+        self.folds.values().map(Fold::hidden).sum()
+    }
+
+    /// Lines of the summary rows
+    pub(crate) fn lines(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.folds.keys().copied()
+    }
+
+    /// Drop folds whose summary row has left history (before line `first`)
+    pub(crate) fn prune(&mut self, first: usize) {
+        self.folds = self.folds.split_off(&first);
+    }
+
+    /// Drop every fold, e.g. when the terminal is reset
+    pub(crate) fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Move folds to the lines their summary rows moved to
+    pub(crate) fn relocate(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        self.folds = mem::take(&mut self.folds)
+            .into_iter()
+            .filter_map(|(line, fold)| Some((moved(line)?, fold)))
+            .collect();
+    }
+}
+
+/// A row showing `text`, one character per cell, cut to the grid's width
+pub(crate) fn summary_row(text: &str, columns: usize) -> Row<Cell> {
+    let mut row: Row<Cell> = Row::new(columns);
+    for (column, c) in text.chars().take(columns).enumerate() {
+        row[Column(column)].c = c;
+    }
+    row
+}
+
+/// Replace grid `lines` with `summary`, returning the rows taken out and how
+/// far history shrank
+///
+/// The rows above move down over the folded ones, so the summary row and
+/// everything below it stay on their lines. History gives up the blank rows
+/// this leaves at the top, as far as it has them; the scroll limit is left
+/// at the new history size for the caller to raise again.
+pub(crate) fn collapse(
+    grid: &mut Grid<Cell>,
+    lines: Range<Line>,
+    summary: Row<Cell>,
+) -> (Vec<Row<Cell>>, usize) {
+    let columns = grid.columns();
+    let hidden = (lines.end - lines.start).0 as usize - 1;
+    let rows = (lines.start.0..lines.end.0)
+        .map(|line| mem::replace(&mut grid[Line(line)], Row::new(columns)))
+        .collect();
+
+    for line in (grid.topmost_line().0..lines.start.0).rev() {
+        let row = mem::replace(&mut grid[Line(line)], Row::new(columns));
+        grid[Line(line + hidden as i32)] = row;
+    }
+    grid[lines.end - 1] = summary;
+
+    let history = grid.history_size();
+    let shrink = hidden.min(history);
+    grid.update_history(history - shrink);
+    (rows, shrink)
+}
+
+/// Put folded `rows` back in place of the summary row on `line`
+///
+/// The rows above move up into history to make room, and the rows below
+/// stay where they are. The scroll limit is raised as far as history grows;
+/// the caller trims it back.
+pub(crate) fn expand(grid: &mut Grid<Cell>, line: Line, rows: Vec<Row<Cell>>) {
+    let hidden = rows.len() - 1;
+    grid.update_history(grid.history_size() + hidden);
+
+    // Scroll everything up, then bring the rows below the summary back down
+    let screen = Line(0)..Line(grid.screen_lines() as i32);
+    grid.scroll_up::<Color>(&screen, hidden);
+    let columns = grid.columns();
+    for below in (line.0 + 1..=grid.bottommost_line().0).rev() {
+        let row = mem::replace(&mut grid[Line(below - hidden as i32)], Row::new(columns));
+        grid[Line(below)] = row;
+    }
+
+    let top = line - hidden;
+    for (offset, row) in rows.into_iter().enumerate() {
+        grid[top + offset] = row;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(grid: &mut Grid<Cell>, line: i32, text: &str) {
+        grid[Line(line)] = summary_row(text, grid.columns());
+    }
+
+    fn text(grid: &Grid<Cell>, line: i32) -> String {
+        let row = &grid[Line(line)];
+        let text: String = row.into_iter().map(|cell| cell.c).collect();
+        text.trim_end().to_string()
+    }
+
+    /// A grid with `history` lines of history, each line showing its number
+    fn numbered(history: usize) -> Grid<Cell> {
+        let mut grid: Grid<Cell> = Grid::new(4, 10, 100);
+        let screen = Line(0)..Line(4);
+        grid.scroll_up::<Color>(&screen, history);
+        for line in grid.topmost_line().0..4 {
+            write(&mut grid, line, &line.to_string());
+        }
+        grid
+    }
+
+    #[test]
+    fn collapse_and_expand_restore_the_grid() {
+        let mut grid = numbered(6);
+        let summary = summary_row("3 lines", 10);
+
+        let (rows, shrink) = collapse(&mut grid, Line(-3)..Line(0), summary);
+        assert_eq!((rows.len(), shrink), (3, 2));
+        assert_eq!(grid.history_size(), 4);
+        // The summary takes the last folded row's line; the rest close up
+        let lines: Vec<_> = (-4..4).map(|line| text(&grid, line)).collect();
+        assert_eq!(lines, ["-6", "-5", "-4", "3 lines", "0", "1", "2", "3"]);
+
+        expand(&mut grid, Line(-1), rows);
+        assert_eq!(grid.history_size(), 6);
+        for line in -6..4 {
+            assert_eq!(text(&grid, line), line.to_string());
+        }
+    }
+
+    #[test]
+    fn collapse_without_history_leaves_blank_rows_on_top() {
+        let mut grid = numbered(0);
+        let (rows, shrink) = collapse(&mut grid, Line(0)..Line(3), summary_row("3 lines", 10));
+        assert_eq!(shrink, 0);
+        let lines: Vec<_> = (0..4).map(|line| text(&grid, line)).collect();
+        assert_eq!(lines, ["", "", "3 lines", "3"]);
+
+        expand(&mut grid, Line(2), rows);
+        let lines: Vec<_> = (-2..4).map(|line| text(&grid, line)).collect();
+        assert_eq!(lines, ["", "", "0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn fold_text_joins_wrapped_rows() {
+        let mut rows = vec![summary_row("long line", 10), summary_row("wraps", 10)];
+        rows[0][Column(9)].flags.insert(Flags::WRAPLINE);
+        rows.push(summary_row("end", 10));
+        let fold = Fold {
+            rows,
+            zones: Vec::new(),
+            summary: String::new(),
+            summary_row: summary_row("", 10),
+        };
+        assert_eq!(fold.hidden(), 2);
+        assert_eq!(fold.text(), "long line wraps\nend");
+    }
+
+    #[test]
+    fn relocate_and_prune_follow_summary_rows() {
+        let fold = || Fold {
+            rows: vec![summary_row("a", 4), summary_row("b", 4)],
+            zones: Vec::new(),
+            summary: String::new(),
+            summary_row: summary_row("", 4),
+        };
+        let mut folds = FoldMap::default();
+        folds.insert(3, fold());
+        folds.insert(8, fold());
+        assert_eq!(folds.hidden(), 2);
+
+        folds.relocate(|line| Some(line + 2));
+        assert_eq!(folds.lines().collect::<Vec<_>>(), vec![5, 10]);
+        folds.prune(6);
+        assert_eq!(folds.lines().collect::<Vec<_>>(), vec![10]);
+        assert!(folds.contains(10) && folds.get(5).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! Kitty keyboard mode stack guard
+//!
+//! Programs opt in to the kitty keyboard protocol by pushing enhancement
+//! flags (`CSI > flags u`) onto a per-screen stack and popping them
+//! (`CSI < n u`) on exit. alacritty_terminal caps each stack at 4096 entries
+//! but evicts from the *title* stack when one is full, which panics unless a
+//! title was pushed first, so a program that pushes in a loop would take the
+//! parser (and the terminal lock with it) down.
+//!
+//! [`KeyboardStackGuard`] tracks each screen's stack depth from the
+//! sequences the terminal's [`crate::scanner::Scanner`] finds, and has pushes
+//! past [`MAX_DEPTH`] cancelled before the parser sees them.
+
+use crate::scanner::Csi;
+
+/// Deepest a keyboard mode stack may grow (kitty itself keeps 8 entries)
+const MAX_DEPTH: usize = 256;
+
+/// Tracks the kitty keyboard mode stacks and caps their depth
+#[derive(Debug, Default)]
+pub(crate) struct KeyboardStackGuard {
+    /// Stack depth for the primary and alternate screen
+    depth: [usize; 2],
+    alt_screen: bool,
+}
+
+impl KeyboardStackGuard {
+    /// Apply a finished sequence; returns false if it must be cancelled
+    pub(crate) fn apply(&mut self, csi: &Csi) -> bool {
+        let depth = &mut self.depth[usize::from(self.alt_screen)];
+        let alt_screen_mode = csi.params().contains(&1049);
+        match (csi.marker, csi.action) {
+            (Some(b'>'), b'u') => {
+                if *depth >= MAX_DEPTH {
+                    log::debug!("Kitty keyboard mode stack full; dropping push");
+                    return false;
+                }
+                *depth += 1;
+            }
+            (Some(b'<'), b'u') => {
+                let count = match csi.first() {
+                    0 => 1,
+                    count => usize::from(count),
+                };
+                *depth = depth.saturating_sub(count);
+            }
+            (Some(b'?'), b'h') if alt_screen_mode => self.alt_screen = true,
+            (Some(b'?'), b'l') if alt_screen_mode => self.alt_screen = false,
+            _ => {}
+        }
+        true
+    }
+
+    /// RIS clears both stacks and leaves the alternate screen
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{MAX_PARAMS, Scanner, Sequence};
+
+    /// Scan `bytes`, returning how many pushes were cancelled
+    fn feed(guard: &mut KeyboardStackGuard, scanner: &mut Scanner, bytes: &[u8]) -> usize {
+        let mut cancelled = 0;
+        for &byte in bytes {
+            match scanner.advance(byte) {
+                Some(Sequence::Csi(csi)) if !guard.apply(csi) => cancelled += 1,
+                Some(Sequence::Reset) => guard.reset(),
+                _ => {}
+            }
+        }
+        cancelled
+    }
+
+    fn scan(bytes: &[u8]) -> KeyboardStackGuard {
+        let mut guard = KeyboardStackGuard::default();
+        feed(&mut guard, &mut Scanner::default(), bytes);
+        guard
+    }
+
+    #[test]
+    fn tracks_pushes_and_pops_per_screen() {
+        assert_eq!(scan(b"\x1b[>1u\x1b[>3u").depth, [2, 0]);
+        assert_eq!(
+            scan(b"\x1b[>1u\x1b[>3u\x1b[?1049h\x1b[>1u\x1b[<u").depth,
+            [2, 0]
+        );
+        assert_eq!(
+            scan(b"\x1b[>1u\x1b[>3u\x1b[?1049h\x1b[?1049l\x1b[<5u").depth,
+            [0, 0]
+        );
+    }
+
+    #[test]
+    fn ignores_other_sequences() {
+        // Query, set, an intermediate, and a push vte itself would ignore
+        let too_many_params = format!("\x1b[>{}u", "1;".repeat(MAX_PARAMS));
+        assert_eq!(scan(b"\x1b[?u\x1b[=1;1u\x1b[> u\x1b[1>u").depth, [0, 0]);
+        assert_eq!(scan(too_many_params.as_bytes()).depth, [0, 0]);
+    }
+
+    #[test]
+    fn reset_clears_stacks() {
+        assert_eq!(
+            scan(b"\x1b[>1u\x1b[?1049h\x1b[>1u\x1bc\x1b[>1u").depth,
+            [1, 0]
+        );
+    }
+
+    #[test]
+    fn cancels_pushes_past_limit() {
+        let mut guard = KeyboardStackGuard::default();
+        let mut scanner = Scanner::default();
+        let pushes = b"\x1b[>1u".repeat(MAX_DEPTH);
+        assert_eq!(feed(&mut guard, &mut scanner, &pushes), 0);
+
+        assert_eq!(feed(&mut guard, &mut scanner, b"\x1b[>1u"), 1);
+        assert_eq!(guard.depth, [MAX_DEPTH, 0]);
+
+        // The other screen has a stack of its own
+        assert_eq!(feed(&mut guard, &mut scanner, b"\x1b[?1049h\x1b[>1u"), 0);
+        assert_eq!(guard.depth, [MAX_DEPTH, 1]);
+    }
+}
@@ -5,9 +5,24 @@
 //! - ANSI escape sequence parsing (via vte)
 //! - PTY process management (via portable-pty)
 
+mod commands;
+mod folds;
+mod keyboard_stack;
+mod mouse_modes;
+mod osc133;
 pub mod pty;
-
-pub use pty::{Pty, PtyBackend, ShellType, SpawnOptions};
+mod scanner;
+mod scrollback;
+mod search;
+mod worker;
+mod zones;
+
+pub use commands::CommandRecord;
+pub use mouse_modes::MouseModes;
+pub use osc133::{ClickMove, PromptKind, PromptOptions};
+pub use pty::{ForegroundProcess, Pty, PtyBackend, PtyOutput, ShellType, SpawnOptions};
+pub use search::{Search, SearchOptions};
+pub use worker::{ParserWorker, Wakeup};
 
 // Re-export alacritty_terminal types needed for rendering
 pub use alacritty_terminal::event::Event as TerminalEvent;
@@ -15,7 +30,7 @@ pub use alacritty_terminal::grid::Scroll;
 pub use alacritty_terminal::index::Side;
 pub use alacritty_terminal::index::{Column, Line, Point};
 pub use alacritty_terminal::selection::{Selection, SelectionRange, SelectionType};
-pub use alacritty_terminal::term::TermMode;
+pub use alacritty_terminal::term::{ClipboardType, TermMode};
 pub use alacritty_terminal::term::{
     LineDamageBounds, RenderableContent, RenderableCursor, TermDamage,
     cell::Cell,
@@ -25,13 +40,24 @@ pub use alacritty_terminal::term::{
 pub use alacritty_terminal::vte::ansi::Color as AnsiColor;
 pub use alacritty_terminal::vte::ansi::CursorShape;
 pub use alacritty_terminal::vte::ansi::NamedColor;
+pub use alacritty_terminal::vte::ansi::Rgb;
 
-use std::collections::BTreeMap;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::ops::{Range, RangeInclusive};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
 
 use alacritty_terminal::event::{Event, EventListener};
 use crossbeam_queue::SegQueue;
 
+use crate::commands::CommandLog;
+use crate::folds::{Fold, FoldMap};
+use crate::keyboard_stack::KeyboardStackGuard;
+use crate::osc133::PromptMark;
+use crate::scanner::{CAN, Csi, Scanner, Sequence};
+use crate::scrollback::Scrollback;
+use crate::zones::ZoneMap;
+
 /// Semantic zone type from OSC 133 shell integration
 ///
 /// OSC 133 sequences mark boundaries between prompt, input, and output regions.
@@ -62,9 +88,98 @@ pub enum ShellEvent {
     /// Command failed (OSC 133;D with non-zero exit code)
     CommandFail(i32),
 }
+/// Clipboard access requested by a program through OSC 52
+///
+/// The terminal accepts both directions; the application decides whether a
+/// request is honored based on its clipboard policy.
+pub enum ClipboardRequest {
+    /// Program wants to place `text` on the clipboard (`OSC 52 ; c ; <base64>`)
+    Store {
+        clipboard: ClipboardType,
+        text: String,
+    },
+    /// Program wants to read the clipboard (`OSC 52 ; c ; ?`)
+    ///
+    /// `formatter` turns the clipboard text into the OSC 52 reply to write
+    /// back to the PTY.
+    Load {
+        clipboard: ClipboardType,
+        formatter: Arc<dyn Fn(&str) -> String + Sync + Send>,
+    },
+}
+
+impl std::fmt::Debug for ClipboardRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Store { clipboard, text } => f
+                .debug_struct("Store")
+                .field("clipboard", clipboard)
+                .field("len", &text.len())
+                .finish(),
+            Self::Load { clipboard, .. } => f
+                .debug_struct("Load")
+                .field("clipboard", clipboard)
+                .finish(),
+        }
+    }
+}
+
+/// A `file://host/path` location, as reported by OSC 7
+///
+/// Shells report their working directory this way after every `cd`; the host
+/// tells us whether the path is on this machine or at the far end of ssh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileUri {
+    /// Host name, or `None` when the URI has an empty host (`file:///path`)
+    pub host: Option<String>,
+    /// Percent-decoded path
+    pub path: std::path::PathBuf,
+}
+
+impl FileUri {
+    /// Parse a `file://host/path` URI
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("file://")?;
+        let slash = rest.find('/')?;
+        let (host, path) = rest.split_at(slash);
+        Some(Self {
+            host: (!host.is_empty()).then(|| host.to_string()),
+            path: percent_decode(path).into(),
+        })
+    }
+
+    /// Whether the path is on this machine (no host, `localhost`, or `local_host`)
+    pub fn is_local(&self, local_host: Option<&str>) -> bool {
+        match self.host.as_deref() {
+            None | Some("localhost") => true,
+            Some(host) => Some(host) == local_host,
+        }
+    }
+}
+
+/// Decode `%XX` escapes, leaving malformed escapes as-is
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = text.get(i + 1..i + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 use alacritty_terminal::grid::Dimensions;
 use alacritty_terminal::term::test::TermSize;
-use alacritty_terminal::term::{self, Config as TermConfig, Term};
+use alacritty_terminal::term::{self, Config as TermConfig, Osc52, Term};
 use alacritty_terminal::vte::ansi;
 
 /// Terminal event handler that collects events for the application.
@@ -105,38 +220,124 @@ impl EventListener for TerminalEventProxy {
     }
 }
 
-/// Terminal size in characters
-#[derive(Debug, Clone, Copy)]
+/// Terminal size in characters, with the cell size in pixels once known
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Size {
     pub columns: usize,
     pub lines: usize,
+    /// Cell width in pixels, as the font measures it (0 if unknown)
+    pub cell_width: f32,
+    /// Cell height in pixels, as the font measures it (0 if unknown)
+    pub cell_height: f32,
 }
 
 impl Size {
     pub fn new(columns: usize, lines: usize) -> Self {
-        Self { columns, lines }
+        Self {
+            columns,
+            lines,
+            cell_width: 0.0,
+            cell_height: 0.0,
+        }
+    }
+
+    /// Set the cell size from the font's metrics
+    pub fn with_cell_size(self, cell_width: f32, cell_height: f32) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            ..self
+        }
+    }
+
+    /// Width of the text area in pixels
+    pub fn pixel_width(&self) -> u16 {
+        pixels(self.columns, self.cell_width)
+    }
+
+    /// Height of the text area in pixels
+    pub fn pixel_height(&self) -> u16 {
+        pixels(self.lines, self.cell_height)
+    }
+}
+
+/// Pixels spanned by `cells` cells, rounded once so fractional cell sizes
+/// don't add up to a rounding error per cell (saturates at `u16::MAX`)
+fn pixels(cells: usize, cell_size: f32) -> u16 {
+    (cells as f32 * cell_size).round() as u16
+}
+
+/// Check for the erase that clears the scrollback (`CSI 3 J`)
+fn clears_history(csi: &Csi) -> bool {
+    csi.marker.is_none() && csi.action == b'J' && csi.first() == 3
+}
+
+/// Check for the xterm window op asking for the cell size (`CSI 16 t`)
+fn is_cell_size_query(csi: &Csi) -> bool {
+    csi.marker.is_none() && csi.action == b't' && csi.first() == 16
+}
+
+/// Lines a scroll up (`CSI S`) moves into history
+fn scrolled_lines(csi: &Csi) -> usize {
+    match (csi.marker, csi.action) {
+        (None, b'S') => usize::from(csi.first().max(1)),
+        _ => 0,
     }
 }
 
+/// Lines of scrollback kept
+const SCROLLBACK_LINES: usize = 10_000;
+
+/// How far history may grow past [`SCROLLBACK_LINES`] before it's trimmed
+///
+/// Output that could add more lines than this (counting line feeds, text
+/// that may wrap, and scrolls) is parsed in more than one piece.
+const HISTORY_SLACK: usize = 1024;
+
 /// CRT Terminal wrapper around alacritty_terminal
 pub struct Terminal {
     term: Term<TerminalEventProxy>,
     event_proxy: TerminalEventProxy,
     parser: ansi::Processor,
     size: Size,
-    /// Semantic zones per line (from OSC 133)
-    /// Maps line number (can be negative for scrollback) to zone type
-    line_zones: BTreeMap<i32, SemanticZone>,
+    /// Numbers lines so zones and commands stay with them as they scroll
+    scrollback: Scrollback,
+    /// Semantic zones per absolute line (from OSC 133)
+    zones: ZoneMap,
+    /// Commands the shell ran (from OSC 133)
+    commands: CommandLog,
+    /// Command output folded into summary rows
+    folds: FoldMap,
+    /// Times lines moved other than by scrolling (resize, folds, reset),
+    /// which sends searches back to the start
+    relayouts: u64,
     /// Current semantic zone state (for marking new content)
     current_zone: SemanticZone,
+    /// Options of the latest prompt start (OSC 133;A)
+    prompt_options: Option<PromptOptions>,
     /// Pending shell events for theme triggers (bell, command success/fail)
     pending_shell_events: Vec<ShellEvent>,
+    /// Last working directory reported via OSC 7
+    reported_cwd: Option<FileUri>,
+    /// Caps the kitty keyboard mode stacks before they reach the parser
+    keyboard_stack: KeyboardStackGuard,
+    /// Finds the sequences handled beside the parser
+    scanner: Scanner,
+    /// Mouse modes the parser doesn't track
+    mouse_modes: MouseModes,
 }
 
 impl Terminal {
     /// Create a new terminal with the given size
     pub fn new(size: Size) -> Self {
-        let config = TermConfig::default();
+        // Accept OSC 52 in both directions; the application applies its own policy.
+        // Programs can opt in to the kitty keyboard protocol (CSI > flags u)
+        let config = TermConfig {
+            osc52: Osc52::CopyPaste,
+            kitty_keyboard: true,
+            scrolling_history: SCROLLBACK_LINES + HISTORY_SLACK,
+            ..TermConfig::default()
+        };
         let term_size = TermSize::new(size.columns, size.lines);
         let event_proxy = TerminalEventProxy::new();
         let term = Term::new(config, &term_size, event_proxy.clone());
@@ -147,9 +348,18 @@ impl Terminal {
             event_proxy,
             parser,
             size,
-            line_zones: BTreeMap::new(),
+            scrollback: Scrollback::default(),
+            zones: ZoneMap::default(),
+            commands: CommandLog::default(),
+            folds: FoldMap::default(),
+            relayouts: 0,
             current_zone: SemanticZone::Unknown,
+            prompt_options: None,
             pending_shell_events: Vec::new(),
+            reported_cwd: None,
+            keyboard_stack: KeyboardStackGuard::default(),
+            scanner: Scanner::default(),
+            mouse_modes: MouseModes::default(),
         }
     }
 
@@ -173,16 +383,180 @@ impl Terminal {
     /// Selection is preserved across output processing to support copy/paste
     /// during active shell output (e.g., during builds, long-running commands).
     pub fn process_input(&mut self, bytes: &[u8]) {
-        // Scan for OSC 133 sequences before passing to parser
-        self.scan_osc133(bytes);
+        // An update the program never ended doesn't swallow what comes next
+        self.end_expired_sync_update();
+
+        // Pass through to the terminal parser in one piece, except where the
+        // terminal has to act at a point in the stream: at each OSC 133 mark,
+        // so it's placed where the cursor is then; around each history clear,
+        // so the lines it drops are counted; after a cell size query, so the
+        // reply follows those before it; and before history could overrun
+        // the slack between trims. Budgeted in cells, a line feed or scrolled
+        // line costs a whole row and anything else one cell it may wrap into.
+        let columns = self.term.columns();
+        let budget = HISTORY_SLACK * columns;
+        let mut scanner = std::mem::take(&mut self.scanner);
+        let mut parsed = 0;
+        let mut spent = 0;
+        let mut index = 0;
+        while index < bytes.len() {
+            // Text between sequences only needs its cost counted
+            let plain = &bytes[index..index + scanner.plain_len(&bytes[index..])];
+            if !plain.is_empty() {
+                let line_feeds = plain.iter().filter(|&&byte| byte == b'\n').count();
+                let cost = plain.len() + line_feeds * (columns - 1);
+                if spent + cost <= budget {
+                    spent += cost;
+                } else {
+                    for (offset, &byte) in plain.iter().enumerate() {
+                        let cost = if byte == b'\n' { columns } else { 1 };
+                        if spent + cost > budget {
+                            self.parse_bytes(&bytes[parsed..index + offset]);
+                            parsed = index + offset;
+                            spent = 0;
+                        }
+                        spent += cost;
+                    }
+                }
+                index += plain.len();
+                continue;
+            }
+
+            let byte = bytes[index];
+            let mut cost = if byte == b'\n' { columns } else { 1 };
+            let split = match scanner.advance(byte) {
+                None => false,
+                Some(Sequence::Csi(csi)) if !self.keyboard_stack.apply(csi) => {
+                    // The parser sees the push cancelled in place of its final byte
+                    self.parse_bytes(&bytes[parsed..index]);
+                    self.parse_bytes(&[CAN]);
+                    true
+                }
+                Some(Sequence::Csi(csi)) if is_cell_size_query(csi) => {
+                    self.parse_bytes(&bytes[parsed..=index]);
+                    self.reply_cell_size();
+                    true
+                }
+                Some(Sequence::Csi(csi)) if clears_history(csi) => {
+                    self.parse_bytes(&bytes[parsed..index]);
+                    self.parse_bytes(&bytes[index..=index]);
+                    true
+                }
+                Some(Sequence::Csi(csi)) => {
+                    self.mouse_modes.apply(csi);
+                    cost += scrolled_lines(csi).min(self.term.screen_lines()) * columns;
+                    false
+                }
+                Some(Sequence::Reset) => {
+                    self.parse_bytes(&bytes[parsed..index]);
+                    self.reset_state();
+                    self.parse_bytes(&bytes[index..=index]);
+                    true
+                }
+                Some(Sequence::Osc(osc)) => match osc133::parse(osc) {
+                    Some(mark) => {
+                        self.parse_bytes(&bytes[parsed..=index]);
+                        self.handle_osc133(mark);
+                        true
+                    }
+                    None => {
+                        if let Some(uri) = osc.strip_prefix(b"7;") {
+                            self.handle_osc7(uri);
+                        }
+                        false
+                    }
+                },
+            };
+            if split {
+                parsed = index + 1;
+                spent = 0;
+            } else {
+                if spent + cost > budget {
+                    self.parse_bytes(&bytes[parsed..index]);
+                    parsed = index;
+                    spent = 0;
+                }
+                spent += cost;
+            }
+            index += 1;
+        }
+        self.parse_bytes(&bytes[parsed..]);
+        self.scanner = scanner;
+    }
+
+    fn parse_bytes(&mut self, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            self.run_parser(|parser, term| parser.advance(term, bytes));
+        }
+    }
+
+    /// Run the parser, following the lines it moves out of history
+    fn run_parser(
+        &mut self,
+        parse: impl FnOnce(&mut ansi::Processor, &mut Term<TerminalEventProxy>),
+    ) {
+        let alt_screen = self.in_alt_screen();
+        let history = self.term.history_size();
+        self.parse_preserving_selection(parse);
+
+        // Zones belong to the primary screen; its history is out of reach
+        // while the alternate screen is up
+        if !alt_screen && !self.in_alt_screen() {
+            // Output only shrinks history by clearing it
+            self.evict(history.saturating_sub(self.term.history_size()));
+            self.trim_history();
+        }
+    }
+
+    /// Trim history back to [`SCROLLBACK_LINES`], dropping zones on the lines trimmed
+    ///
+    /// alacritty_terminal drops the oldest line without a word once history
+    /// is full, which would leave zones keyed to the wrong lines. Its limit
+    /// is set [`HISTORY_SLACK`] higher, and the excess trimmed here instead.
+    /// Rows hidden by folds count toward the limit, so folding output makes
+    /// no room for more.
+    fn trim_history(&mut self) {
+        let limit = SCROLLBACK_LINES.saturating_sub(self.folds.hidden());
+        let excess = self.term.history_size().saturating_sub(limit);
+        if excess > 0 {
+            let grid = self.term.grid_mut();
+            grid.update_history(limit);
+            grid.update_history(SCROLLBACK_LINES + HISTORY_SLACK);
+            self.evict(excess);
+        }
+    }
+
+    /// Forget zones, commands and folds on `lines` lines that left the top of history
+    fn evict(&mut self, lines: usize) {
+        if lines > 0 {
+            self.scrollback.evict(lines);
+            self.zones.prune(self.scrollback.first());
+            self.commands.prune(self.scrollback.first());
+            // Folds go with their command, whose prompt is above them
+            let first_output = self
+                .commands
+                .iter()
+                .next()
+                .map_or(usize::MAX, |command| command.output_start);
+            self.folds.prune(first_output);
+        }
+    }
 
-        // Preserve selection across output processing
-        // Alacritty_terminal clears selection when lines are cleared or screen is modified,
-        // but we want to keep it for copy/paste convenience
+    fn in_alt_screen(&self) -> bool {
+        self.term.mode().contains(TermMode::ALT_SCREEN)
+    }
+
+    /// Run the parser without losing the selection
+    ///
+    /// Alacritty_terminal clears selection when lines are cleared or screen is modified,
+    /// but we want to keep it for copy/paste convenience
+    fn parse_preserving_selection(
+        &mut self,
+        parse: impl FnOnce(&mut ansi::Processor, &mut Term<TerminalEventProxy>),
+    ) {
         let saved_selection = self.term.selection.clone();
 
-        // Pass through to terminal parser unchanged
-        self.parser.advance(&mut self.term, bytes);
+        parse(&mut self.parser, &mut self.term);
 
         // Restore selection if it was cleared during processing
         // Only restore if we had a selection and it was cleared
@@ -191,103 +565,124 @@ impl Terminal {
         }
     }
 
-    /// Scan input bytes for OSC 133 semantic prompt sequences
+    /// Deadline of the synchronized update (DEC mode 2026) in progress
     ///
-    /// OSC 133 format: `\x1b]133;X\x07` or `\x1b]133;X\x1b\\`
-    /// Where X is: A (prompt start), B (command start), C (output start), D (output end)
-    /// For D, may include exit code: `\x1b]133;D;exitcode\x07`
-    fn scan_osc133(&mut self, bytes: &[u8]) {
-        // OSC starts with \x1b] (ESC ])
-        let mut i = 0;
-        while i < bytes.len() {
-            // Look for ESC ]
-            if bytes[i] == 0x1b && i + 1 < bytes.len() && bytes[i + 1] == b']' {
-                // Check for "133;" pattern
-                if i + 6 < bytes.len()
-                    && bytes[i + 2] == b'1'
-                    && bytes[i + 3] == b'3'
-                    && bytes[i + 4] == b'3'
-                    && bytes[i + 5] == b';'
-                {
-                    let cmd = bytes[i + 6];
-
-                    // For D command, try to parse exit code (format: D;exitcode)
-                    let exit_code = if cmd == b'D' && i + 8 < bytes.len() && bytes[i + 7] == b';' {
-                        // Find terminator and parse exit code
-                        let mut end = i + 8;
-                        while end < bytes.len()
-                            && bytes[end] != 0x07
-                            && bytes[end] != 0x1b
-                            && bytes[end].is_ascii_digit()
-                        {
-                            end += 1;
-                        }
-                        if end > i + 8 {
-                            std::str::from_utf8(&bytes[i + 8..end])
-                                .ok()
-                                .and_then(|s| s.parse::<i32>().ok())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-
-                    // Check for valid terminator anywhere after command
-                    // Scan forward to find BEL or ST
-                    let mut term_pos = i + 7;
-                    let mut has_terminator = false;
-                    while term_pos < bytes.len() && term_pos < i + 20 {
-                        // limit search
-                        if bytes[term_pos] == 0x07 {
-                            has_terminator = true;
-                            break;
-                        }
-                        if bytes[term_pos] == 0x1b
-                            && term_pos + 1 < bytes.len()
-                            && bytes[term_pos + 1] == b'\\'
-                        {
-                            has_terminator = true;
-                            break;
-                        }
-                        term_pos += 1;
-                    }
+    /// Between `CSI ? 2026 h` and `CSI ? 2026 l` the parser buffers output
+    /// instead of applying it, so the screen changes all at once and is never
+    /// drawn half-updated. An update still open at its deadline is applied
+    /// anyway by `end_expired_sync_update`.
+    pub fn sync_update_deadline(&self) -> Option<Instant> {
+        self.parser.sync_timeout().sync_timeout()
+    }
 
-                    if has_terminator {
-                        self.handle_osc133(cmd, exit_code);
-                    }
-                }
-            }
-            i += 1;
+    /// Check if a synchronized update is in progress
+    pub fn in_sync_update(&self) -> bool {
+        self.sync_update_deadline().is_some()
+    }
+
+    /// Apply the synchronized update in progress if its deadline has passed
+    ///
+    /// Returns true if an update was applied.
+    pub fn end_expired_sync_update(&mut self) -> bool {
+        let expired = self
+            .sync_update_deadline()
+            .is_some_and(|deadline| deadline <= Instant::now());
+        if expired {
+            log::debug!("Synchronized update timed out, applying buffered output");
+            self.end_sync_update();
+        }
+        expired
+    }
+
+    /// Apply any buffered synchronized update output now
+    pub fn end_sync_update(&mut self) {
+        if self.in_sync_update() {
+            self.run_parser(|parser, term| parser.stop_sync(term));
         }
     }
 
-    /// Handle an OSC 133 command
-    fn handle_osc133(&mut self, cmd: u8, exit_code: Option<i32>) {
-        // Get current cursor line from terminal
-        let cursor = self.term.renderable_content().cursor;
-        let line = cursor.point.line.0;
+    /// Answer the cell size query (`CSI 16 t`) with the size from the last resize
+    fn reply_cell_size(&self) {
+        let cell_width = self.size.cell_width.round() as u16;
+        let cell_height = self.size.cell_height.round() as u16;
+        let reply = format!("\x1b[6;{cell_height};{cell_width}t");
+        self.event_proxy.send_event(Event::PtyWrite(reply));
+    }
+
+    /// Forget what RIS resets beside the parser; the screen goes with the history
+    fn reset_state(&mut self) {
+        self.keyboard_stack.reset();
+        self.mouse_modes = MouseModes::default();
+        self.zones.clear();
+        self.commands.clear();
+        self.folds.clear();
+        self.relayouts += 1;
+    }
+
+    /// Handle an OSC 7 working directory report (`OSC 7 ; file://host/path ST`)
+    ///
+    /// The VTE parser ignores OSC 7. Reports are taken in order with OSC 133
+    /// marks, so each prompt records the directory reported before it.
+    fn handle_osc7(&mut self, uri: &[u8]) {
+        let Ok(uri) = std::str::from_utf8(uri) else {
+            return;
+        };
+        match FileUri::parse(uri) {
+            Some(cwd) => {
+                log::debug!("OSC 7: working directory {:?}", cwd);
+                self.reported_cwd = Some(cwd);
+            }
+            None => log::debug!("OSC 7: ignoring malformed URI {:?}", uri),
+        }
+    }
 
-        match cmd {
-            b'A' => {
-                // Prompt start
-                self.current_zone = SemanticZone::Prompt;
-                self.line_zones.insert(line, SemanticZone::Prompt);
-                log::debug!("OSC 133;A: Prompt start at line {}", line);
+    /// Handle an OSC 133 mark
+    fn handle_osc133(&mut self, mark: PromptMark) {
+        let cursor = self.term.grid().cursor.point;
+        let line = cursor.line.0;
+        // Commands are only followed on the primary screen
+        let absolute = self.cursor_line();
+        let now = Instant::now();
+
+        match mark {
+            PromptMark::PromptStart(options) => {
+                self.mark_zone(SemanticZone::Prompt);
+                if let Some(absolute) = absolute {
+                    let cwd = self.reported_cwd.clone();
+                    self.commands.prompt_start(absolute, cwd, now);
+                }
+                log::debug!("OSC 133;A: Prompt start at line {} {:?}", line, options);
+                self.prompt_options = Some(options);
+            }
+            // A right prompt shares its line with the input
+            PromptMark::PromptLine(options) if options.kind == PromptKind::Right => {}
+            PromptMark::PromptLine(options) => {
+                self.mark_zone(SemanticZone::Prompt);
+                log::debug!("OSC 133;P: {:?} prompt at line {}", options.kind, line);
+            }
+            PromptMark::FreshLine => {
+                if cursor.column.0 != 0 {
+                    self.run_parser(|parser, term| parser.advance(term, b"\r\n"));
+                }
             }
-            b'B' => {
+            PromptMark::InputStart => {
                 // Command start (end of prompt, user input begins)
-                self.current_zone = SemanticZone::Input;
-                self.line_zones.insert(line, SemanticZone::Input);
+                self.mark_zone(SemanticZone::Input);
+                if let Some(absolute) = absolute {
+                    self.commands.input_start(absolute, cursor.column.0);
+                }
                 log::debug!("OSC 133;B: Input start at line {}", line);
             }
-            b'C' => {
+            PromptMark::OutputStart => {
                 // Output start (command executed)
-                self.current_zone = SemanticZone::Output;
-                self.line_zones.insert(line, SemanticZone::Output);
+                self.mark_zone(SemanticZone::Output);
+                if let Some(absolute) = absolute {
+                    let command = self.input_text();
+                    self.commands.output_start(command, absolute, now);
+                }
                 log::debug!("OSC 133;C: Output start at line {}", line);
             }
-            b'D' => {
+            PromptMark::CommandEnd { exit_code } => {
                 // Output end with exit code - emit command success/fail event
                 let code = exit_code.unwrap_or(0);
                 log::debug!(
@@ -295,6 +690,11 @@ impl Terminal {
                     line,
                     code
                 );
+                if let Some(absolute) = absolute {
+                    // Output ends with the cursor's line unless nothing's on it yet
+                    let end = absolute + usize::from(cursor.column.0 > 0);
+                    self.commands.command_end(exit_code, end, now);
+                }
                 if code == 0 {
                     self.pending_shell_events.push(ShellEvent::CommandSuccess);
                 } else {
@@ -302,18 +702,319 @@ impl Terminal {
                         .push(ShellEvent::CommandFail(code));
                 }
             }
-            _ => {}
         }
     }
 
-    /// Get semantic zone for a given line
+    /// Enter a zone starting on the cursor's line
+    fn mark_zone(&mut self, zone: SemanticZone) {
+        self.current_zone = zone;
+        if let Some(line) = self.cursor_line() {
+            self.zones.insert(line, zone);
+        }
+    }
+
+    /// Absolute line of the cursor, on the primary screen
+    ///
+    /// Full-screen programs draw their own prompts, if any, so marks on the
+    /// alternate screen don't start zones or commands.
+    fn cursor_line(&self) -> Option<usize> {
+        if self.in_alt_screen() {
+            return None;
+        }
+        let grid = self.term.grid();
+        self.scrollback.absolute(grid, grid.cursor.point.line)
+    }
+
+    /// Text of the pending command line, from its input start to the cursor
+    fn input_text(&self) -> String {
+        let Some((line, column)) = self.commands.input() else {
+            return String::new();
+        };
+        let grid = self.term.grid();
+        let start = Point::new(self.scrollback.line(grid, line), Column(column));
+        // The output starts at the cursor; the input ends just before it
+        let cursor = grid.cursor.point;
+        let end = match cursor.column.0 {
+            0 => Point::new(cursor.line - 1, grid.last_column()),
+            column => Point::new(cursor.line, Column(column - 1)),
+        };
+        if start > end || start.line < grid.topmost_line() {
+            return String::new();
+        }
+        self.term.bounds_to_string(start, end).trim().to_string()
+    }
+
+    /// Commands the shell has run, oldest first
+    ///
+    /// Built from OSC 133 marks, so empty unless the shell sends them.
+    /// Commands are dropped once their prompt leaves the scrollback.
+    pub fn commands(&self) -> Vec<CommandRecord> {
+        let grid = self.term.grid();
+        let line = |absolute| self.scrollback.line(grid, absolute).0;
+        let cursor = grid.cursor.point;
+        let running_end = cursor.line.0 + i32::from(cursor.column.0 > 0);
+        let end = |output_end: Option<usize>| output_end.map_or(running_end, line);
+        self.commands
+            .iter()
+            .map(|command| CommandRecord {
+                id: command.id,
+                prompt_line: line(command.prompt),
+                command: command.command.clone(),
+                started: command.started,
+                finished: command.finished,
+                exit_code: command.exit_code,
+                cwd: command.cwd.clone(),
+                output: line(command.output_start)..end(command.output_end),
+                folded: self.folds.contains(command.output_start),
+            })
+            .collect()
+    }
+
+    /// Grid lines of the prompts still in the terminal, oldest first
+    ///
+    /// Includes the prompt being edited. Built from OSC 133 marks, like
+    /// [`Self::commands`], and empty on the alternate screen.
+    pub fn prompt_lines(&self) -> Vec<i32> {
+        if self.in_alt_screen() {
+            return Vec::new();
+        }
+        let grid = self.term.grid();
+        self.commands
+            .prompts()
+            .map(|absolute| self.scrollback.line(grid, absolute).0)
+            .collect()
+    }
+
+    /// Fold a finished command's output into one row showing `summary`
+    ///
+    /// The command is the one whose prompt is on grid line `prompt_line`.
+    /// Its output rows are kept aside until [`Self::unfold_command`], and
+    /// scrolling, selection and search see the summary row in their place.
+    /// The view keeps its top line where it can. Returns false if there's no
+    /// such command, it's still running, its output is under two lines or
+    /// already folded, or the alternate screen is up.
+    ///
+    /// The rows moved aren't reported as damage; redraw the whole view.
+    pub fn fold_command(&mut self, prompt_line: i32, summary: &str) -> bool {
+        let Some((start, end)) = self
+            .command_at_prompt(prompt_line)
+            .and_then(|command| Some((command.output_start, command.output_end?)))
+        else {
+            return false;
+        };
+        let grid = self.term.grid();
+        let lines = self.scrollback.line(grid, start)..self.scrollback.line(grid, end);
+        // The summary row goes on the last line, which must be above the cursor's
+        if end - start < 2
+            || self.folds.contains(start)
+            || lines.start < grid.topmost_line()
+            || lines.end > grid.cursor.point.line
+        {
+            return false;
+        }
+        let hidden = end - start - 1;
+        let offset = self.display_offset();
+
+        // A selection in the folded rows goes; one above them moves with its text
+        let selection = self.term.selection.take();
+        let grid = self.term.grid();
+        self.term.selection = selection.and_then(|selection| {
+            if selection.intersects_range(lines.clone()) {
+                None
+            } else if selection.intersects_range(..lines.start) {
+                selection.rotate(grid, &(grid.topmost_line()..lines.end), -(hidden as i32))
+            } else {
+                Some(selection)
+            }
+        });
+
+        let summary_row = folds::summary_row(summary, self.columns());
+        let grid = self.term.grid_mut();
+        let (rows, shrink) = folds::collapse(grid, lines.clone(), summary_row.clone());
+        grid.update_history(SCROLLBACK_LINES + HISTORY_SLACK);
+
+        // Lines above move down as far as history couldn't make up for it by
+        // shrinking; lines below move up as far as it did
+        let pushed = hidden - shrink;
+        let zones = (start + 1..end)
+            .map(|line| (line - start, self.zones.get(line)))
+            .filter(|&(_, zone)| zone != SemanticZone::Unknown)
+            .collect();
+        let moved = |line: usize| match line {
+            line if line <= start => Some(line + pushed),
+            line if line < end => None,
+            line => Some(line - shrink),
+        };
+        self.zones.relocate(moved);
+        self.commands.relocate(moved);
+        self.folds.relocate(moved);
+        self.folds.insert(
+            start + pushed,
+            Fold {
+                rows,
+                zones,
+                summary: summary.to_string(),
+                summary_row,
+            },
+        );
+
+        // Keep the top of the view on its text, unless the view is at the bottom
+        let top = -(offset as i32);
+        let target = if offset == 0 || top >= lines.end.0 {
+            offset
+        } else if top < lines.start.0 {
+            offset.saturating_sub(hidden)
+        } else {
+            (1 - lines.end.0).max(0) as usize
+        };
+        let delta = target as i32 - self.display_offset() as i32;
+        self.term.scroll_display(Scroll::Delta(delta));
+        self.relayouts += 1;
+        true
+    }
+
+    /// Unfold output folded by [`Self::fold_command`]
+    ///
+    /// Returns false if the command whose prompt is on grid line
+    /// `prompt_line` isn't folded. Like folding, keeps the top of the view
+    /// on its text and leaves the redraw to the caller.
+    pub fn unfold_command(&mut self, prompt_line: i32) -> bool {
+        match self.command_at_prompt(prompt_line) {
+            Some(command) => self.unfold(command.output_start),
+            None => false,
+        }
+    }
+
+    /// Check if a grid line is the summary row of folded output
+    pub fn is_folded_line(&self, line: i32) -> bool {
+        self.fold_at(line).is_some()
+    }
+
+    /// Command whose prompt is on a grid line of the primary screen
+    fn command_at_prompt(&self, prompt_line: i32) -> Option<&commands::Command> {
+        if self.in_alt_screen() {
+            return None;
+        }
+        let prompt = self
+            .scrollback
+            .absolute(self.term.grid(), Line(prompt_line))?;
+        self.commands
+            .iter()
+            .find(|command| command.prompt == prompt)
+    }
+
+    /// Fold whose summary row is on a grid line
+    fn fold_at(&self, line: i32) -> Option<&Fold> {
+        if self.in_alt_screen() {
+            return None;
+        }
+        let line = self.scrollback.absolute(self.term.grid(), Line(line))?;
+        self.folds.get(line)
+    }
+
+    /// Unfold the fold whose summary row is on absolute line `start`
+    ///
+    /// A fold whose summary row was overwritten or erased is dropped along
+    /// with its rows, as there's nowhere left to put them back.
+    fn unfold(&mut self, start: usize) -> bool {
+        let Some(fold) = self.folds.remove(start) else {
+            return false;
+        };
+        let grid = self.term.grid();
+        let line = self.scrollback.line(grid, start);
+        if line > grid.bottommost_line() || grid[line] != fold.summary_row {
+            log::debug!("Dropping fold at line {}: summary row is gone", line);
+            return true;
+        }
+        let hidden = fold.hidden();
+        let offset = self.display_offset();
+        let selection = self.term.selection.take();
+
+        let grid = self.term.grid_mut();
+        folds::expand(grid, line, fold.rows);
+        grid.update_history(SCROLLBACK_LINES + HISTORY_SLACK);
+
+        // Lines after the summary row move down past the rows put back
+        let moved = |line: usize| Some(if line > start { line + hidden } else { line });
+        self.zones.relocate(moved);
+        self.commands.relocate(moved);
+        self.folds.relocate(moved);
+        for (offset, zone) in fold.zones {
+            self.zones.insert(start + offset, zone);
+        }
+
+        // A selection on the summary row goes; one above it moves with its text
+        let grid = self.term.grid();
+        self.term.selection = selection.and_then(|selection| {
+            if selection.intersects_range(line..=line) {
+                None
+            } else if selection.intersects_range(..line) {
+                selection.rotate(grid, &(grid.topmost_line()..line + 1), hidden as i32)
+            } else {
+                Some(selection)
+            }
+        });
+
+        let target = if offset > 0 && -(offset as i32) <= line.0 {
+            offset + hidden
+        } else {
+            offset
+        };
+        let delta = target as i32 - self.display_offset() as i32;
+        self.term.scroll_display(Scroll::Delta(delta));
+        self.trim_history();
+        self.relayouts += 1;
+        true
+    }
+
+    /// Unfold everything, returning what's needed to fold it again: each
+    /// command's place counting back from the last, and its summary
+    fn unfold_all(&mut self) -> Vec<(usize, String)> {
+        let mut unfolded = Vec::new();
+        if self.in_alt_screen() {
+            return unfolded;
+        }
+        // From the bottom up, so the lines of folds still to go stay put
+        let starts: Vec<usize> = self.folds.lines().rev().collect();
+        for start in starts {
+            let summary = self.folds.get(start).map(|fold| fold.summary.clone());
+            let from_last = self
+                .commands
+                .iter()
+                .rev()
+                .position(|command| command.output_start == start);
+            if self.unfold(start)
+                && let (Some(summary), Some(from_last)) = (summary, from_last)
+            {
+                unfolded.push((from_last, summary));
+            }
+        }
+        unfolded
+    }
+
+    /// Fold commands again after [`Self::unfold_all`]
+    fn refold(&mut self, unfolded: Vec<(usize, String)>) {
+        for (from_last, summary) in unfolded {
+            let Some(prompt) = self.commands.iter().rev().nth(from_last).map(|c| c.prompt) else {
+                continue;
+            };
+            let line = self.scrollback.line(self.term.grid(), prompt);
+            self.fold_command(line.0, &summary);
+        }
+    }
+
+    /// Get semantic zone for a given grid line (negative for scrollback)
     ///
-    /// Returns Unknown if no OSC 133 marker has been seen for this line.
+    /// Zones stay with their lines as output scrolls, and are dropped with
+    /// them when history is trimmed or cleared. Returns Unknown if no OSC 133
+    /// marker has been seen for this line, or on the alternate screen.
     pub fn get_line_zone(&self, line: i32) -> SemanticZone {
-        self.line_zones
-            .get(&line)
-            .copied()
-            .unwrap_or(SemanticZone::Unknown)
+        if self.in_alt_screen() {
+            return SemanticZone::Unknown;
+        }
+        self.scrollback
+            .absolute(self.term.grid(), Line(line))
+            .map_or(SemanticZone::Unknown, |line| self.zones.get(line))
     }
 
     /// Check if any OSC 133 zones have been detected
@@ -321,7 +1022,20 @@ impl Terminal {
     /// Returns true if the shell has sent at least one OSC 133 sequence,
     /// indicating it supports semantic prompts.
     pub fn has_semantic_zones(&self) -> bool {
-        !self.line_zones.is_empty()
+        self.zones.is_marked()
+    }
+
+    /// Working directory last reported by the shell via OSC 7
+    ///
+    /// Unlike the PTY's process cwd, this follows the shell into ssh, tmux
+    /// and nested subshells, as long as they emit OSC 7 themselves.
+    pub fn reported_cwd(&self) -> Option<&FileUri> {
+        self.reported_cwd.as_ref()
+    }
+
+    /// Options the shell gave with the latest prompt (OSC 133;A)
+    pub fn prompt_options(&self) -> Option<&PromptOptions> {
+        self.prompt_options.as_ref()
     }
 
     /// Get the current semantic zone state
@@ -334,6 +1048,23 @@ impl Terminal {
         self.term.renderable_content()
     }
 
+    /// Colors set by applications at runtime (OSC 4/10/11/12)
+    ///
+    /// Entries are `None` until a program sets them and return to `None` on
+    /// OSC 104/110/111/112 or `reset_colors()`.
+    pub fn colors(&self) -> &Colors {
+        self.term.colors()
+    }
+
+    /// Drop every runtime color override, reverting to the theme palette
+    pub fn reset_colors(&mut self) {
+        for index in 0..color::COUNT {
+            if self.term.colors()[index].is_some() {
+                ansi::Handler::reset_color(&mut self.term, index);
+            }
+        }
+    }
+
     /// Get the cursor information
     pub fn cursor(&self) -> term::RenderableCursor {
         self.renderable_content().cursor
@@ -349,6 +1080,11 @@ impl Terminal {
         *self.term.mode()
     }
 
+    /// Mouse modes beyond those in [`TermMode`]
+    pub fn mouse_modes(&self) -> MouseModes {
+        self.mouse_modes
+    }
+
     /// Take pending terminal events
     pub fn take_events(&self) -> Vec<Event> {
         self.event_proxy.take_events()
@@ -362,10 +1098,39 @@ impl Terminal {
     }
 
     /// Resize the terminal
+    ///
+    /// Semantic zones and commands follow their lines as the text reflows.
+    /// Folded output is put back to reflow with the rest, then folded again.
     pub fn resize(&mut self, size: Size) {
         self.size = size;
         let term_size = TermSize::new(size.columns, size.lines);
+        let unfolded = self.unfold_all();
+        let followed = (!self.in_alt_screen()).then(|| {
+            let mut lines: Vec<usize> = self.zones.lines().collect();
+            lines.extend(self.commands.lines());
+            let anchors = self.scrollback.anchor(self.term.grid(), &lines);
+            (lines, anchors)
+        });
         self.term.resize(term_size);
+        if let Some((lines, anchors)) = followed {
+            let found = self.scrollback.find(self.term.grid(), &anchors);
+            let moved: HashMap<usize, Option<usize>> = lines.into_iter().zip(found).collect();
+            let moved = |line| moved.get(&line).copied().flatten();
+            self.zones.relocate(moved);
+            self.commands.relocate(moved);
+            self.trim_history();
+        }
+        self.refold(unfolded);
+        self.relayouts += 1;
+    }
+
+    /// Clear the scrollback history, keeping the screen
+    pub fn clear_history(&mut self) {
+        let cleared = self.term.history_size();
+        ansi::Handler::clear_screen(&mut self.term, ansi::ClearMode::Saved);
+        if !self.in_alt_screen() {
+            self.evict(cleared);
+        }
     }
 
     /// Access the underlying Term for advanced operations
@@ -486,6 +1251,39 @@ impl Terminal {
         self.term.grid().history_size()
     }
 
+    /// Search the screen and history for a query
+    ///
+    /// Only lines that may have changed since `search` last ran here are
+    /// searched again, so it's cheap to call as output arrives. Returns how
+    /// many of the matches found before were dropped from the front as their
+    /// lines left history.
+    pub fn update_search(&self, search: &mut Search) -> usize {
+        let seen = (self.relayouts, self.in_alt_screen());
+        search.update(&self.term, &self.scrollback, seen)
+    }
+
+    /// Matches found by [`Self::update_search`], oldest first, from first
+    /// cell to last in grid lines (negative for scrollback)
+    ///
+    /// A match ending on a wide character ends on its spacer.
+    pub fn search_matches(&self, search: &Search) -> Vec<RangeInclusive<Point>> {
+        let grid = self.term.grid();
+        let point = |(line, column)| Point::new(self.scrollback.line(grid, line), column);
+        search
+            .matches()
+            .map(|found| point(*found.start())..=point(*found.end()))
+            .filter(|found| found.start().line >= grid.topmost_line())
+            .map(|found| {
+                let (start, mut end) = found.into_inner();
+                if grid[end].flags.contains(CellFlags::WIDE_CHAR) && end.column < grid.last_column()
+                {
+                    end.column += 1;
+                }
+                start..=end
+            })
+            .collect()
+    }
+
     /// Get all lines as text (history + visible), returns Vec of (line_index, text)
     /// Line indices are relative to the grid: negative = history, 0+ = visible
     pub fn all_lines_text(&self) -> Vec<(i32, String)> {
@@ -523,18 +1321,89 @@ impl Terminal {
         lines
     }
 
+    /// Text of a range of grid lines, with wrapped rows joined
+    ///
+    /// Folded output is included in place of its summary row. Lines outside
+    /// the grid are left out.
+    pub fn lines_text(&self, lines: Range<i32>) -> String {
+        let grid = self.term.grid();
+        let start = lines.start.max(grid.topmost_line().0);
+        let end = lines.end.min(grid.bottommost_line().0 + 1);
+        let mut parts = Vec::new();
+        let mut from = start;
+        for line in start..end {
+            if let Some(fold) = self.fold_at(line) {
+                parts.extend(self.rows_text(from..line));
+                parts.push(fold.text());
+                from = line + 1;
+            }
+        }
+        parts.extend(self.rows_text(from..end));
+        parts.join("\n")
+    }
+
+    /// Text of grid lines within the grid, if there are any
+    fn rows_text(&self, lines: Range<i32>) -> Option<String> {
+        if lines.is_empty() {
+            return None;
+        }
+        let start = Point::new(Line(lines.start), Column(0));
+        let end = Point::new(Line(lines.end - 1), self.term.grid().last_column());
+        Some(self.term.bounds_to_string(start, end))
+    }
+
     /// Check if bracketed paste mode is enabled
     pub fn bracketed_paste_enabled(&self) -> bool {
         self.term.mode().contains(TermMode::BRACKETED_PASTE)
     }
 }
 
+/// Log PTY output with escape sequences made visible, for debugging
+pub(crate) fn log_pty_output(output: &[u8]) {
+    if output.len() >= 2000 {
+        return;
+    }
+    use std::fmt::Write;
+    let mut escaped = String::with_capacity(output.len() * 2);
+    for &b in output {
+        if b == 0x1b {
+            escaped.push_str("ESC");
+        } else if b == 0x07 {
+            escaped.push_str("BEL");
+        } else if b < 32 {
+            let _ = write!(escaped, "^{}", (b + 64) as char);
+        } else if b < 127 {
+            escaped.push(b as char);
+        } else {
+            let _ = write!(escaped, "\\x{:02x}", b);
+        }
+    }
+    log::debug!("PTY output ({} bytes): {}", output.len(), escaped);
+    // Check for black color sequences
+    let output_str = String::from_utf8_lossy(output);
+    if output_str.contains("[30m") || output_str.contains("[30;") {
+        log::warn!("PTY output contains BLACK foreground sequence!");
+    }
+}
+
 /// A terminal connected to a PTY backend running a shell.
 ///
 /// Generic over the PTY backend to enable testing with mock PTY implementations.
+/// Backends that hand over their output stream are parsed on a
+/// [`ParserWorker`] thread; others are read and parsed in `process_pty_output`.
 pub struct ShellTerminalGeneric<P: PtyBackend> {
-    terminal: Terminal,
+    terminal: Arc<Mutex<Terminal>>,
     pty: P,
+    /// Thread parsing the PTY output, if the backend handed it over
+    worker: Option<ParserWorker>,
+    /// OSC 52 clipboard requests awaiting the application's policy decision
+    pending_clipboard: Vec<ClipboardRequest>,
+    /// Exit code once the shell process has exited
+    exit_code: Option<i32>,
+    /// Exit code from `Event::ChildExit` not yet taken by the application
+    pending_child_exit: Option<i32>,
+    /// Title set by the program (OSC 0/2), cleared when it resets it
+    title: Option<String>,
 }
 
 /// Backward-compatible alias for the concrete PTY implementation
@@ -543,36 +1412,29 @@ pub type ShellTerminal = ShellTerminalGeneric<Pty>;
 impl ShellTerminal {
     /// Create a new shell terminal with the given size
     pub fn new(size: Size) -> anyhow::Result<Self> {
-        let terminal = Terminal::new(size);
         let pty = Pty::spawn(None, size.columns as u16, size.lines as u16)?;
-
-        Ok(Self { terminal, pty })
+        Ok(Self::with_backend(size, pty))
     }
 
     /// Create a new shell terminal with a specific working directory
     pub fn with_cwd(size: Size, cwd: std::path::PathBuf) -> anyhow::Result<Self> {
-        let terminal = Terminal::new(size);
         let pty = Pty::spawn_with_cwd(None, size.columns as u16, size.lines as u16, Some(cwd))?;
-
-        Ok(Self { terminal, pty })
+        Ok(Self::with_backend(size, pty))
     }
 
     /// Create a new shell terminal with a specific shell
     pub fn with_shell(size: Size, shell: &str) -> anyhow::Result<Self> {
-        let terminal = Terminal::new(size);
         let pty = Pty::spawn(Some(shell), size.columns as u16, size.lines as u16)?;
-
-        Ok(Self { terminal, pty })
+        Ok(Self::with_backend(size, pty))
     }
 
     /// Create a new shell terminal with full spawn options
     ///
     /// This enables semantic prompt support (OSC 133) for command success/fail detection.
     pub fn with_options(size: Size, options: SpawnOptions) -> anyhow::Result<Self> {
-        let terminal = Terminal::new(size);
-        let pty = Pty::spawn_with_options(size.columns as u16, size.lines as u16, options)?;
-
-        Ok(Self { terminal, pty })
+        let wakeup = options.wakeup.clone();
+        let pty = Pty::spawn_with_options(size, options)?;
+        Ok(Self::with_backend_and_wakeup(size, pty, wakeup))
     }
 
     /// Get access to the PTY
@@ -584,8 +1446,25 @@ impl ShellTerminal {
 impl<P: PtyBackend> ShellTerminalGeneric<P> {
     /// Create a shell terminal with a custom PTY backend
     pub fn with_backend(size: Size, pty: P) -> Self {
-        let terminal = Terminal::new(size);
-        Self { terminal, pty }
+        Self::with_backend_and_wakeup(size, pty, None)
+    }
+
+    /// Create a shell terminal with a custom PTY backend whose parser
+    /// thread runs `wakeup` when new output is ready
+    pub fn with_backend_and_wakeup(size: Size, mut pty: P, wakeup: Option<Wakeup>) -> Self {
+        let terminal = Arc::new(Mutex::new(Terminal::new(size)));
+        let worker = pty
+            .take_output()
+            .map(|output| ParserWorker::spawn(output, &terminal, wakeup));
+        Self {
+            terminal,
+            pty,
+            worker,
+            pending_clipboard: Vec::new(),
+            exit_code: None,
+            pending_child_exit: None,
+            title: None,
+        }
     }
 
     /// Get the current working directory of the shell process
@@ -593,39 +1472,80 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
         self.pty.working_directory()
     }
 
+    /// Get the process in the terminal's foreground (the shell when idle)
+    pub fn foreground_process(&self) -> Option<ForegroundProcess> {
+        self.pty.foreground_process()
+    }
+
+    /// Get the foreground process unless it's the shell itself, i.e. the
+    /// program the user would interrupt by closing the terminal
+    pub fn foreground_job(&self) -> Option<ForegroundProcess> {
+        let process = self.pty.foreground_process()?;
+        (Some(process.pid) != self.pty.process_id()).then_some(process)
+    }
+
+    /// Title most recently set by the program, if it hasn't been reset
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
     /// Process any available PTY output through the terminal
     /// Returns true if any output was processed
+    ///
+    /// With a parser thread this only reports whether it parsed anything
+    /// since the last call. Once the output runs dry, also polls the shell
+    /// process and emits `Event::ChildExit` the first time it has exited.
     pub fn process_pty_output(&mut self) -> bool {
+        if let Some(worker) = &self.worker {
+            let updated = worker.take_updated();
+            if !updated && worker.is_idle() {
+                self.poll_child_exit();
+            }
+            return updated;
+        }
+
         let output = self.pty.read_available();
-        if !output.is_empty() {
-            // Log escape sequences for debugging
-            if output.len() < 2000 {
-                use std::fmt::Write;
-                let mut escaped = String::with_capacity(output.len() * 2);
-                for &b in &output {
-                    if b == 0x1b {
-                        escaped.push_str("ESC");
-                    } else if b == 0x07 {
-                        escaped.push_str("BEL");
-                    } else if b < 32 {
-                        let _ = write!(escaped, "^{}", (b + 64) as char);
-                    } else if b < 127 {
-                        escaped.push(b as char);
-                    } else {
-                        let _ = write!(escaped, "\\x{:02x}", b);
-                    }
-                }
-                log::debug!("PTY output ({} bytes): {}", output.len(), escaped);
-                // Check for black color sequences
-                let output_str = String::from_utf8_lossy(&output);
-                if output_str.contains("[30m") || output_str.contains("[30;") {
-                    log::warn!("PTY output contains BLACK foreground sequence!");
-                }
+        if output.is_empty() {
+            if self.terminal().end_expired_sync_update() {
+                return true;
             }
-            self.terminal.process_input(&output);
-            true
-        } else {
-            false
+            self.poll_child_exit();
+            return false;
+        }
+        log_pty_output(&output);
+        self.terminal().process_input(&output);
+        true
+    }
+
+    /// Deadline of the synchronized update (DEC mode 2026) the program is
+    /// in the middle of, if any
+    pub fn sync_update_deadline(&self) -> Option<Instant> {
+        self.terminal().sync_update_deadline()
+    }
+
+    /// Check for output the parser thread has parsed but
+    /// `process_pty_output` hasn't reported yet
+    pub fn has_pending_output(&self) -> bool {
+        self.worker.as_ref().is_some_and(ParserWorker::has_update)
+    }
+
+    /// Check whether the PTY has closed but the shell's exit hasn't been
+    /// seen yet, so `process_pty_output` should keep polling for it
+    pub fn awaiting_exit(&self) -> bool {
+        self.exit_code.is_none() && self.worker.as_ref().is_some_and(ParserWorker::is_finished)
+    }
+
+    /// Emit `Event::ChildExit` if the shell has exited since the last poll
+    fn poll_child_exit(&mut self) {
+        if self.exit_code.is_some() {
+            return;
+        }
+        self.exit_code = self.pty.try_wait();
+        if let Some(code) = self.exit_code {
+            log::info!("Shell exited with code {}", code);
+            self.terminal()
+                .event_proxy
+                .send_event(Event::ChildExit(code));
         }
     }
 
@@ -634,25 +1554,56 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
         self.pty.write(data);
     }
 
+    /// Tell the program its tab gained or lost focus, if it asked (mode 1004)
+    pub fn report_focus(&self, focused: bool) {
+        if self.terminal().mode().contains(TermMode::FOCUS_IN_OUT) {
+            self.send_input(if focused { b"\x1b[I" } else { b"\x1b[O" });
+        }
+    }
+
     /// Resize both the terminal and PTY
     pub fn resize(&mut self, size: Size) {
-        self.terminal.resize(size);
-        self.pty.resize(size.columns as u16, size.lines as u16);
+        self.terminal().resize(size);
+        self.pty.resize(size);
     }
 
-    /// Get access to the terminal for rendering
-    pub fn terminal(&self) -> &Terminal {
-        &self.terminal
+    /// Lock the terminal for rendering
+    ///
+    /// The parser thread waits while the guard is held, so keep it short and
+    /// don't call other `ShellTerminal` methods that lock it meanwhile.
+    pub fn terminal(&self) -> MutexGuard<'_, Terminal> {
+        self.terminal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the terminal for modification
+    pub fn terminal_mut(&mut self) -> MutexGuard<'_, Terminal> {
+        self.terminal()
+    }
+
+    /// Write a terminal-generated reply (DA, DSR, CPR, DECRQM) back to the PTY
+    ///
+    /// alacritty_terminal answers queries by emitting `Event::PtyWrite`; programs
+    /// waiting on those answers (fzf, neovim, shells probing the cursor) stall
+    /// if they are dropped.
+    fn write_reply(&self, reply: &str) {
+        log::debug!("Writing terminal reply to PTY: {:?}", reply);
+        self.pty.write(reply.as_bytes());
     }
 
-    /// Get mutable access to the terminal
-    pub fn terminal_mut(&mut self) -> &mut Terminal {
-        &mut self.terminal
+    /// Answer the text area size query (`CSI 14 t`) with the size from the
+    /// last resize
+    ///
+    /// alacritty_terminal's formatter multiplies a whole-pixel cell size,
+    /// which is off for fractional font metrics, so the reply is built here.
+    fn reply_text_area_size(&self) {
+        let size = self.terminal().size();
+        let reply = format!("\x1b[4;{};{}t", size.pixel_height(), size.pixel_width());
+        self.write_reply(&reply);
     }
 
     /// Take any pending terminal events (title changes, bells, etc.)
     pub fn take_events(&self) -> Vec<Event> {
-        self.terminal.take_events()
+        self.terminal().take_events()
     }
 
     /// Check for title change and return it, preserving other events
@@ -668,7 +1619,7 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
     /// Note: For theme-triggerable events, prefer `take_shell_events()` which
     /// provides a unified `ShellEvent` enum including bell, command success/fail.
     pub fn check_events(&self) -> (Option<String>, bool) {
-        let events = self.terminal.take_events();
+        let events = self.terminal().take_events();
         let mut title = None;
         let mut bell = false;
 
@@ -683,6 +1634,8 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
                     log::debug!("Bell event received from terminal");
                     bell = true;
                 }
+                Event::PtyWrite(reply) => self.write_reply(&reply),
+                Event::TextAreaSizeRequest(_) => self.reply_text_area_size(),
                 _ => {} // Ignore other events
             }
         }
@@ -697,20 +1650,54 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
     ///
     /// Also checks for Bell from alacritty_terminal and includes it as ShellEvent::Bell.
     /// Returns (shell_events, Option<title>) to combine with title checking.
-    pub fn take_shell_events(&mut self) -> (Vec<ShellEvent>, Option<String>) {
-        let mut shell_events = self.terminal.take_shell_events();
+    ///
+    /// Color queries (OSC 4/10/11/12 with `?`) are answered with `color`, which
+    /// gets the palette index (or `NamedColor` value: `Foreground` for OSC 10,
+    /// `Background` for OSC 11, `Cursor` for OSC 12) and the colors the program
+    /// set. They're answered alongside the terminal's other replies, so a
+    /// program probing with `OSC 11 ; ?` then `CSI c` hears back in that order.
+    /// Queries `color` returns `None` for go unanswered.
+    pub fn take_shell_events(
+        &mut self,
+        color: impl Fn(usize, &Colors) -> Option<Rgb>,
+    ) -> (Vec<ShellEvent>, Option<String>) {
+        let mut shell_events = self.terminal().take_shell_events();
 
         // Also check terminal events for Bell and title
-        let events = self.terminal.take_events();
+        let events = self.terminal().take_events();
         let mut title = None;
 
         for event in events {
             match event {
-                Event::Title(t) => title = Some(t),
+                Event::Title(t) => {
+                    // An empty title hands the tab back to us
+                    self.title = (!t.is_empty()).then(|| t.clone());
+                    title = Some(t);
+                }
+                Event::ResetTitle => self.title = None,
                 Event::Bell => {
                     log::debug!("Bell event converted to ShellEvent");
                     shell_events.push(ShellEvent::Bell);
                 }
+                Event::PtyWrite(reply) => self.write_reply(&reply),
+                Event::TextAreaSizeRequest(_) => self.reply_text_area_size(),
+                Event::ClipboardStore(clipboard, text) => {
+                    self.pending_clipboard
+                        .push(ClipboardRequest::Store { clipboard, text });
+                }
+                Event::ClipboardLoad(clipboard, formatter) => {
+                    self.pending_clipboard.push(ClipboardRequest::Load {
+                        clipboard,
+                        formatter,
+                    });
+                }
+                Event::ColorRequest(index, formatter) => {
+                    let colors = *self.terminal().colors();
+                    if let Some(rgb) = color(index, &colors) {
+                        self.write_reply(&formatter(rgb));
+                    }
+                }
+                Event::ChildExit(code) => self.pending_child_exit = Some(code),
                 _ => {}
             }
         }
@@ -718,54 +1705,81 @@ impl<P: PtyBackend> ShellTerminalGeneric<P> {
         (shell_events, title)
     }
 
+    /// Take OSC 52 clipboard requests collected by `take_shell_events()`
+    pub fn take_clipboard_requests(&mut self) -> Vec<ClipboardRequest> {
+        std::mem::take(&mut self.pending_clipboard)
+    }
+
+    /// Take the shell's exit code collected by `take_shell_events()`
+    ///
+    /// Returns `Some` once, on the frame the exit is first seen.
+    pub fn take_child_exit(&mut self) -> Option<i32> {
+        self.pending_child_exit.take()
+    }
+
+    /// Exit code of the shell, or `None` while it is still running
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
     /// Start a new selection at the given point
     pub fn start_selection(&mut self, point: Point, selection_type: SelectionType) {
-        self.terminal.start_selection(point, selection_type);
+        self.terminal().start_selection(point, selection_type);
     }
 
     /// Update the selection end point
     pub fn update_selection(&mut self, point: Point) {
-        self.terminal.update_selection(point);
+        self.terminal().update_selection(point);
     }
 
     /// Clear the current selection
     pub fn clear_selection(&mut self) {
-        self.terminal.clear_selection();
+        self.terminal().clear_selection();
+    }
+
+    /// Clear the scrollback history, keeping the screen
+    pub fn clear_history(&mut self) {
+        self.terminal().clear_history();
+    }
+
+    /// Commands the shell has run, oldest first (from OSC 133)
+    pub fn commands(&self) -> Vec<CommandRecord> {
+        self.terminal().commands()
     }
 
     /// Check if a selection exists
     pub fn has_selection(&self) -> bool {
-        self.terminal.has_selection()
+        self.terminal().has_selection()
     }
 
     /// Get the selection as text, if any
     pub fn selection_to_string(&self) -> Option<String> {
-        self.terminal.selection_to_string()
+        self.terminal().selection_to_string()
     }
 
     /// Scroll the terminal viewport
     pub fn scroll(&mut self, scroll: crate::Scroll) {
-        self.terminal.scroll(scroll);
+        self.terminal().scroll(scroll);
     }
 
     /// Get the current display offset (how far scrolled into history)
     pub fn display_offset(&self) -> usize {
-        self.terminal.display_offset()
+        self.terminal().display_offset()
     }
 
     /// Check if the terminal is scrolled back (not showing live output)
     pub fn is_scrolled_back(&self) -> bool {
-        self.terminal.is_scrolled_back()
+        self.terminal().is_scrolled_back()
     }
 
     /// Scroll to the bottom (show live output)
     pub fn scroll_to_bottom(&mut self) {
-        self.terminal.scroll_to_bottom();
+        self.terminal().scroll_to_bottom();
     }
 
     /// Check if bracketed paste mode is enabled
     pub fn bracketed_paste_enabled(&self) -> bool {
-        self.terminal.bracketed_paste_enabled()
+        self.terminal().bracketed_paste_enabled()
     }
 }
 
@@ -963,6 +1977,500 @@ mod tests {
         assert_eq!(events[0], ShellEvent::CommandSuccess);
     }
 
+    #[test]
+    fn osc133_split_across_reads() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        term.process_input(b"out\r\n\x1b]13");
+        term.process_input(b"3;D;");
+        assert!(term.take_shell_events().is_empty());
+        term.process_input(b"2\x1b");
+        term.process_input(b"\\");
+
+        assert_eq!(term.take_shell_events(), vec![ShellEvent::CommandFail(2)]);
+    }
+
+    #[test]
+    fn osc133_marks_line_where_it_appears() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        // Output and the next prompt arrive in one read
+        term.process_input(b"\x1b]133;C\x07one\r\ntwo\r\n\x1b]133;A;aid=42\x07$ ");
+
+        assert_eq!(term.get_line_zone(0), SemanticZone::Output);
+        assert_eq!(term.get_line_zone(2), SemanticZone::Prompt);
+        assert_eq!(term.prompt_options().unwrap().aid.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn osc133_prompt_lines_and_fresh_line() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        // L starts a new line only when the cursor isn't at the start of one
+        term.process_input(b"no newline\x1b]133;L\x07\x1b]133;L\x07");
+        assert_eq!(term.cursor().point, Point::new(Line(1), Column(0)));
+
+        term.process_input(b"\x1b]133;B\x07for x in 1 2\r\n\x1b]133;P;k=c\x07> ");
+        assert_eq!(term.get_line_zone(1), SemanticZone::Input);
+        assert_eq!(term.get_line_zone(2), SemanticZone::Prompt);
+        assert_eq!(term.current_zone(), SemanticZone::Prompt);
+
+        // A right prompt leaves its line to the input
+        term.process_input(b"\x1b]133;B\x07\x1b]133;P;k=r\x07");
+        assert_eq!(term.current_zone(), SemanticZone::Input);
+    }
+
+    /// Prompt, input and output marks for one command, then its output
+    fn command(term: &mut Terminal, output_lines: usize) {
+        term.process_input(b"\x1b]133;A\x07$ \x1b]133;B\x07seq\r\n\x1b]133;C\x07");
+        term.process_input("line\r\n".repeat(output_lines).as_bytes());
+    }
+
+    /// Grid line of the first zone of a kind still in the terminal
+    fn zone_line(term: &Terminal, zone: SemanticZone) -> Option<i32> {
+        let top = -(term.history_size() as i32);
+        (top..term.screen_lines() as i32).find(|&line| term.get_line_zone(line) == zone)
+    }
+
+    #[test]
+    fn zones_follow_output_into_history() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        command(&mut term, 5000);
+
+        // The prompt, with the input on its line, was the first line written
+        let top = -(term.history_size() as i32);
+        assert_eq!(term.history_size(), 5000 + 2 - 24);
+        assert_eq!(term.get_line_zone(top), SemanticZone::Input);
+        assert_eq!(term.get_line_zone(top + 1), SemanticZone::Output);
+        assert_eq!(term.get_line_zone(top + 2), SemanticZone::Unknown);
+        for line in 0..24 {
+            assert_eq!(term.get_line_zone(line), SemanticZone::Unknown);
+        }
+    }
+
+    #[test]
+    fn zones_follow_output_past_the_history_limit() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        command(&mut term, 3000);
+        // Enough to fill history several times over, in reads of all sizes
+        for lines in [1, 700, 4096, 20_000] {
+            command(&mut term, lines);
+        }
+        command(&mut term, 2500);
+        assert_eq!(term.history_size(), SCROLLBACK_LINES);
+
+        // Only the last prompt is left, 2500 lines of output above the cursor
+        let prompt = zone_line(&term, SemanticZone::Input).unwrap();
+        assert_eq!(prompt, term.cursor().point.line.0 - 2501);
+        assert_eq!(term.get_line_zone(prompt + 1), SemanticZone::Output);
+        let lines = term.all_lines_text();
+        let (_, text) = lines.iter().find(|(line, _)| *line == prompt).unwrap();
+        assert_eq!(text, "$ seq");
+    }
+
+    #[test]
+    fn zones_follow_scrolls_past_the_history_limit() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        command(&mut term, 9000);
+        command(&mut term, 0);
+        // A screenful at a time, past the history limit, in one read
+        term.process_input("\x1b[24S".repeat(100).as_bytes());
+        assert_eq!(term.history_size(), SCROLLBACK_LINES);
+
+        // Only the last prompt is left, and still on its line
+        let prompt = zone_line(&term, SemanticZone::Input).unwrap();
+        let lines = term.all_lines_text();
+        let (_, text) = lines.iter().find(|(line, _)| *line == prompt).unwrap();
+        assert_eq!(text, "$ seq");
+    }
+
+    #[test]
+    fn clearing_history_drops_its_zones() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        command(&mut term, 100);
+        command(&mut term, 3);
+        let input = |term: &Terminal| zone_line(term, SemanticZone::Input);
+        assert!(input(&term).unwrap() < 0);
+
+        // The screen keeps the zones shown on it
+        term.clear_history();
+        assert_eq!(term.history_size(), 0);
+        assert_eq!(input(&term), Some(23 - 3 - 1));
+        assert!(term.has_semantic_zones());
+
+        // A clear from the program, with output after it in the same read
+        command(&mut term, 100);
+        term.process_input(b"\x1b[3J\x1b]133;A\x07$ \r\n\r\n");
+        assert_eq!(input(&term), None);
+        assert_eq!(zone_line(&term, SemanticZone::Prompt), Some(21));
+        term.process_input("\r\n".repeat(30).as_bytes());
+        assert_eq!(zone_line(&term, SemanticZone::Prompt), Some(21 - 30));
+
+        // A reset clears the screen too
+        term.process_input(b"\x1bc");
+        assert_eq!(zone_line(&term, SemanticZone::Prompt), None);
+    }
+
+    #[test]
+    fn commands_recorded_from_marks() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        term.process_input(b"\x1b]7;file:///src\x07\x1b]133;A\x07$ \x1b]133;B\x07cargo test\r\n");
+        term.process_input(b"\x1b]133;C\x07running\r\nfailed\r\n");
+        let running = term.commands().pop().unwrap();
+        assert!(running.is_running());
+        assert_eq!(running.output, 1..3);
+
+        term.process_input(b"\x1b]133;D;101\x07\x1b]133;A\x07$ \x1b]133;B\x07");
+        // A long command line wraps; an empty one never runs
+        let long = format!("echo {}", "y".repeat(100));
+        term.process_input(format!("{long}\r\n\x1b]133;C\x07\x1b]133;D\x07").as_bytes());
+        term.process_input(b"\x1b]133;A\x07$ \x1b]133;B\x07\r\n\x1b]133;D;0\x07");
+
+        let commands = term.commands();
+        assert_eq!(commands.len(), 2);
+        let (cargo, echo) = (&commands[0], &commands[1]);
+        assert_eq!((cargo.prompt_line, &*cargo.command), (0, "cargo test"));
+        assert_eq!((cargo.exit_code, cargo.output.clone()), (Some(101), 1..3));
+        assert_eq!(cargo.cwd, FileUri::parse("file:///src"));
+        assert!(cargo.finished >= Some(cargo.started));
+        assert_eq!((echo.prompt_line, &echo.command), (3, &long));
+        assert_eq!((echo.exit_code, echo.output.clone()), (None, 5..5));
+    }
+
+    #[test]
+    fn prompt_lines_and_output_text() {
+        let mut term = Terminal::new(Size::new(10, 24));
+        term.process_input(b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07");
+        term.process_input(b"a.txt\r\nsome-long-name\r\n\x1b]133;D;0\x07");
+        term.process_input(b"\x1b]133;A\x07$ \x1b]133;B\x07");
+        assert_eq!(term.prompt_lines(), vec![0, 4]);
+
+        // The wrapped file name comes back as one line
+        let output = term.commands()[0].output.clone();
+        assert_eq!(output, 1..4);
+        assert_eq!(term.lines_text(output), "a.txt\nsome-long-name");
+        assert_eq!(term.lines_text(-5..1), "$ ls");
+    }
+
+    /// A finished command with numbered output, then the next prompt
+    fn numbered_command(term: &mut Terminal, output_lines: usize) {
+        command(term, 0);
+        for line in 0..output_lines {
+            term.process_input(format!("out {line}\r\n").as_bytes());
+        }
+        term.process_input(b"\x1b]133;D;0\x07\x1b]133;A\x07$ ");
+    }
+
+    #[test]
+    fn folded_output_shows_as_one_row() {
+        let mut term = Terminal::new(Size::new(20, 10));
+        numbered_command(&mut term, 30);
+        let record = term.commands().pop().unwrap();
+        let text = term.lines_text(record.output.clone());
+        assert_eq!(term.history_size(), 22);
+        assert!(!term.fold_command(record.prompt_line + 1, "no command here"));
+
+        // The prompt stays selected as the output closes up below it
+        let prompt = record.prompt_line;
+        term.start_selection(Point::new(Line(prompt), Column(0)), SelectionType::Simple);
+        term.update_selection(Point::new(Line(prompt), Column(4)));
+        assert!(term.fold_command(prompt, "seq: 30 lines"));
+        assert!(!term.fold_command(prompt, "seq: 30 lines"));
+
+        // History had 22 of the 29 rows to give up; the rest stay blank on top
+        assert_eq!(term.history_size(), 0);
+        let folded = term.commands().pop().unwrap();
+        assert!(folded.folded);
+        assert_eq!((folded.prompt_line, folded.output.clone()), (7, 8..9));
+        assert_eq!(term.prompt_lines(), vec![7, 9]);
+        assert!(term.is_folded_line(8) && !term.is_folded_line(7));
+        assert_eq!(term.lines_text(7..8), "$ seq");
+        assert_eq!(term.lines_text(8..9), text);
+        assert_eq!(term.get_line_zone(8), SemanticZone::Output);
+        assert_eq!(term.selection_to_string().as_deref(), Some("$ seq"));
+        let lines = term.all_lines_text();
+        assert_eq!(lines[8], (8, "seq: 30 lines".to_string()));
+
+        assert!(term.unfold_command(7));
+        assert!(!term.unfold_command(-22));
+        let record = term.commands().pop().unwrap();
+        assert!(!record.folded);
+        assert_eq!((record.prompt_line, record.output.clone()), (-22, -21..9));
+        assert_eq!(term.lines_text(record.output), text);
+        assert_eq!(term.get_line_zone(-21), SemanticZone::Output);
+        assert_eq!(term.selection_to_string().as_deref(), Some("$ seq"));
+    }
+
+    #[test]
+    fn folds_keep_the_view_and_count_toward_history() {
+        let mut term = Terminal::new(Size::new(20, 10));
+        numbered_command(&mut term, 100);
+        numbered_command(&mut term, 3000);
+        term.process_input(b"\r\n".repeat(100).as_slice());
+        let prompt = |term: &Terminal| term.commands().pop().unwrap().prompt_line;
+
+        // Scrolled back to the prompt, which stays at the top
+        term.scroll(Scroll::Delta(-prompt(&term)));
+        assert!(term.fold_command(prompt(&term), "seq: 3000 lines"));
+        assert_eq!(term.display_offset() as i32, -prompt(&term));
+        assert!(term.unfold_command(prompt(&term)));
+        assert_eq!(term.display_offset() as i32, -prompt(&term));
+
+        // Folded rows are held against the history limit, so output that
+        // fills it trims the lines above the fold...
+        term.scroll_to_bottom();
+        assert!(term.fold_command(prompt(&term), "seq: 3000 lines"));
+        let room = SCROLLBACK_LINES - 2999 - term.history_size();
+        term.process_input(b"x\r\n".repeat(room + 50).as_slice());
+        assert_eq!(term.history_size(), SCROLLBACK_LINES - 2999);
+        assert!(term.commands().pop().unwrap().folded);
+
+        // ...and unfolding fits in the room they took
+        assert!(term.unfold_command(prompt(&term)));
+        assert_eq!(term.history_size(), SCROLLBACK_LINES);
+        let record = term.commands().pop().unwrap();
+        assert_eq!(record.output.len(), 3000);
+        let last = record.output.end - 1..record.output.end;
+        assert_eq!(term.lines_text(last), "out 2999");
+    }
+
+    #[test]
+    fn folds_survive_resize() {
+        let mut term = Terminal::new(Size::new(20, 10));
+        numbered_command(&mut term, 30);
+        let record = term.commands().pop().unwrap();
+        let text = term.lines_text(record.output);
+        assert!(term.fold_command(record.prompt_line, "seq: 30 lines"));
+
+        term.resize(Size::new(40, 12));
+        let record = term.commands().pop().unwrap();
+        assert!(record.folded);
+        assert_eq!(term.lines_text(record.output.clone()), text);
+        assert!(term.is_folded_line(record.output.start));
+        assert!(term.unfold_command(record.prompt_line));
+        let record = term.commands().pop().unwrap();
+        assert_eq!(term.lines_text(record.output), text);
+
+        // A reset drops the folds with everything else
+        assert!(term.fold_command(record.prompt_line, "seq: 30 lines"));
+        term.process_input(b"\x1bc");
+        assert!((0..12).all(|line| !term.is_folded_line(line)));
+    }
+
+    fn point(line: i32, column: usize) -> Point {
+        Point::new(Line(line), Column(column))
+    }
+
+    #[test]
+    fn search_spans_wrapped_rows_and_follows_scrolling() {
+        let mut term = Terminal::new(Size::new(10, 4));
+        let mut search = Search::new("hello", SearchOptions::default()).unwrap();
+        // "hello" wraps from the first row onto the second
+        term.process_input(b"1234567HELlo\r\n");
+        assert_eq!(term.update_search(&mut search), 0);
+        assert_eq!(
+            term.search_matches(&search),
+            vec![point(0, 7)..=point(1, 1)]
+        );
+
+        // New output is searched as it arrives; earlier matches scroll with it
+        term.process_input(b"hello\r\n\r\n\r\n");
+        assert_eq!(term.update_search(&mut search), 0);
+        assert_eq!(
+            term.search_matches(&search),
+            vec![point(-2, 7)..=point(-1, 1), point(0, 0)..=point(0, 4)]
+        );
+
+        // Matches leave with the history they were found in
+        term.clear_history();
+        assert_eq!(term.update_search(&mut search), 1);
+        assert_eq!(term.search_matches(&search).len(), 1);
+    }
+
+    #[test]
+    fn search_options_narrow_matches() {
+        let mut term = Terminal::new(Size::new(40, 4));
+        term.process_input(b"Error errors error_log error.");
+        let matches = |query: &str, options| {
+            let mut search = Search::new(query, options).unwrap();
+            term.update_search(&mut search);
+            let found = term.search_matches(&search);
+            found.iter().map(|m| m.start().column.0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(matches("error", SearchOptions::default()), [0, 6, 13, 23]);
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(matches("error", case_sensitive), [6, 13, 23]);
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(matches("error", whole_word), [0, 23]);
+        let regex = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(matches(r"error\w+", regex), [6, 13]);
+    }
+
+    #[test]
+    fn search_starts_over_when_lines_move() {
+        let mut term = Terminal::new(Size::new(20, 10));
+        numbered_command(&mut term, 30);
+        let mut search = Search::new("out 1", SearchOptions::default()).unwrap();
+        term.update_search(&mut search);
+        // out 1 and out 10 to out 19
+        assert_eq!(term.search_matches(&search).len(), 11);
+
+        let record = term.commands().pop().unwrap();
+        assert!(term.fold_command(record.prompt_line, "seq: 30 lines"));
+        term.update_search(&mut search);
+        assert!(term.search_matches(&search).is_empty());
+        let record = term.commands().pop().unwrap();
+        assert!(term.unfold_command(record.prompt_line));
+        term.update_search(&mut search);
+        assert_eq!(term.search_matches(&search).len(), 11);
+    }
+
+    #[test]
+    fn commands_follow_output_into_history() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        for lines in [10, 3000, 12_000, 2000] {
+            command(&mut term, lines);
+            term.process_input(b"\x1b]133;D;0\x07");
+        }
+
+        // Only the last prompt is still in history, its output below it
+        let commands = term.commands();
+        assert_eq!(commands.len(), 1);
+        let record = &commands[0];
+        assert_eq!(record.output.start, record.prompt_line + 1);
+        assert_eq!(record.output.len(), 2000);
+        let lines = term.all_lines_text();
+        let text = |line| &lines.iter().find(|(at, _)| *at == line).unwrap().1;
+        assert_eq!(text(record.prompt_line), "$ seq");
+        assert_eq!(text(record.output.end - 1), "line");
+        assert_eq!(text(record.output.end), "");
+    }
+
+    #[test]
+    fn zones_follow_text_as_resize_reflows_it() {
+        let mut term = Terminal::new(Size::new(20, 10));
+        for _ in 0..3 {
+            command(&mut term, 0);
+            term.process_input(format!("{}\r\n", "x".repeat(50)).as_bytes());
+        }
+        let zones_on = |term: &Terminal, text: &str| {
+            term.all_lines_text()
+                .into_iter()
+                .filter(|(_, line)| line == text)
+                .map(|(line, _)| term.get_line_zone(line))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(zones_on(&term, "$ seq"), vec![SemanticZone::Input; 3]);
+
+        // Wider: each long line joins back into one row
+        let output = "x".repeat(50);
+        term.resize(Size::new(80, 10));
+        assert_eq!(zones_on(&term, "$ seq"), vec![SemanticZone::Input; 3]);
+        assert_eq!(zones_on(&term, &output), vec![SemanticZone::Output; 3]);
+
+        // Narrower than the prompt line itself
+        term.resize(Size::new(4, 10));
+        assert_eq!(zones_on(&term, "$ se"), vec![SemanticZone::Input; 3]);
+        assert_eq!(zones_on(&term, "q"), vec![SemanticZone::Unknown; 3]);
+    }
+
+    #[test]
+    fn osc7_records_host_and_path() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        assert!(term.reported_cwd().is_none());
+
+        term.process_input(b"\x1b]7;file://devbox/home/me/src\x07$ ");
+
+        let cwd = term.reported_cwd().unwrap();
+        assert_eq!(cwd.host.as_deref(), Some("devbox"));
+        assert_eq!(cwd.path, std::path::PathBuf::from("/home/me/src"));
+    }
+
+    #[test]
+    fn osc7_with_st_and_percent_encoding() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        term.process_input(b"\x1b]7;file:///tmp/my%20dir\x1b\\");
+
+        let cwd = term.reported_cwd().unwrap();
+        assert_eq!(cwd.host, None);
+        assert_eq!(cwd.path, std::path::PathBuf::from("/tmp/my dir"));
+    }
+
+    #[test]
+    fn osc7_latest_report_wins() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        term.process_input(b"\x1b]7;file://h/one\x07\x1b]7;file://h/two\x07");
+        term.process_input(b"\x1b]7;not-a-uri\x07");
+
+        assert_eq!(
+            term.reported_cwd().map(|cwd| cwd.path.clone()),
+            Some(std::path::PathBuf::from("/two"))
+        );
+    }
+
+    #[test]
+    fn osc7_split_across_reads() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        term.process_input(b"\x1b]7;file://h/ho");
+        assert!(term.reported_cwd().is_none());
+        term.process_input(b"me/me\x1b");
+        term.process_input(b"\\$ ");
+
+        assert_eq!(
+            term.reported_cwd().map(|cwd| cwd.path.clone()),
+            Some(std::path::PathBuf::from("/home/me"))
+        );
+    }
+
+    #[test]
+    fn osc7_recorded_with_the_prompt_after_it() {
+        let mut term = Terminal::new(Size::new(80, 24));
+
+        // cd runs, then the shell reports the new directory before its prompt
+        term.process_input(
+            b"\x1b]7;file://h/one\x07\x1b]133;A\x07$ \x1b]133;B\x07cd /two\r\n\x1b]133;C\x07\
+              \x1b]133;D;0\x07\x1b]7;file://h/two\x07\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07",
+        );
+
+        let cwds: Vec<_> = term
+            .commands()
+            .into_iter()
+            .map(|command| command.cwd.map(|cwd| cwd.path))
+            .collect();
+        assert_eq!(
+            cwds,
+            vec![
+                Some(std::path::PathBuf::from("/one")),
+                Some(std::path::PathBuf::from("/two"))
+            ]
+        );
+    }
+
+    #[test]
+    fn file_uri_locality() {
+        let local = FileUri::parse("file://localhost/x").unwrap();
+        let empty = FileUri::parse("file:///x").unwrap();
+        let named = FileUri::parse("file://box/x").unwrap();
+        assert!(local.is_local(None));
+        assert!(empty.is_local(None));
+        assert!(named.is_local(Some("box")));
+        assert!(!named.is_local(Some("other")));
+        assert!(FileUri::parse("https://box/x").is_none());
+    }
+
     #[test]
     fn shell_events_clear_after_take() {
         let mut term = Terminal::new(Size::new(80, 24));
@@ -990,22 +2498,29 @@ mod tests {
         assert_eq!(events[1], ShellEvent::CommandFail(1));
     }
 
+    /// Process ID the mock reports for its shell
+    const SHELL_PID: u32 = 100;
+
     /// Mock PTY backend for deterministic testing without real shell processes
     pub struct MockPty {
-        output_queue: std::collections::VecDeque<Vec<u8>>,
+        output_queue: std::cell::RefCell<std::collections::VecDeque<Vec<u8>>>,
         captured_input: std::cell::RefCell<Vec<u8>>,
-        last_resize: std::cell::Cell<Option<(u16, u16)>>,
+        last_resize: std::cell::Cell<Option<Size>>,
         shutdown_called: std::cell::Cell<bool>,
+        exit_code: std::cell::Cell<Option<i32>>,
+        foreground: std::cell::RefCell<Option<ForegroundProcess>>,
     }
 
     impl MockPty {
         /// Create a MockPty with pre-loaded output chunks
         pub fn with_output(chunks: Vec<Vec<u8>>) -> Self {
             Self {
-                output_queue: chunks.into(),
+                output_queue: std::cell::RefCell::new(chunks.into()),
                 captured_input: std::cell::RefCell::new(Vec::new()),
                 last_resize: std::cell::Cell::new(None),
                 shutdown_called: std::cell::Cell::new(false),
+                exit_code: std::cell::Cell::new(None),
+                foreground: std::cell::RefCell::new(None),
             }
         }
 
@@ -1015,24 +2530,26 @@ mod tests {
         }
     }
 
+    /// Color resolver for tests that don't ask for colors
+    fn no_colors(_: usize, _: &Colors) -> Option<Rgb> {
+        None
+    }
+
     impl PtyBackend for MockPty {
         fn write(&self, data: &[u8]) {
             self.captured_input.borrow_mut().extend_from_slice(data);
         }
 
         fn try_read(&self) -> Option<Vec<u8>> {
-            // MockPty uses interior mutability pattern for read — but VecDeque needs &mut
-            // Since this is test-only, we use unsafe to match the non-mutable trait signature
-            None // try_read not needed for basic mock
+            self.output_queue.borrow_mut().pop_front()
         }
 
         fn read_available(&self) -> Vec<u8> {
-            // Return empty for basic mock — output is fed directly via terminal in tests
-            Vec::new()
+            self.output_queue.borrow_mut().drain(..).flatten().collect()
         }
 
-        fn resize(&self, cols: u16, rows: u16) {
-            self.last_resize.set(Some((cols, rows)));
+        fn resize(&self, size: Size) {
+            self.last_resize.set(Some(size));
         }
 
         fn shutdown(&self) {
@@ -1040,12 +2557,20 @@ mod tests {
         }
 
         fn process_id(&self) -> Option<u32> {
-            None
+            Some(SHELL_PID)
         }
 
         fn working_directory(&self) -> Option<std::path::PathBuf> {
             None
         }
+
+        fn try_wait(&self) -> Option<i32> {
+            self.exit_code.get()
+        }
+
+        fn foreground_process(&self) -> Option<ForegroundProcess> {
+            self.foreground.borrow().clone()
+        }
     }
 
     #[test]
@@ -1053,11 +2578,12 @@ mod tests {
         let mock = MockPty::with_output(vec![]);
         let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
 
-        // Feed output directly to the terminal (bypassing PTY, as mock read_available returns empty)
+        // Feed output directly to the terminal (bypassing the PTY output queue)
         term.terminal_mut().process_input(b"Hello, world!\r\n");
 
         // Verify terminal content
-        let content = term.terminal().renderable_content();
+        let terminal = term.terminal();
+        let content = terminal.renderable_content();
         let first_line: String = content
             .display_iter
             .take_while(|cell| cell.point.line.0 == 0)
@@ -1070,6 +2596,7 @@ mod tests {
             "Expected 'Hello, world!' in first line, got: '{}'",
             first_line
         );
+        drop(terminal);
 
         // Verify input capture
         term.send_input(b"ls\n");
@@ -1077,6 +2604,320 @@ mod tests {
 
         // Verify resize tracking
         term.resize(Size::new(120, 40));
-        assert_eq!(term.pty.last_resize.get(), Some((120, 40)));
+        assert_eq!(term.pty.last_resize.get(), Some(Size::new(120, 40)));
+    }
+
+    #[test]
+    fn cursor_position_report_written_to_pty() {
+        // Move the cursor to row 5, column 10, then ask for its position
+        let mock = MockPty::with_output(vec![b"\x1b[5;10H".to_vec(), b"\x1b[6n".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        assert!(term.process_pty_output());
+        assert!(term.pty.captured_input().is_empty());
+
+        term.take_shell_events(no_colors);
+        assert_eq!(term.pty.captured_input(), b"\x1b[5;10R");
+    }
+
+    #[test]
+    fn device_status_report_written_to_pty() {
+        let mock = MockPty::with_output(vec![b"\x1b[5n".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        term.check_events();
+        assert_eq!(term.pty.captured_input(), b"\x1b[0n");
+    }
+
+    #[test]
+    fn device_attributes_written_to_pty() {
+        let mock = MockPty::with_output(vec![b"\x1b[c".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        let (events, title) = term.take_shell_events(no_colors);
+        assert!(events.is_empty());
+        assert!(title.is_none());
+        assert_eq!(term.pty.captured_input(), b"\x1b[?6c");
+    }
+
+    #[test]
+    fn replies_preserve_order_and_other_events() {
+        let mock = MockPty::with_output(vec![
+            b"\x1b]0;title\x07\x1b[6n\x07\x1b[2;3H\x1b[6n".to_vec(),
+        ]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        let (events, title) = term.take_shell_events(no_colors);
+        assert_eq!(events, vec![ShellEvent::Bell]);
+        assert_eq!(title.as_deref(), Some("title"));
+        assert_eq!(term.pty.captured_input(), b"\x1b[1;1R\x1b[2;3R");
+    }
+
+    #[test]
+    fn osc52_store_collected_as_clipboard_request() {
+        // "hello" base64-encoded
+        let mock = MockPty::with_output(vec![b"\x1b]52;c;aGVsbG8=\x07".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        term.take_shell_events(no_colors);
+        let requests = term.take_clipboard_requests();
+        assert_eq!(requests.len(), 1);
+        match &requests[0] {
+            ClipboardRequest::Store { clipboard, text } => {
+                assert_eq!(*clipboard, ClipboardType::Clipboard);
+                assert_eq!(text, "hello");
+            }
+            other => panic!("Expected Store, got {:?}", other),
+        }
+        assert!(term.take_clipboard_requests().is_empty());
+    }
+
+    #[test]
+    fn osc52_load_formats_reply() {
+        let mock = MockPty::with_output(vec![b"\x1b]52;c;?\x07".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        term.take_shell_events(no_colors);
+        let requests = term.take_clipboard_requests();
+        match &requests[..] {
+            [ClipboardRequest::Load { formatter, .. }] => {
+                assert_eq!(formatter("hello"), "\x1b]52;c;aGVsbG8=\x07");
+            }
+            other => panic!("Expected a single Load, got {:?}", other),
+        }
+        // Nothing is written until the application answers
+        assert!(term.pty.captured_input().is_empty());
+    }
+
+    #[test]
+    fn color_queries_answered_in_order_with_other_replies() {
+        let mock = MockPty::with_output(vec![
+            b"\x1b]11;?\x07\x1b[c\x1b]10;?\x1b\\\x1b]4;1;?\x07\x1b]4;2;?\x07".to_vec(),
+        ]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        let blue = Rgb {
+            r: 0x12,
+            g: 0x34,
+            b: 0xab,
+        };
+        // Palette entry 2 goes unanswered
+        term.take_shell_events(|index, _| (index != 2).then_some(blue));
+        let replies = String::from_utf8(term.pty.captured_input()).unwrap();
+        assert_eq!(
+            replies,
+            "\x1b]11;rgb:1212/3434/abab\x07\x1b[?6c\x1b]10;rgb:1212/3434/abab\x1b\\\
+             \x1b]4;1;rgb:1212/3434/abab\x07"
+        );
+    }
+
+    fn has_text(term: &Terminal, text: &str) -> bool {
+        term.all_lines_text()
+            .iter()
+            .any(|(_, line)| line.contains(text))
+    }
+
+    #[test]
+    fn sync_update_applied_at_end() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        term.process_input(b"\x1b[?2026hhalf drawn");
+        assert!(term.in_sync_update());
+        assert!(!has_text(&term, "half drawn"));
+
+        term.process_input(b" screen\x1b[?2026l");
+        assert!(!term.in_sync_update());
+        assert!(has_text(&term, "half drawn screen"));
+    }
+
+    #[test]
+    fn sync_update_applied_after_deadline() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        term.process_input(b"\x1b[?2026hnever ended");
+        assert!(!term.end_expired_sync_update());
+        assert!(!has_text(&term, "never ended"));
+
+        let deadline = term.sync_update_deadline().unwrap();
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        assert!(term.end_expired_sync_update());
+        assert!(!term.in_sync_update());
+        assert!(has_text(&term, "never ended"));
+    }
+
+    #[test]
+    fn sync_update_mode_reported_to_decrqm() {
+        let mock = MockPty::with_output(vec![b"\x1b[?2026$p".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        term.check_events();
+        // 2 = recognized and currently reset
+        assert_eq!(term.pty.captured_input(), b"\x1b[?2026;2$y");
+    }
+
+    #[test]
+    fn focus_reported_only_when_requested() {
+        let mock = MockPty::with_output(vec![b"\x1b[?1004h".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+        term.report_focus(true);
+        assert!(term.pty.captured_input().is_empty());
+
+        term.process_pty_output();
+        term.report_focus(false);
+        term.report_focus(true);
+        assert_eq!(term.pty.captured_input(), b"\x1b[O\x1b[I");
+    }
+
+    #[test]
+    fn window_size_queries_answered() {
+        let mock = MockPty::with_output(vec![b"\x1b[14t\x1b[16t\x1b[18t".to_vec()]);
+        let size = Size::new(80, 24).with_cell_size(9.4, 18.0);
+        let mut term = ShellTerminalGeneric::with_backend(size, mock);
+        term.process_pty_output();
+        term.take_shell_events(no_colors);
+        let replies = String::from_utf8(term.pty.captured_input()).unwrap();
+        // Text area in pixels, cell size, then cells, in the order asked
+        assert_eq!(replies, "\x1b[4;432;752t\x1b[6;18;9t\x1b[8;24;80t");
+
+        term.resize(size);
+        assert_eq!(term.pty.last_resize.get(), Some(size));
+        assert_eq!((size.pixel_width(), size.pixel_height()), (752, 432));
+    }
+
+    #[test]
+    fn kitty_keyboard_flags_per_screen() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        term.process_input(b"\x1b[>1u");
+        assert!(term.mode().contains(TermMode::DISAMBIGUATE_ESC_CODES));
+
+        // The alternate screen has a stack of its own
+        term.process_input(b"\x1b[?1049h");
+        assert!(!term.mode().intersects(TermMode::KITTY_KEYBOARD_PROTOCOL));
+        term.process_input(b"\x1b[>11u");
+        assert!(term.mode().contains(
+            TermMode::DISAMBIGUATE_ESC_CODES
+                | TermMode::REPORT_EVENT_TYPES
+                | TermMode::REPORT_ALL_KEYS_AS_ESC
+        ));
+
+        term.process_input(b"\x1b[<u\x1b[?1049l");
+        assert_eq!(
+            term.mode() & TermMode::KITTY_KEYBOARD_PROTOCOL,
+            TermMode::DISAMBIGUATE_ESC_CODES
+        );
+        term.process_input(b"\x1b[<u");
+        assert!(!term.mode().intersects(TermMode::KITTY_KEYBOARD_PROTOCOL));
+    }
+
+    #[test]
+    fn kitty_keyboard_flags_reported_to_query() {
+        let mock = MockPty::with_output(vec![b"\x1b[>5u\x1b[?u".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        term.process_pty_output();
+        term.check_events();
+        assert_eq!(term.pty.captured_input(), b"\x1b[?5u");
+    }
+
+    #[test]
+    fn kitty_keyboard_stack_overflow_is_contained() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        term.process_input(&b"\x1b[>1u".repeat(5000));
+        assert!(term.mode().contains(TermMode::DISAMBIGUATE_ESC_CODES));
+
+        term.process_input(&b"\x1b[<u".repeat(5000));
+        assert!(!term.mode().intersects(TermMode::KITTY_KEYBOARD_PROTOCOL));
+    }
+
+    #[test]
+    fn runtime_colors_set_and_reset() {
+        let mut term = Terminal::new(Size::new(80, 24));
+        assert!(term.colors()[1].is_none());
+
+        term.process_input(b"\x1b]4;1;rgb:12/34/56\x07\x1b]11;#abcdef\x07");
+        assert_eq!(
+            term.colors()[1],
+            Some(Rgb {
+                r: 0x12,
+                g: 0x34,
+                b: 0x56
+            })
+        );
+        assert!(term.colors()[NamedColor::Background].is_some());
+
+        // OSC 104 resets palette entries, OSC 111 the background
+        term.process_input(b"\x1b]104;1\x07");
+        assert!(term.colors()[1].is_none());
+        term.process_input(b"\x1b]111\x07");
+        assert!(term.colors()[NamedColor::Background].is_none());
+
+        term.process_input(b"\x1b]4;2;rgb:ff/00/00\x07\x1b]12;#00ff00\x07");
+        term.reset_colors();
+        assert!(term.colors()[2].is_none());
+        assert!(term.colors()[NamedColor::Cursor].is_none());
+    }
+
+    #[test]
+    fn child_exit_reported_once_after_output_drained() {
+        let mock = MockPty::with_output(vec![b"bye\r\n".to_vec()]);
+        mock.exit_code.set(Some(2));
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+
+        // Pending output is processed before the exit is noticed
+        assert!(term.process_pty_output());
+        term.take_shell_events(no_colors);
+        assert_eq!(term.take_child_exit(), None);
+        assert_eq!(term.exit_code(), None);
+
+        assert!(!term.process_pty_output());
+        term.take_shell_events(no_colors);
+        assert_eq!(term.take_child_exit(), Some(2));
+        assert_eq!(term.exit_code(), Some(2));
+
+        term.process_pty_output();
+        term.take_shell_events(no_colors);
+        assert_eq!(term.take_child_exit(), None);
+    }
+
+    fn process(pid: u32, name: &str) -> ForegroundProcess {
+        ForegroundProcess {
+            pid,
+            name: name.to_string(),
+            cmdline: vec![name.to_string()],
+        }
+    }
+
+    #[test]
+    fn foreground_job_ignores_idle_shell() {
+        let mock = MockPty::with_output(vec![]);
+        *mock.foreground.borrow_mut() = Some(process(SHELL_PID, "bash"));
+        let term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+        assert_eq!(term.foreground_process().unwrap().name, "bash");
+        assert_eq!(term.foreground_job(), None);
+
+        *term.pty.foreground.borrow_mut() = Some(process(200, "vim"));
+        assert_eq!(term.foreground_job().unwrap().name, "vim");
+    }
+
+    #[test]
+    fn title_tracks_set_and_reset() {
+        let mock = MockPty::with_output(vec![b"\x1b]2;build\x07".to_vec()]);
+        let mut term = ShellTerminalGeneric::with_backend(Size::new(80, 24), mock);
+        assert_eq!(term.title(), None);
+
+        term.process_pty_output();
+        let (_, title) = term.take_shell_events(no_colors);
+        assert_eq!(title.as_deref(), Some("build"));
+        assert_eq!(term.title(), Some("build"));
+
+        // An empty title resets it
+        term.terminal_mut().process_input(b"\x1b]2;\x07");
+        term.take_shell_events(no_colors);
+        assert_eq!(term.title(), None);
     }
 }
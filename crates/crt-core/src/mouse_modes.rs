@@ -0,0 +1,84 @@
+//! Mouse modes alacritty_terminal doesn't track
+//!
+//! alacritty_terminal follows click, drag and motion tracking (1000/1002/1003)
+//! and the UTF-8 and SGR encodings (1005/1006), but drops X10 compatibility
+//! mode (9), the urxvt encoding (1015) and SGR-pixels (1016) as unknown.
+//! [`MouseModes`] records those three from the `CSI ? Pm h` / `CSI ? Pm l`
+//! sequences the terminal's [`crate::scanner::Scanner`] finds, so the frontend
+//! can honor them.
+
+use crate::scanner::Csi;
+
+/// Mouse modes set by the program beyond those in [`crate::TermMode`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MouseModes {
+    /// X10 compatibility (9): button presses only, without modifiers
+    pub x10: bool,
+    /// urxvt encoding (1015): `CSI b ; x ; y M` with decimal coordinates
+    pub urxvt: bool,
+    /// SGR-pixels encoding (1016): SGR reports in pixels rather than cells
+    pub sgr_pixels: bool,
+}
+
+impl MouseModes {
+    /// Apply a DEC private mode set or reset
+    pub(crate) fn apply(&mut self, csi: &Csi) {
+        let set = match (csi.marker, csi.action) {
+            (Some(b'?'), b'h') => true,
+            (Some(b'?'), b'l') => false,
+            _ => return,
+        };
+        for &mode in csi.params() {
+            match mode {
+                9 => self.x10 = set,
+                1015 => self.urxvt = set,
+                1016 => self.sgr_pixels = set,
+                // Tracking modes replace one another, X10 included
+                1000 | 1002 | 1003 if set => self.x10 = false,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{Scanner, Sequence};
+
+    fn scan(bytes: &[u8]) -> MouseModes {
+        let mut scanner = Scanner::default();
+        let mut modes = MouseModes::default();
+        for &byte in bytes {
+            match scanner.advance(byte) {
+                Some(Sequence::Csi(csi)) => modes.apply(csi),
+                Some(Sequence::Reset) => modes = MouseModes::default(),
+                Some(Sequence::Osc(_)) | None => {}
+            }
+        }
+        modes
+    }
+
+    #[test]
+    fn sets_and_resets_modes() {
+        let modes = scan(b"\x1b[?1000;1015h\x1b[?1016h");
+        assert!(modes.urxvt && modes.sgr_pixels && !modes.x10);
+
+        let modes = scan(b"\x1b[?1015;1016h\x1b[?1016l");
+        assert!(modes.urxvt && !modes.sgr_pixels);
+    }
+
+    #[test]
+    fn tracking_modes_replace_x10() {
+        assert!(scan(b"\x1b[?9h").x10);
+        assert!(!scan(b"\x1b[?9h\x1b[?1002h").x10);
+        assert!(!scan(b"\x1b[?9h\x1b[?9l").x10);
+    }
+
+    #[test]
+    fn ignores_other_sequences() {
+        // ANSI mode, intermediate, and a subparameter
+        let modes = scan(b"\x1b[1016h\x1b[?1016$h\x1b[?1:1016h");
+        assert_eq!(modes, MouseModes::default());
+    }
+}
@@ -0,0 +1,234 @@
+//! OSC 133 semantic prompt parser
+//!
+//! Shell integrations mark prompts, input and command output with
+//! `OSC 133 ; <command> [; <param>]... ST`. alacritty_terminal's parser drops
+//! these, so [`parse`] reads them from the OSC strings the terminal's
+//! [`crate::scanner::Scanner`] finds beside it.
+//!
+//! Supported commands:
+//! - `A` prompt start, `P` prompt line (`k=` kind), `L` fresh line
+//! - `B` input start, `C` output start, `D[;exit]` command end
+//!
+//! `aid=` (application id) and `cl=` (click-to-move) options are kept with
+//! the prompt; unknown options and commands are ignored.
+
+const PREFIX: &[u8] = b"133;";
+
+/// Kind of prompt a mark starts (`k=`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptKind {
+    /// The main prompt (`k=i`, the default)
+    #[default]
+    Initial,
+    /// Continuation prompt, e.g. `PS2` (`k=c`)
+    Continuation,
+    /// Secondary prompt for an unfinished command (`k=s`)
+    Secondary,
+    /// Prompt drawn at the right edge of the input line (`k=r`)
+    Right,
+}
+
+/// How a click in the prompt's input may move the cursor (`cl=`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickMove {
+    /// Left/right arrows within the line (`cl=line`)
+    Line,
+    /// Left/right arrows across lines (`cl=m`)
+    MultiLine,
+    /// Up/down arrows, conservatively (`cl=v`)
+    ConservativeVertical,
+    /// Up/down arrows, where the shell handles wrapping (`cl=w`)
+    SmartVertical,
+}
+
+/// Options given with a prompt mark
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptOptions {
+    /// Application id, so nested shells can tell their marks apart (`aid=`)
+    pub aid: Option<String>,
+    /// Cursor movement a click may request (`cl=`)
+    pub click: Option<ClickMove>,
+    pub kind: PromptKind,
+}
+
+/// A complete OSC 133 mark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PromptMark {
+    /// `A`: a new prompt begins
+    PromptStart(PromptOptions),
+    /// `P`: another line of the prompt begins
+    PromptLine(PromptOptions),
+    /// `L`: move to a new line unless already at the start of one
+    FreshLine,
+    /// `B`: the prompt ends and user input begins
+    InputStart,
+    /// `C`: the command runs and its output begins
+    OutputStart,
+    /// `D`: the command finished
+    CommandEnd { exit_code: Option<i32> },
+}
+
+/// Mark for an OSC string's payload, if it's a valid OSC 133
+pub(crate) fn parse(osc: &[u8]) -> Option<PromptMark> {
+    let payload = osc.strip_prefix(PREFIX)?;
+    let mark = parse_payload(payload);
+    if mark.is_none() {
+        log::debug!("OSC 133: ignoring {:?}", String::from_utf8_lossy(payload));
+    }
+    mark
+}
+
+/// Parse what follows `133;`
+fn parse_payload(payload: &[u8]) -> Option<PromptMark> {
+    let payload = std::str::from_utf8(payload).ok()?;
+    let mut params = payload.split(';');
+    let command = params.next()?;
+
+    let mut options = PromptOptions::default();
+    let mut exit_code = None;
+    for param in params {
+        match param.split_once('=') {
+            Some(("aid", aid)) => options.aid = Some(aid.to_string()),
+            Some(("cl", click)) => options.click = parse_click(click),
+            Some(("k", kind)) => options.kind = parse_kind(kind),
+            Some(_) => {}
+            // The exit code is D's only positional parameter
+            None if exit_code.is_none() => exit_code = param.parse().ok(),
+            None => {}
+        }
+    }
+
+    Some(match command {
+        "A" => PromptMark::PromptStart(options),
+        "P" => PromptMark::PromptLine(options),
+        "L" => PromptMark::FreshLine,
+        "B" => PromptMark::InputStart,
+        "C" => PromptMark::OutputStart,
+        "D" => PromptMark::CommandEnd { exit_code },
+        _ => return None,
+    })
+}
+
+fn parse_kind(kind: &str) -> PromptKind {
+    match kind {
+        "c" => PromptKind::Continuation,
+        "s" => PromptKind::Secondary,
+        "r" => PromptKind::Right,
+        _ => PromptKind::Initial,
+    }
+}
+
+fn parse_click(click: &str) -> Option<ClickMove> {
+    match click {
+        "line" => Some(ClickMove::Line),
+        "m" => Some(ClickMove::MultiLine),
+        "v" => Some(ClickMove::ConservativeVertical),
+        "w" => Some(ClickMove::SmartVertical),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::scanner::{MAX_OSC_BYTES, Scanner, Sequence};
+
+    fn parse_chunks(chunks: &[&[u8]]) -> Vec<PromptMark> {
+        let mut scanner = Scanner::default();
+        let mut marks = Vec::new();
+        for &byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+            if let Some(Sequence::Osc(osc)) = scanner.advance(byte)
+                && let Some(mark) = super::parse(osc)
+            {
+                marks.push(mark);
+            }
+        }
+        marks
+    }
+
+    fn parse(bytes: &[u8]) -> Vec<PromptMark> {
+        parse_chunks(&[bytes])
+    }
+
+    #[test]
+    fn parses_each_command() {
+        let marks = parse(
+            b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07out\r\n\x1b]133;D;2\x07\x1b]133;L\x07",
+        );
+        assert_eq!(
+            marks,
+            vec![
+                PromptMark::PromptStart(PromptOptions::default()),
+                PromptMark::InputStart,
+                PromptMark::OutputStart,
+                PromptMark::CommandEnd { exit_code: Some(2) },
+                PromptMark::FreshLine,
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_options() {
+        let marks =
+            parse(b"\x1b]133;A;aid=1234;cl=m\x1b\\\x1b]133;P;k=s\x07\x1b]133;D;0;aid=1234\x07");
+        assert_eq!(
+            marks,
+            vec![
+                PromptMark::PromptStart(PromptOptions {
+                    aid: Some("1234".into()),
+                    click: Some(ClickMove::MultiLine),
+                    kind: PromptKind::Initial,
+                }),
+                PromptMark::PromptLine(PromptOptions {
+                    kind: PromptKind::Secondary,
+                    ..PromptOptions::default()
+                }),
+                PromptMark::CommandEnd { exit_code: Some(0) },
+            ]
+        );
+    }
+
+    #[test]
+    fn survives_every_split_point() {
+        let sequence = b"ab\x1b]133;D;127;aid=7\x1b\\cd";
+        for split in 0..=sequence.len() {
+            let (head, tail) = sequence.split_at(split);
+            assert_eq!(
+                parse_chunks(&[head, tail]),
+                vec![PromptMark::CommandEnd {
+                    exit_code: Some(127)
+                }],
+                "split at {split}"
+            );
+        }
+    }
+
+    #[test]
+    fn long_parameters_are_not_cut_short() {
+        let aid = "x".repeat(200);
+        let marks = parse(format!("\x1b]133;A;aid={aid}\x07").as_bytes());
+        assert_eq!(
+            marks,
+            vec![PromptMark::PromptStart(PromptOptions {
+                aid: Some(aid),
+                ..PromptOptions::default()
+            })]
+        );
+    }
+
+    #[test]
+    fn ignores_partial_and_foreign_sequences() {
+        // Cancelled, another OSC, an unknown command, and one never finished
+        let oversized = format!("\x1b]133;A;aid={}\x07", "x".repeat(MAX_OSC_BYTES));
+        assert!(parse(b"\x1b]133;A\x18\x1b]1337;A\x07\x1b]133;X\x07\x1b]133;B").is_empty());
+        assert!(parse(oversized.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn any_escape_ends_the_string() {
+        // vte dispatches the OSC when the next escape begins
+        let marks = parse(b"\x1b]133;C\x1b[0m");
+        assert_eq!(marks, vec![PromptMark::OutputStart]);
+    }
+}
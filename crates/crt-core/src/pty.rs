@@ -4,13 +4,17 @@
 
 use std::io::{Read, Write};
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
 
-use portable_pty::{Child, CommandBuilder, PtySize, native_pty_system};
+use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, native_pty_system};
 use std::path::PathBuf;
 
+use crate::Size;
+use crate::worker::Wakeup;
+
 /// Options for spawning a shell with semantic prompt support
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct SpawnOptions {
     /// Shell program to run (None = use $SHELL or /bin/sh)
     pub shell: Option<String>,
@@ -20,6 +24,41 @@ pub struct SpawnOptions {
     pub semantic_prompts: bool,
     /// Path to shell integration assets directory
     pub shell_assets_dir: Option<PathBuf>,
+    /// Extra arguments, passed after the ones CRT adds
+    pub args: Vec<String>,
+    /// Extra environment variables, applied over CRT's defaults
+    /// (`TERM`, `COLORTERM`, `TERM_PROGRAM`, `TERM_PROGRAM_VERSION`)
+    pub env: Vec<(String, String)>,
+    /// Start the shell as a login shell (default: true)
+    pub login_shell: bool,
+    /// Run from the parser thread when new output is ready to draw
+    pub wakeup: Option<Wakeup>,
+}
+
+impl Default for SpawnOptions {
+    fn default() -> Self {
+        Self {
+            shell: None,
+            cwd: None,
+            semantic_prompts: false,
+            shell_assets_dir: None,
+            args: Vec::new(),
+            env: Vec::new(),
+            login_shell: true,
+            wakeup: None,
+        }
+    }
+}
+
+/// The process in the terminal's foreground process group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForegroundProcess {
+    /// Process ID of the group leader
+    pub pid: u32,
+    /// Executable name, e.g. `vim`
+    pub name: String,
+    /// Full command line (empty if it couldn't be read)
+    pub cmdline: Vec<String>,
 }
 
 /// Messages sent to the PTY writer thread
@@ -27,7 +66,7 @@ pub enum PtyInput {
     /// Data to write to the PTY
     Data(Vec<u8>),
     /// Resize the PTY
-    Resize { cols: u16, rows: u16 },
+    Resize(PtySize),
     /// Shutdown the PTY
     Shutdown,
 }
@@ -43,26 +82,62 @@ pub trait PtyBackend: Send {
     fn try_read(&self) -> Option<Vec<u8>>;
     /// Read all available output from the PTY (non-blocking)
     fn read_available(&self) -> Vec<u8>;
-    /// Resize the PTY
-    fn resize(&self, cols: u16, rows: u16);
+    /// Resize the PTY, telling the program the text area's size in pixels
+    fn resize(&self, size: Size);
     /// Shutdown the PTY
     fn shutdown(&self);
     /// Get the process ID of the shell
     fn process_id(&self) -> Option<u32>;
     /// Get the current working directory of the shell process
     fn working_directory(&self) -> Option<PathBuf>;
+    /// Exit code of the shell if it has exited (non-blocking)
+    ///
+    /// Backends that can't tell never report an exit.
+    fn try_wait(&self) -> Option<i32> {
+        None
+    }
+    /// Get the process in the foreground (the shell itself when idle)
+    fn foreground_process(&self) -> Option<ForegroundProcess> {
+        None
+    }
+    /// Hand the output stream over to a parser thread
+    ///
+    /// Backends that return `None` are read on the caller's thread through
+    /// `read_available` instead.
+    fn take_output(&mut self) -> Option<PtyOutput> {
+        None
+    }
+}
+
+/// The read half of a PTY: output chunks from its reader thread
+pub struct PtyOutput {
+    /// Output chunks in the order they were read
+    pub chunks: Receiver<Vec<u8>>,
+    /// Channel to return spent buffers to the reader thread for reuse
+    pub recycle: Sender<Vec<u8>>,
 }
 
 /// PTY handle for communicating with a shell process
 pub struct Pty {
     /// Channel to send input to the PTY
     input_tx: Sender<PtyInput>,
-    /// Channel to receive output from the PTY
-    output_rx: Receiver<Vec<u8>>,
-    /// Channel to return spent buffers to the reader thread for reuse
-    recycle_tx: Sender<Vec<u8>>,
-    /// Child process handle
-    child: Box<dyn Child + Send + Sync>,
+    /// Output from the reader thread (None once handed to a parser thread)
+    output: Option<PtyOutput>,
+    /// Child process handle (locked because reaping it needs `&mut`)
+    child: Mutex<Box<dyn Child + Send + Sync>>,
+    /// PTY master, shared with the writer thread for resizes
+    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+}
+
+/// PTY size for a terminal, with the text area in pixels for programs that
+/// size graphics from `TIOCGWINSZ`
+fn pty_size(size: Size) -> PtySize {
+    PtySize {
+        rows: size.lines as u16,
+        cols: size.columns as u16,
+        pixel_width: size.pixel_width(),
+        pixel_height: size.pixel_height(),
+    }
 }
 
 /// Spawn reader and writer threads for a PTY pair and return the Pty handle.
@@ -79,7 +154,9 @@ fn spawn_pty_threads(
     let (recycle_tx, recycle_rx) = mpsc::channel::<Vec<u8>>();
 
     let mut reader = pair.master.try_clone_reader()?;
-    let master = pair.master;
+    let mut writer = pair.master.take_writer()?;
+    let master = Arc::new(Mutex::new(pair.master));
+    let writer_master = Arc::clone(&master);
 
     // Spawn reader thread — reads PTY output and sends to channel.
     // Recycles buffers returned by the consumer to avoid per-read allocations.
@@ -112,8 +189,6 @@ fn spawn_pty_threads(
 
     // Spawn writer thread — receives from channel and writes to PTY
     thread::spawn(move || {
-        let mut writer = master.take_writer().expect("Failed to get PTY writer");
-
         for msg in input_rx {
             match msg {
                 PtyInput::Data(data) => {
@@ -123,14 +198,12 @@ fn spawn_pty_threads(
                     }
                     let _ = writer.flush();
                 }
-                PtyInput::Resize { cols, rows } => {
-                    let size = PtySize {
-                        rows,
-                        cols,
-                        pixel_width: 0,
-                        pixel_height: 0,
+                PtyInput::Resize(size) => {
+                    let result = match writer_master.lock() {
+                        Ok(master) => master.resize(size),
+                        Err(_) => break,
                     };
-                    if let Err(e) = master.resize(size) {
+                    if let Err(e) = result {
                         log::error!("PTY resize error: {}", e);
                     }
                 }
@@ -145,9 +218,12 @@ fn spawn_pty_threads(
 
     Ok(Pty {
         input_tx,
-        output_rx,
-        recycle_tx,
-        child,
+        output: Some(PtyOutput {
+            chunks: output_rx,
+            recycle: recycle_tx,
+        }),
+        child: Mutex::new(child),
+        master,
     })
 }
 
@@ -158,90 +234,10 @@ impl Pty {
     }
 
     /// Spawn a new shell with full options including semantic prompt support
-    pub fn spawn_with_options(cols: u16, rows: u16, options: SpawnOptions) -> anyhow::Result<Self> {
+    pub fn spawn_with_options(size: Size, options: SpawnOptions) -> anyhow::Result<Self> {
         let pty_system = native_pty_system();
-
-        let size = PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        };
-
-        let pair = pty_system.openpty(size)?;
-
-        // Determine shell to use
-        let shell = options
-            .shell
-            .or_else(|| std::env::var("SHELL").ok())
-            .unwrap_or_else(|| String::from("/bin/sh"));
-
-        // Detect shell type for semantic prompt integration
-        let shell_type = detect_shell_type(&shell);
-
-        let mut cmd = CommandBuilder::new(&shell);
-
-        // Set terminal-specific environment variables
-        cmd.env("TERM", "xterm-256color");
-        cmd.env("COLORTERM", "truecolor");
-
-        // Set working directory if specified
-        if let Some(dir) = options.cwd {
-            cmd.cwd(dir);
-        }
-
-        // Apply semantic prompt integration based on shell type
-        if options.semantic_prompts {
-            if let Some(assets_dir) = options.shell_assets_dir {
-                match shell_type {
-                    ShellType::Bash => {
-                        // Use --rcfile to load our init script (sources user's .bashrc)
-                        let init_path = assets_dir.join("crt-bash-init");
-                        if init_path.exists() {
-                            cmd.arg("--rcfile");
-                            cmd.arg(init_path);
-                            log::info!("Bash semantic prompts enabled via --rcfile");
-                        } else {
-                            // Fall back to login shell
-                            cmd.arg("-l");
-                            log::warn!("Bash init script not found at {:?}", init_path);
-                        }
-                    }
-                    ShellType::Zsh => {
-                        // Set ZDOTDIR to our init directory (has .zshrc that sources user's)
-                        let init_dir = assets_dir.join("crt-zsh-init");
-                        if init_dir.exists() {
-                            // Save original ZDOTDIR so our script can restore it
-                            if let Ok(original) = std::env::var("ZDOTDIR") {
-                                cmd.env("CRT_ORIGINAL_ZDOTDIR", original);
-                            }
-                            cmd.env("ZDOTDIR", &init_dir);
-                            cmd.arg("-l");
-                            log::info!("Zsh semantic prompts enabled via ZDOTDIR");
-                        } else {
-                            cmd.arg("-l");
-                            log::warn!("Zsh init directory not found at {:?}", init_dir);
-                        }
-                    }
-                    ShellType::Fish => {
-                        // Fish has built-in OSC 133 support since v3.4
-                        cmd.arg("-l");
-                        log::info!("Fish has built-in semantic prompt support");
-                    }
-                    ShellType::Unknown => {
-                        // Unknown shell, just spawn as login shell
-                        cmd.arg("-l");
-                    }
-                }
-            } else {
-                // No assets dir, spawn as login shell
-                cmd.arg("-l");
-            }
-        } else {
-            // No semantic prompts, spawn as login shell
-            cmd.arg("-l");
-        }
-
+        let pair = pty_system.openpty(pty_size(size))?;
+        let cmd = build_command(options);
         let child = pair.slave.spawn_command(cmd)?;
         spawn_pty_threads(pair, child)
     }
@@ -253,43 +249,25 @@ impl Pty {
         rows: u16,
         cwd: Option<PathBuf>,
     ) -> anyhow::Result<Self> {
-        let pty_system = native_pty_system();
-
-        let size = PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
+        let options = SpawnOptions {
+            shell: shell.map(String::from),
+            cwd,
+            ..SpawnOptions::default()
         };
-
-        let pair = pty_system.openpty(size)?;
-
-        // Determine shell to use
-        let shell = shell
-            .map(String::from)
-            .or_else(|| std::env::var("SHELL").ok())
-            .unwrap_or_else(|| String::from("/bin/sh"));
-
-        let mut cmd = CommandBuilder::new(&shell);
-        // Spawn as login shell so it sources user's profile (.zprofile, .bash_profile, etc.)
-        // This ensures proper PATH, LANG, and other user environment settings
-        cmd.arg("-l");
-        // Set terminal-specific environment variables (we know our capabilities)
-        // TERM is critical - without it, shells may output prompts incorrectly
-        cmd.env("TERM", "xterm-256color");
-        // COLORTERM indicates 24-bit true color support
-        cmd.env("COLORTERM", "truecolor");
-        // Set working directory if specified
-        if let Some(dir) = cwd {
-            cmd.cwd(dir);
-        }
-        let child = pair.slave.spawn_command(cmd)?;
-        spawn_pty_threads(pair, child)
+        Self::spawn_with_options(Size::new(cols.into(), rows.into()), options)
     }
 
     /// Get the process ID of the shell
     pub fn process_id(&self) -> Option<u32> {
-        self.child.process_id()
+        self.child.lock().ok()?.process_id()
+    }
+
+    /// Exit code of the shell if it has exited (non-blocking)
+    ///
+    /// Shells killed by a signal report code 1, matching portable-pty.
+    pub fn try_wait(&self) -> Option<i32> {
+        let status = self.child.lock().ok()?.try_wait().ok()??;
+        Some(status.exit_code() as i32)
     }
 
     /// Get the current working directory of the shell process
@@ -299,6 +277,22 @@ impl Pty {
         get_process_cwd(pid)
     }
 
+    /// Get the process in the foreground (the shell itself when idle)
+    ///
+    /// Asks the PTY for its foreground process group (`tcgetpgrp`) and
+    /// describes the group leader.
+    pub fn foreground_process(&self) -> Option<ForegroundProcess> {
+        #[cfg(unix)]
+        {
+            let pgid = self.master.lock().ok()?.process_group_leader()?;
+            get_process_info(u32::try_from(pgid).ok()?)
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
     /// Write data to the PTY (keyboard input)
     pub fn write(&self, data: &[u8]) {
         let _ = self.input_tx.send(PtyInput::Data(data.to_vec()));
@@ -306,7 +300,7 @@ impl Pty {
 
     /// Try to read available output from the PTY (non-blocking)
     pub fn try_read(&self) -> Option<Vec<u8>> {
-        self.output_rx.try_recv().ok()
+        self.output.as_ref()?.chunks.try_recv().ok()
     }
 
     /// Read all available output from the PTY (non-blocking)
@@ -314,17 +308,27 @@ impl Pty {
     /// Recycles consumed buffers back to the reader thread to reduce allocations.
     pub fn read_available(&self) -> Vec<u8> {
         let mut output = Vec::new();
-        while let Ok(data) = self.output_rx.try_recv() {
+        let Some(source) = &self.output else {
+            return output;
+        };
+        while let Ok(data) = source.chunks.try_recv() {
             output.extend_from_slice(&data);
             // Return the buffer to the reader thread for reuse (best-effort)
-            let _ = self.recycle_tx.send(data);
+            let _ = source.recycle.send(data);
         }
         output
     }
 
+    /// Hand the output stream over to a parser thread
+    ///
+    /// Afterwards `try_read` and `read_available` return nothing.
+    pub fn take_output(&mut self) -> Option<PtyOutput> {
+        self.output.take()
+    }
+
     /// Resize the PTY
-    pub fn resize(&self, cols: u16, rows: u16) {
-        let _ = self.input_tx.send(PtyInput::Resize { cols, rows });
+    pub fn resize(&self, size: Size) {
+        let _ = self.input_tx.send(PtyInput::Resize(pty_size(size)));
     }
 
     /// Shutdown the PTY
@@ -346,8 +350,8 @@ impl PtyBackend for Pty {
         self.read_available()
     }
 
-    fn resize(&self, cols: u16, rows: u16) {
-        self.resize(cols, rows);
+    fn resize(&self, size: Size) {
+        self.resize(size);
     }
 
     fn shutdown(&self) {
@@ -361,6 +365,18 @@ impl PtyBackend for Pty {
     fn working_directory(&self) -> Option<PathBuf> {
         self.working_directory()
     }
+
+    fn try_wait(&self) -> Option<i32> {
+        self.try_wait()
+    }
+
+    fn foreground_process(&self) -> Option<ForegroundProcess> {
+        self.foreground_process()
+    }
+
+    fn take_output(&mut self) -> Option<PtyOutput> {
+        self.take_output()
+    }
 }
 
 impl Drop for Pty {
@@ -369,6 +385,113 @@ impl Drop for Pty {
     }
 }
 
+/// Build the shell command line and environment for `options`
+fn build_command(options: SpawnOptions) -> CommandBuilder {
+    // Determine shell to use
+    let shell = options
+        .shell
+        .or_else(|| std::env::var("SHELL").ok())
+        .unwrap_or_else(|| String::from("/bin/sh"));
+
+    // Detect shell type for semantic prompt integration
+    let shell_type = detect_shell_type(&shell);
+
+    let mut cmd = CommandBuilder::new(&shell);
+
+    // Set terminal-specific environment variables (we know our capabilities)
+    // TERM is critical - without it, shells may output prompts incorrectly
+    cmd.env("TERM", "xterm-256color");
+    // COLORTERM indicates 24-bit true color support
+    cmd.env("COLORTERM", "truecolor");
+    cmd.env("TERM_PROGRAM", "crt");
+    cmd.env("TERM_PROGRAM_VERSION", env!("CARGO_PKG_VERSION"));
+    for (key, value) in &options.env {
+        cmd.env(key, value);
+    }
+
+    // Set working directory if specified
+    if let Some(dir) = options.cwd {
+        cmd.cwd(dir);
+    }
+
+    // A login shell sources the user's profile (.zprofile, .bash_profile, etc.),
+    // which is where PATH, LANG and friends usually get set up
+    let mut login = options.login_shell;
+
+    // Apply semantic prompt integration based on shell type
+    if options.semantic_prompts
+        && let Some(assets_dir) = options.shell_assets_dir
+    {
+        match shell_type {
+            ShellType::Bash => {
+                // Use --rcfile to load our init script (sources user's startup files).
+                // Long options must come before -l for bash.
+                let init_path = assets_dir.join("crt-bash-init");
+                if init_path.exists() {
+                    cmd.arg("--rcfile");
+                    cmd.arg(init_path);
+                    // A login bash ignores --rcfile, so our script reads the profile itself
+                    if login {
+                        cmd.env("CRT_BASH_LOGIN", "1");
+                        login = false;
+                    }
+                    log::info!("Bash semantic prompts enabled via --rcfile");
+                } else {
+                    log::warn!("Bash init script not found at {:?}", init_path);
+                }
+            }
+            ShellType::Zsh => {
+                // Set ZDOTDIR to our init directory. Its .zshenv restores the
+                // user's ZDOTDIR, sources their dotfiles, and loads our hooks.
+                let init_dir = assets_dir.join("crt-zsh-init");
+                if init_dir.join(".zshenv").exists() {
+                    // Save original ZDOTDIR (configured or inherited) so our script can restore it
+                    let original = options
+                        .env
+                        .iter()
+                        .find(|(key, _)| key == "ZDOTDIR")
+                        .map(|(_, value)| value.clone())
+                        .or_else(|| std::env::var("ZDOTDIR").ok());
+                    if let Some(original) = original {
+                        cmd.env("CRT_ORIGINAL_ZDOTDIR", original);
+                    }
+                    cmd.env("ZDOTDIR", &init_dir);
+                    log::info!("Zsh semantic prompts enabled via ZDOTDIR");
+                } else {
+                    log::warn!("Zsh init directory not found at {:?}", init_dir);
+                }
+            }
+            ShellType::Fish => {
+                // --init-command runs after the user's config.fish
+                let init_path = assets_dir.join("crt-fish-init.fish");
+                if init_path.exists() {
+                    cmd.arg("--init-command");
+                    cmd.arg(fish_source_command(&init_path));
+                    log::info!("Fish semantic prompts enabled via --init-command");
+                } else {
+                    log::warn!("Fish init script not found at {:?}", init_path);
+                }
+            }
+            ShellType::Unknown => {}
+        }
+    }
+
+    if login {
+        cmd.arg("-l");
+    }
+    cmd.args(&options.args);
+    cmd
+}
+
+/// Build a fish `source` command for `path`, single-quoted for fish
+fn fish_source_command(path: &std::path::Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'");
+    format!("source '{}'", escaped)
+}
+
 /// Get the current working directory of a process by PID
 #[cfg(target_os = "macos")]
 fn get_process_cwd(pid: u32) -> Option<PathBuf> {
@@ -413,6 +536,103 @@ fn get_process_cwd(_pid: u32) -> Option<PathBuf> {
     None
 }
 
+/// Describe a process by PID
+#[cfg(target_os = "linux")]
+fn get_process_info(pid: u32) -> Option<ForegroundProcess> {
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+    Some(ForegroundProcess {
+        pid,
+        name: comm.trim_end().to_string(),
+        cmdline: parse_cmdline(&cmdline),
+    })
+}
+
+/// Describe a process by PID
+///
+/// Asks the kernel directly (`proc_pidpath` and `KERN_PROCARGS2`) rather than
+/// running `ps`, since tab titles look this up for every tab as they redraw.
+#[cfg(target_os = "macos")]
+fn get_process_info(pid: u32) -> Option<ForegroundProcess> {
+    let pid_arg = libc::c_int::try_from(pid).ok()?;
+
+    let mut path = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+    // SAFETY: the buffer is valid for writes of the size passed
+    let len = unsafe { libc::proc_pidpath(pid_arg, path.as_mut_ptr().cast(), path.len() as u32) };
+    let len = usize::try_from(len).ok().filter(|&len| len > 0)?;
+    let path = std::path::PathBuf::from(String::from_utf8_lossy(&path[..len]).into_owned());
+    let name = path.file_name()?.to_string_lossy().into_owned();
+
+    Some(ForegroundProcess {
+        pid,
+        name,
+        cmdline: process_args(pid_arg).unwrap_or_default(),
+    })
+}
+
+/// Argument list of a process, from `sysctl(KERN_PROCARGS2)`
+#[cfg(target_os = "macos")]
+fn process_args(pid: libc::c_int) -> Option<Vec<String>> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid];
+    let mut size = 0;
+    let mut read = |buffer: *mut libc::c_void, size: &mut libc::size_t| {
+        // SAFETY: `buffer` is null (asking for the size) or valid for `size` bytes
+        let status = unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib.len() as libc::c_uint,
+                buffer,
+                size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        (status == 0).then_some(())
+    };
+    read(std::ptr::null_mut(), &mut size)?;
+    let mut raw = vec![0u8; size];
+    read(raw.as_mut_ptr().cast(), &mut size)?;
+    raw.truncate(size);
+    Some(parse_procargs(&raw))
+}
+
+/// Fallback for other platforms
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn get_process_info(_pid: u32) -> Option<ForegroundProcess> {
+    None
+}
+
+/// Split a NUL-separated `/proc/<pid>/cmdline` into arguments
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cmdline(raw: &[u8]) -> Vec<String> {
+    raw.split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+/// Arguments from a macOS `KERN_PROCARGS2` buffer
+///
+/// The buffer holds `argc` (native-endian `i32`), the executable path, NUL
+/// padding, then `argc` NUL-terminated arguments (the environment follows).
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_procargs(raw: &[u8]) -> Vec<String> {
+    let Some((argc, rest)) = raw.split_first_chunk::<4>() else {
+        return Vec::new();
+    };
+    let argc = usize::try_from(i32::from_ne_bytes(*argc)).unwrap_or(0);
+    let Some(path_end) = rest.iter().position(|&b| b == 0) else {
+        return Vec::new();
+    };
+    let rest = &rest[path_end..];
+    let args_start = rest.iter().position(|&b| b != 0).unwrap_or(rest.len());
+    rest[args_start..]
+        .split(|&b| b == 0)
+        .take(argc)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
 /// Shell types for semantic prompt integration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShellType {
@@ -495,4 +715,128 @@ mod tests {
             output_str
         );
     }
+
+    #[test]
+    fn try_wait_reports_exit_code() {
+        let options = SpawnOptions {
+            shell: Some("/bin/sh".into()),
+            args: vec!["-c".into(), "exit 3".into()],
+            login_shell: false,
+            ..SpawnOptions::default()
+        };
+        let pty = Pty::spawn_with_options(Size::new(80, 24), options).expect("Failed to spawn PTY");
+
+        let start = std::time::Instant::now();
+        let mut code = None;
+        while code.is_none() && start.elapsed() < Duration::from_secs(2) {
+            code = pty.try_wait();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(code, Some(3));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn foreground_process_names_running_program() {
+        let options = SpawnOptions {
+            shell: Some("/bin/sh".into()),
+            args: vec!["-c".into(), "exec sleep 5".into()],
+            login_shell: false,
+            ..SpawnOptions::default()
+        };
+        let pty = Pty::spawn_with_options(Size::new(80, 24), options).expect("Failed to spawn PTY");
+
+        let start = std::time::Instant::now();
+        let mut process = None;
+        while start.elapsed() < Duration::from_secs(2) {
+            process = pty.foreground_process();
+            if process.as_ref().is_some_and(|p| p.name == "sleep") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let process = process.expect("no foreground process");
+        assert_eq!(process.name, "sleep");
+        assert_eq!(process.cmdline, ["sleep", "5"]);
+        assert_eq!(Some(process.pid), pty.process_id());
+    }
+
+    #[test]
+    fn parse_procargs_skips_path_and_environment() {
+        let mut raw = 2i32.to_ne_bytes().to_vec();
+        raw.extend_from_slice(b"/usr/bin/vim\0\0\0vim\0notes.txt\0HOME=/Users/me\0");
+        assert_eq!(parse_procargs(&raw), ["vim", "notes.txt"]);
+        assert!(parse_procargs(b"\0\0").is_empty());
+    }
+
+    #[test]
+    fn parse_cmdline_splits_on_nul() {
+        assert_eq!(parse_cmdline(b"vim\0-u\0NONE\0"), ["vim", "-u", "NONE"]);
+        assert!(parse_cmdline(b"").is_empty());
+    }
+
+    fn argv(cmd: &CommandBuilder) -> Vec<String> {
+        cmd.get_argv()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn login_shell_by_default_with_configured_args() {
+        let cmd = build_command(SpawnOptions {
+            shell: Some("/bin/sh".into()),
+            args: vec!["-i".into()],
+            ..SpawnOptions::default()
+        });
+        assert_eq!(argv(&cmd), ["/bin/sh", "-l", "-i"]);
+    }
+
+    #[test]
+    fn login_shell_opt_out() {
+        let cmd = build_command(SpawnOptions {
+            shell: Some("/bin/sh".into()),
+            login_shell: false,
+            ..SpawnOptions::default()
+        });
+        assert_eq!(argv(&cmd), ["/bin/sh"]);
+    }
+
+    #[test]
+    fn env_sets_term_program_and_allows_overrides() {
+        let cmd = build_command(SpawnOptions {
+            shell: Some("/bin/sh".into()),
+            env: vec![
+                ("TERM".into(), "xterm-crt".into()),
+                ("EDITOR".into(), "vim".into()),
+            ],
+            ..SpawnOptions::default()
+        });
+        assert_eq!(cmd.get_env("TERM_PROGRAM").unwrap(), "crt");
+        assert_eq!(
+            cmd.get_env("TERM_PROGRAM_VERSION").unwrap(),
+            env!("CARGO_PKG_VERSION")
+        );
+        assert_eq!(cmd.get_env("TERM").unwrap(), "xterm-crt");
+        assert_eq!(cmd.get_env("EDITOR").unwrap(), "vim");
+    }
+
+    #[test]
+    fn bash_integration_puts_rcfile_before_args() {
+        let assets = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/shell");
+        let cmd = build_command(SpawnOptions {
+            shell: Some("/bin/bash".into()),
+            semantic_prompts: true,
+            shell_assets_dir: Some(assets.clone()),
+            args: vec!["-O".into(), "globstar".into()],
+            ..SpawnOptions::default()
+        });
+        let rcfile = assets.join("crt-bash-init").to_string_lossy().into_owned();
+        assert_eq!(
+            argv(&cmd),
+            ["/bin/bash", "--rcfile", rcfile.as_str(), "-O", "globstar"]
+        );
+        // Login startup files are read by the init script instead of `-l`
+        assert_eq!(cmd.get_env("CRT_BASH_LOGIN").unwrap(), "1");
+    }
 }
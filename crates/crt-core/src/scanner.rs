@@ -0,0 +1,296 @@
+//! Control sequence scanner for sequences handled outside the parser
+//!
+//! A few control sequences need attention before alacritty_terminal's parser
+//! sees them, or instead of it: kitty keyboard pushes are capped, some modes
+//! and window ops it ignores are answered here, and OSC strings it drops
+//! (semantic prompts) are read. [`Scanner`] follows the output stream byte by
+//! byte, mirroring vte's state machine closely enough to agree on where each
+//! sequence starts and ends, and hands back the ones it completes. Sequences
+//! split across reads are handled; the scanner keeps its state between chunks.
+
+/// Parameters vte collects before it ignores a sequence
+pub(crate) const MAX_PARAMS: usize = 32;
+/// Cancels the sequence in progress without dispatching it
+pub(crate) const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+const BEL: u8 = 0x07;
+/// Longest OSC payload kept; longer ones are dropped
+pub(crate) const MAX_OSC_BYTES: usize = 4096;
+
+/// A sequence completed by the byte just scanned
+pub(crate) enum Sequence<'a> {
+    /// `CSI`, with its final byte
+    Csi(&'a Csi),
+    /// `ESC c` (RIS)
+    Reset,
+    /// `OSC`, with its payload (everything up to the terminator)
+    Osc(&'a [u8]),
+}
+
+/// A control sequence without intermediates
+#[derive(Debug, Default)]
+pub(crate) struct Csi {
+    /// Private marker (`<`, `=`, `>` or `?`) leading the sequence
+    pub(crate) marker: Option<u8>,
+    /// Final byte
+    pub(crate) action: u8,
+    /// Parameters, without their subparameters
+    params: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+impl Csi {
+    /// Parameters in order; an omitted parameter reads as 0
+    pub(crate) fn params(&self) -> &[u16] {
+        &self.params[..self.len]
+    }
+
+    /// First parameter (always present, 0 if omitted)
+    pub(crate) fn first(&self) -> u16 {
+        self.params().first().copied().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// Inside `CSI`; dispatch is decided once the final byte arrives
+    Csi,
+    /// Inside an OSC string
+    Osc,
+}
+
+/// Follows an output stream for complete control sequences
+#[derive(Debug, Default)]
+pub(crate) struct Scanner {
+    state: State,
+    /// No parameter or marker bytes read yet
+    at_start: bool,
+    /// The sequence has bytes that keep it from being dispatched here
+    ignored: bool,
+    /// Parameters and subparameters finished so far
+    count: usize,
+    /// Value of the parameter being read
+    value: u16,
+    /// The parameter being read is a subparameter
+    in_subparam: bool,
+    csi: Csi,
+    /// Payload of the OSC string being read
+    osc: Vec<u8>,
+    /// The OSC payload outgrew [`MAX_OSC_BYTES`]
+    osc_overflow: bool,
+}
+
+impl Scanner {
+    /// Feed one byte; returns the sequence it completes, if any
+    pub(crate) fn advance(&mut self, byte: u8) -> Option<Sequence<'_>> {
+        match (self.state, byte) {
+            // Like vte, any escape ends an OSC string, not only ST
+            (State::Osc, BEL | 0x1b) => {
+                self.state = if byte == BEL {
+                    State::Ground
+                } else {
+                    State::Escape
+                };
+                return (!self.osc_overflow).then_some(Sequence::Osc(&self.osc));
+            }
+            (_, 0x1b) => self.state = State::Escape,
+            (_, CAN | SUB) => self.state = State::Ground,
+            (State::Ground, _) => {}
+            (State::Escape, b'[') => {
+                self.start_csi();
+                self.state = State::Csi;
+            }
+            (State::Escape, b']') => {
+                self.osc.clear();
+                self.osc_overflow = false;
+                self.state = State::Osc;
+            }
+            (State::Escape, b'c') => {
+                self.state = State::Ground;
+                return Some(Sequence::Reset);
+            }
+            // C0 controls are executed without ending the sequence
+            (State::Escape, 0x00..=0x1f | 0x7f) => {}
+            (State::Escape, _) => self.state = State::Ground,
+            (State::Csi, 0x40..=0x7e) => {
+                self.state = State::Ground;
+                self.finish_param();
+                if self.ignored {
+                    return None;
+                }
+                self.csi.action = byte;
+                return Some(Sequence::Csi(&self.csi));
+            }
+            (State::Csi, _) => self.collect(byte),
+            // C0 controls are executed without ending the string
+            (State::Osc, 0x00..=0x1f) => {}
+            (State::Osc, _) if self.osc.len() < MAX_OSC_BYTES => self.osc.push(byte),
+            (State::Osc, _) => self.osc_overflow = true,
+        }
+        None
+    }
+
+    /// Length of the text at the start of `bytes` that the scanner would
+    /// pass over unchanged, outside any sequence up to the next escape
+    pub(crate) fn plain_len(&self, bytes: &[u8]) -> usize {
+        if self.state != State::Ground {
+            return 0;
+        }
+        bytes
+            .iter()
+            .position(|&byte| byte == 0x1b)
+            .unwrap_or(bytes.len())
+    }
+
+    fn start_csi(&mut self) {
+        self.at_start = true;
+        self.ignored = false;
+        self.count = 0;
+        self.value = 0;
+        self.in_subparam = false;
+        self.csi.marker = None;
+        self.csi.len = 0;
+    }
+
+    fn collect(&mut self, byte: u8) {
+        let at_start = self.at_start && byte >= 0x20;
+        if at_start {
+            self.at_start = false;
+        }
+        match byte {
+            b'0'..=b'9' => {
+                self.value = self
+                    .value
+                    .saturating_mul(10)
+                    .saturating_add(u16::from(byte - b'0'));
+            }
+            b':' | b';' => {
+                self.finish_param();
+                self.in_subparam = byte == b':';
+            }
+            b'<'..=b'?' if at_start => self.csi.marker = Some(byte),
+            // Intermediates, or a marker out of place
+            0x20..=0x2f | b'<'..=b'?' => self.ignored = true,
+            // C0 controls are executed without ending the sequence
+            _ => {}
+        }
+    }
+
+    fn finish_param(&mut self) {
+        if self.count == MAX_PARAMS {
+            self.ignored = true;
+            return;
+        }
+        if !self.in_subparam {
+            self.csi.params[self.csi.len] = self.value;
+            self.csi.len += 1;
+        }
+        self.count += 1;
+        self.value = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (marker, params, action) of each CSI completed, with RIS as `c`
+    fn scan(bytes: &[u8]) -> Vec<(Option<u8>, Vec<u16>, u8)> {
+        let mut scanner = Scanner::default();
+        let mut found = Vec::new();
+        for &byte in bytes {
+            match scanner.advance(byte) {
+                Some(Sequence::Csi(csi)) => {
+                    found.push((csi.marker, csi.params().to_vec(), csi.action));
+                }
+                Some(Sequence::Reset) => found.push((None, Vec::new(), b'c')),
+                Some(Sequence::Osc(_)) | None => {}
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn collects_marker_and_params() {
+        assert_eq!(
+            scan(b"\x1b[?1000;1006h\x1b[16t\x1b[<u\x1bc"),
+            vec![
+                (Some(b'?'), vec![1000, 1006], b'h'),
+                (None, vec![16], b't'),
+                (Some(b'<'), vec![0], b'u'),
+                (None, Vec::new(), b'c'),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_subparameters() {
+        assert_eq!(scan(b"\x1b[4:3;5m"), vec![(None, vec![4, 5], b'm')]);
+    }
+
+    #[test]
+    fn follows_sequences_split_across_reads() {
+        let mut scanner = Scanner::default();
+        for chunk in [&b"\x1b"[..], b"\x00[1", b"6"] {
+            for &byte in chunk {
+                assert!(scanner.advance(byte).is_none());
+            }
+        }
+        assert!(matches!(scanner.advance(b't'), Some(Sequence::Csi(csi)) if csi.first() == 16));
+    }
+
+    #[test]
+    fn ignores_what_vte_ignores() {
+        // An intermediate, a marker out of place, too many params, cancelled
+        let too_many_params = format!("\x1b[{}t", "1;".repeat(MAX_PARAMS));
+        assert!(scan(b"\x1b[1$p\x1b[1>u\x1b[16\x18t").is_empty());
+        assert!(scan(too_many_params.as_bytes()).is_empty());
+    }
+
+    /// Payloads of each OSC string completed
+    fn scan_osc(chunks: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut scanner = Scanner::default();
+        let mut found = Vec::new();
+        for &byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+            if let Some(Sequence::Osc(payload)) = scanner.advance(byte) {
+                found.push(payload.to_vec());
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn osc_ends_at_bel_or_any_escape() {
+        assert_eq!(
+            scan_osc(&[b"\x1b]133;A\x07$ \x1b]7;file:///tmp\x1b\\\x1b]133;C\x1b[0m"]),
+            vec![
+                b"133;A".to_vec(),
+                b"7;file:///tmp".to_vec(),
+                b"133;C".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn osc_survives_every_split_point() {
+        let sequence = b"ab\x1b]133;D;127\x1b\\cd";
+        for split in 0..=sequence.len() {
+            let (head, tail) = sequence.split_at(split);
+            assert_eq!(
+                scan_osc(&[head, tail]),
+                vec![b"133;D;127".to_vec()],
+                "split at {split}"
+            );
+        }
+    }
+
+    #[test]
+    fn drops_cancelled_and_oversized_osc() {
+        let oversized = format!("\x1b]7;{}\x07", "x".repeat(MAX_OSC_BYTES));
+        assert!(scan_osc(&[b"\x1b]133;A\x18\x1b]133;B"]).is_empty());
+        assert!(scan_osc(&[oversized.as_bytes()]).is_empty());
+    }
+}
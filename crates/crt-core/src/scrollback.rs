@@ -0,0 +1,188 @@
+//! Absolute line numbers that survive scrolling
+//!
+//! Grid lines are numbered from the top of the screen, so a line's number
+//! changes each time output scrolls. What the terminal remembers about lines
+//! (semantic zones, command records) is keyed by absolute line instead: the
+//! number of lines above it since the terminal started, counting those since
+//! dropped from history. Scrolling leaves that number alone. [`Scrollback`]
+//! converts between the two, given the lines that have left the top of
+//! history, and after a resize reflows the text finds each line again by
+//! counting logical lines up from the cursor.
+
+use alacritty_terminal::grid::{Dimensions, Grid};
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::cell::{Cell, Flags};
+
+/// Where a line sits relative to the cursor, in terms reflow preserves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    /// `lines` logical lines above the cursor's, `row` rows into that line
+    Above { lines: usize, row: usize },
+    /// Rows below the cursor
+    Below(usize),
+}
+
+/// Numbers lines from the start of the terminal
+#[derive(Debug, Default)]
+pub(crate) struct Scrollback {
+    /// Lines dropped from the top of history so far
+    evicted: usize,
+}
+
+impl Scrollback {
+    /// Absolute line of a grid line (negative for history)
+    pub(crate) fn absolute(&self, grid: &Grid<Cell>, line: Line) -> Option<usize> {
+        let from_top = usize::try_from(line.0 + grid.history_size() as i32).ok()?;
+        Some(self.evicted + from_top)
+    }
+
+    /// Grid line of an absolute line, which may have left history
+    pub(crate) fn line(&self, grid: &Grid<Cell>, absolute: usize) -> Line {
+        Line(absolute as i32 - self.evicted as i32 - grid.history_size() as i32)
+    }
+
+    /// First absolute line still in history
+    pub(crate) fn first(&self) -> usize {
+        self.evicted
+    }
+
+    /// Record `lines` lines leaving the top of history
+    pub(crate) fn evict(&mut self, lines: usize) {
+        self.evicted += lines;
+    }
+
+    /// Anchor absolute lines to the cursor before a resize
+    pub(crate) fn anchor(&self, grid: &Grid<Cell>, lines: &[usize]) -> Vec<Anchor> {
+        let cursor = grid.cursor.point.line;
+        let top = grid.topmost_line();
+        let mut anchors = vec![Anchor::Below(0); lines.len()];
+
+        // From the bottom up, counting logical lines passed on the way
+        let mut order: Vec<usize> = (0..lines.len()).collect();
+        order.sort_unstable_by_key(|&index| std::cmp::Reverse(lines[index]));
+        let mut line = cursor;
+        let mut passed = 0;
+        for index in order {
+            let target = self.line(grid, lines[index]);
+            if target > cursor {
+                anchors[index] = Anchor::Below((target - cursor).0 as usize);
+                continue;
+            }
+            while line > target.max(top) {
+                line -= 1;
+                if !wraps(grid, line) {
+                    passed += 1;
+                }
+            }
+            let row = (target - logical_start(grid, target.max(top), top)).0 as usize;
+            anchors[index] = Anchor::Above { lines: passed, row };
+        }
+        anchors
+    }
+
+    /// Absolute lines for anchors from [`Self::anchor`], after the resize
+    ///
+    /// Lines the resize pushed out of the grid are `None`.
+    pub(crate) fn find(&self, grid: &Grid<Cell>, anchors: &[Anchor]) -> Vec<Option<usize>> {
+        let cursor = grid.cursor.point.line;
+        let top = grid.topmost_line();
+        let bottom = grid.bottommost_line();
+        let mut found = vec![None; anchors.len()];
+
+        let above = |index: &usize| match anchors[*index] {
+            Anchor::Above { lines, .. } => lines,
+            Anchor::Below(_) => 0,
+        };
+        let mut order: Vec<usize> = (0..anchors.len()).collect();
+        order.sort_by_key(above);
+        let mut line = cursor;
+        let mut passed = 0;
+        for index in order {
+            let target = match anchors[index] {
+                Anchor::Below(rows) => cursor + rows,
+                Anchor::Above { lines, row } => {
+                    // Walk up to the last row of the line's logical line
+                    while passed < lines && line > top {
+                        line -= 1;
+                        if !wraps(grid, line) {
+                            passed += 1;
+                        }
+                    }
+                    if passed < lines {
+                        continue;
+                    }
+                    (logical_start(grid, line, top) + row).min(line)
+                }
+            };
+            if target <= bottom {
+                found[index] = self.absolute(grid, target);
+            }
+        }
+        found
+    }
+}
+
+/// Check if a row continues onto the next one
+fn wraps(grid: &Grid<Cell>, line: Line) -> bool {
+    grid[line][Column(grid.columns() - 1)]
+        .flags
+        .contains(Flags::WRAPLINE)
+}
+
+/// First row of the logical line a row belongs to
+pub(crate) fn logical_start(grid: &Grid<Cell>, mut line: Line, top: Line) -> Line {
+    while line > top && wraps(grid, line - 1) {
+        line -= 1;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacritty_terminal::vte::ansi::Color;
+
+    fn scroll(grid: &mut Grid<Cell>, lines: usize) {
+        let screen = Line(0)..Line(grid.screen_lines() as i32);
+        grid.scroll_up::<Color>(&screen, lines);
+    }
+
+    #[test]
+    fn absolute_lines_survive_scrolling() {
+        let mut grid: Grid<Cell> = Grid::new(4, 10, 100);
+        let mut scrollback = Scrollback::default();
+        let absolute = scrollback.absolute(&grid, Line(2)).unwrap();
+
+        scroll(&mut grid, 3);
+        assert_eq!(scrollback.line(&grid, absolute), Line(-1));
+
+        // Lines keep their numbers as the lines above them leave
+        scrollback.evict(3);
+        grid.update_history(0);
+        assert_eq!(scrollback.absolute(&grid, Line(0)), Some(3));
+        assert_eq!(scrollback.absolute(&grid, Line(-1)), None);
+    }
+
+    #[test]
+    fn anchors_count_logical_lines() {
+        let mut grid: Grid<Cell> = Grid::new(4, 10, 100);
+        let scrollback = Scrollback::default();
+        // Rows 0 and 1 are one logical line; the cursor is on row 3
+        grid[Line(0)][Column(9)].flags.insert(Flags::WRAPLINE);
+        grid.cursor.point.line = Line(3);
+
+        let anchors = scrollback.anchor(&grid, &[1, 0, 2]);
+        assert_eq!(
+            anchors,
+            vec![
+                Anchor::Above { lines: 2, row: 1 },
+                Anchor::Above { lines: 2, row: 0 },
+                Anchor::Above { lines: 1, row: 0 },
+            ]
+        );
+        assert_eq!(
+            scrollback.find(&grid, &anchors),
+            vec![Some(1), Some(0), Some(2)]
+        );
+    }
+}
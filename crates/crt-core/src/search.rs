@@ -0,0 +1,174 @@
+//! Search of the screen and history
+//!
+//! A [`Search`] compiles its query for alacritty_terminal's regex search,
+//! which reads cells rather than copied text and runs on across soft-wrapped
+//! rows. Matches are kept by absolute line (see [`crate::scrollback`]):
+//! history doesn't change as output scrolls, so searching again as output
+//! arrives only covers the lines from the top of the screen down. A resize,
+//! a fold or a reset moves lines around, and the search starts over.
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Direction, Line, Point};
+use alacritty_terminal::term::Term;
+use alacritty_terminal::term::search::{RegexIter, RegexSearch};
+
+use crate::scrollback::{self, Scrollback};
+
+/// How a search query is matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Read the query as a regular expression rather than plain text
+    pub regex: bool,
+    /// Match case exactly, rather than ignoring it
+    pub case_sensitive: bool,
+    /// Only match the query as a whole word
+    pub whole_word: bool,
+}
+
+/// A query, with the matches found for it so far
+#[derive(Debug, Clone)]
+pub struct Search {
+    regex: RegexSearch,
+    /// Matches by absolute line and column, first cell to last, oldest first
+    matches: VecDeque<RangeInclusive<(usize, Column)>>,
+    /// First absolute line that may have changed since the last search
+    resume: usize,
+    /// The terminal's relayout count and screen when last searched
+    seen: Option<(u64, bool)>,
+}
+
+impl Search {
+    /// Compile a query, or `None` if it isn't a valid regex
+    pub fn new(query: &str, options: SearchOptions) -> Option<Self> {
+        match RegexSearch::new(&pattern(query, options)) {
+            Ok(regex) => Some(Self {
+                regex,
+                matches: VecDeque::new(),
+                resume: 0,
+                seen: None,
+            }),
+            Err(err) => {
+                log::debug!("Invalid search {:?}: {}", query, err);
+                None
+            }
+        }
+    }
+
+    /// Search the lines that may have changed since the last search
+    ///
+    /// `seen` identifies the terminal's layout; when it differs from last
+    /// time, every line is searched again. Returns how many earlier matches
+    /// were dropped from the front as their lines left history.
+    pub(crate) fn update<T>(
+        &mut self,
+        term: &Term<T>,
+        scrollback: &Scrollback,
+        seen: (u64, bool),
+    ) -> usize {
+        if self.seen != Some(seen) {
+            self.matches.clear();
+            self.resume = 0;
+            self.seen = Some(seen);
+        }
+
+        let first = scrollback.first();
+        let before = self.matches.len();
+        while self.matches.front().is_some_and(|m| m.start().0 < first) {
+            self.matches.pop_front();
+        }
+        let dropped = before - self.matches.len();
+
+        // Matches don't cross a line break, so none before a logical line's
+        // start runs into it
+        let resume = self.resume.max(first);
+        while self.matches.back().is_some_and(|m| m.start().0 >= resume) {
+            self.matches.pop_back();
+        }
+
+        let grid = term.grid();
+        let start = Point::new(scrollback.line(grid, resume), Column(0));
+        let end = Point::new(grid.bottommost_line(), grid.last_column());
+        let absolute = |point: Point| {
+            let line = scrollback.absolute(grid, point.line).unwrap_or(first);
+            (line, point.column)
+        };
+        for found in RegexIter::new(start, end, Direction::Right, term, &mut self.regex) {
+            self.matches
+                .push_back(absolute(*found.start())..=absolute(*found.end()));
+        }
+
+        // History above the screen stays as it is until it's dropped
+        let top = scrollback::logical_start(grid, Line(0), grid.topmost_line());
+        self.resume = scrollback.absolute(grid, top).unwrap_or(first);
+        dropped
+    }
+
+    /// Matches in absolute lines, oldest first
+    pub(crate) fn matches(&self) -> impl Iterator<Item = &RangeInclusive<(usize, Column)>> {
+        self.matches.iter()
+    }
+}
+
+/// Regex for a query: escaped unless it's a regex, with the case and word
+/// options as flags
+///
+/// alacritty_terminal ignores case unless the pattern has an uppercase
+/// letter, so the case flag is always set. Word boundaries are ASCII ones,
+/// which its lazy DFA handles for any text.
+fn pattern(query: &str, options: SearchOptions) -> String {
+    let mut pattern = String::from(if options.case_sensitive {
+        "(?-i)"
+    } else {
+        "(?i)"
+    });
+    let query = if options.regex {
+        query.to_string()
+    } else {
+        escape(query)
+    };
+    if options.whole_word {
+        pattern.push_str(&format!(r"(?-u:\b)(?:{query})(?-u:\b)"));
+    } else {
+        pattern.push_str(&format!("(?:{query})"));
+    }
+    pattern
+}
+
+/// Escape the characters a regex treats specially
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if r"\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_queries_are_escaped() {
+        let options = SearchOptions::default();
+        assert_eq!(pattern("a.b(c)", options), r"(?i)(?:a\.b\(c\))");
+        assert!(Search::new("(", options).is_some());
+    }
+
+    #[test]
+    fn options_become_flags() {
+        let options = SearchOptions {
+            regex: true,
+            case_sensitive: true,
+            whole_word: true,
+        };
+        assert_eq!(pattern("fo+", options), r"(?-i)(?-u:\b)(?:fo+)(?-u:\b)");
+        // An unfinished regex doesn't compile
+        assert!(Search::new("(fo", options).is_none());
+    }
+}
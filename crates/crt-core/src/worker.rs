@@ -0,0 +1,334 @@
+//! Per-terminal parser thread
+//!
+//! Each shell's PTY output is parsed on a thread of its own, so a flood in
+//! one tab (`cat` of a large log, a noisy build) can't stall input handling
+//! or rendering. The UI thread locks the terminal briefly each frame to read
+//! it; the worker never holds the lock for longer than `MAX_LOCK_TIME`.
+//!
+//! The worker also enforces the deadline of a synchronized update (DEC mode
+//! 2026): output the program buffered but never released is applied once
+//! it expires, or when the PTY closes.
+//!
+//! An optional [`Wakeup`] lets an event-driven UI sleep until there's
+//! something new to draw instead of polling the worker every frame.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::Terminal;
+use crate::pty::PtyOutput;
+
+/// Most bytes coalesced from queued reads into one parse batch
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+/// Bytes parsed between checks of the lock budget
+const PARSE_CHUNK_BYTES: usize = 64 * 1024;
+/// Longest the worker holds the terminal lock before yielding it
+const MAX_LOCK_TIME: Duration = Duration::from_millis(4);
+/// How often an idle worker checks whether its terminal is gone
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+/// Callback the parser thread runs when new output is ready to draw
+///
+/// Runs once per batch of updates (not again until the UI has taken them),
+/// whenever a synchronized update ends, and once more when the PTY closes.
+#[derive(Clone)]
+pub struct Wakeup(Arc<dyn Fn() + Send + Sync>);
+
+impl Wakeup {
+    pub fn new(wake: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Arc::new(wake))
+    }
+
+    fn wake(&self) {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Wakeup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Wakeup")
+    }
+}
+
+/// Flags shared between a worker thread and its handle
+#[derive(Default)]
+struct WorkerState {
+    /// Output was parsed since the handle last checked
+    updated: AtomicBool,
+    /// Output was received that hasn't been parsed yet
+    busy: AtomicBool,
+    /// Run when `updated` is first set and when the thread exits
+    wakeup: Option<Wakeup>,
+}
+
+impl WorkerState {
+    fn mark_updated(&self) {
+        if !self.updated.swap(true, Ordering::AcqRel) {
+            self.wake();
+        }
+    }
+
+    fn wake(&self) {
+        if let Some(wakeup) = &self.wakeup {
+            wakeup.wake();
+        }
+    }
+}
+
+/// Handle to a thread that parses PTY output into a shared [`Terminal`]
+pub struct ParserWorker {
+    state: Arc<WorkerState>,
+    handle: JoinHandle<()>,
+}
+
+impl ParserWorker {
+    /// Start parsing `output` into `terminal`
+    ///
+    /// The thread exits once the PTY closes or the terminal is dropped.
+    pub fn spawn(
+        output: PtyOutput,
+        terminal: &Arc<Mutex<Terminal>>,
+        wakeup: Option<Wakeup>,
+    ) -> Self {
+        let state = Arc::new(WorkerState {
+            wakeup,
+            ..WorkerState::default()
+        });
+        let terminal = Arc::downgrade(terminal);
+        let thread_state = Arc::clone(&state);
+        let handle = thread::Builder::new()
+            .name("crt-parser".into())
+            .spawn(move || run(output, terminal, &thread_state))
+            .expect("Failed to spawn parser thread");
+        Self { state, handle }
+    }
+
+    /// Check whether output was parsed since the last call
+    pub fn take_updated(&self) -> bool {
+        self.state.updated.swap(false, Ordering::AcqRel)
+    }
+
+    /// Check for parsed output without taking it
+    pub fn has_update(&self) -> bool {
+        self.state.updated.load(Ordering::Acquire)
+    }
+
+    /// Check whether everything received so far has been parsed
+    pub fn is_idle(&self) -> bool {
+        !self.state.busy.load(Ordering::Acquire)
+    }
+
+    /// Check whether the thread has exited (the PTY closed)
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the thread to finish, which happens once the PTY closes
+    pub fn join(self) {
+        let _ = self.handle.join();
+    }
+}
+
+fn run(output: PtyOutput, terminal: Weak<Mutex<Terminal>>, state: &WorkerState) {
+    let mut batch = Vec::with_capacity(MAX_BATCH_BYTES);
+    // Deadline of the synchronized update left open by the last batch
+    let mut sync_deadline: Option<Instant> = None;
+
+    loop {
+        let first = match output.chunks.try_recv() {
+            Ok(chunk) => chunk,
+            Err(TryRecvError::Disconnected) => break,
+            Err(TryRecvError::Empty) => {
+                state.busy.store(false, Ordering::Release);
+                let wait = sync_deadline.map_or(IDLE_POLL, |deadline| {
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(IDLE_POLL)
+                });
+                match output.chunks.recv_timeout(wait) {
+                    Ok(chunk) => chunk,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let Some(terminal) = terminal.upgrade() else {
+                            break;
+                        };
+                        if sync_deadline.is_some() {
+                            sync_deadline = end_expired_sync_update(&terminal, state);
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        };
+        state.busy.store(true, Ordering::Release);
+
+        // Coalesce everything already queued into one batch
+        batch.clear();
+        batch.extend_from_slice(&first);
+        let _ = output.recycle.send(first);
+        while batch.len() < MAX_BATCH_BYTES
+            && let Ok(chunk) = output.chunks.try_recv()
+        {
+            batch.extend_from_slice(&chunk);
+            let _ = output.recycle.send(chunk);
+        }
+
+        let Some(terminal) = terminal.upgrade() else {
+            break;
+        };
+        crate::log_pty_output(&batch);
+        sync_deadline = parse_batch(&terminal, &batch, state);
+    }
+
+    // Nothing more is coming to end an open synchronized update
+    if sync_deadline.is_some()
+        && let Some(terminal) = terminal.upgrade()
+    {
+        lock(&terminal).end_sync_update();
+        state.mark_updated();
+    }
+
+    state.busy.store(false, Ordering::Release);
+    log::debug!("Parser thread exiting");
+    // Let the UI notice the shell is gone without waiting for other input
+    state.wake();
+}
+
+fn lock(terminal: &Mutex<Terminal>) -> MutexGuard<'_, Terminal> {
+    terminal.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Parse `batch`, giving up the lock every `MAX_LOCK_TIME` so the UI thread
+/// can take a frame in between
+///
+/// Returns the deadline of a synchronized update left open by the batch.
+fn parse_batch(terminal: &Mutex<Terminal>, batch: &[u8], state: &WorkerState) -> Option<Instant> {
+    let mut sync_deadline = None;
+    let mut chunks = batch.chunks(PARSE_CHUNK_BYTES).peekable();
+    while chunks.peek().is_some() {
+        let sync_ended = {
+            let mut terminal = lock(terminal);
+            let was_in_sync = terminal.in_sync_update();
+            let start = Instant::now();
+            while start.elapsed() < MAX_LOCK_TIME
+                && let Some(chunk) = chunks.next()
+            {
+                terminal.process_input(chunk);
+            }
+            sync_deadline = terminal.sync_update_deadline();
+            was_in_sync && sync_deadline.is_none()
+        };
+        state.mark_updated();
+        // The UI holds frames during an update, so it may not have taken
+        // the output that started it
+        if sync_ended {
+            state.wake();
+        }
+        thread::yield_now();
+    }
+    sync_deadline
+}
+
+/// Apply a synchronized update that outlived its deadline, returning the
+/// deadline still pending if it hasn't expired yet
+fn end_expired_sync_update(terminal: &Mutex<Terminal>, state: &WorkerState) -> Option<Instant> {
+    let mut terminal = lock(terminal);
+    if terminal.end_expired_sync_update() {
+        state.mark_updated();
+        state.wake();
+    }
+    terminal.sync_update_deadline()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+    use std::sync::mpsc;
+
+    fn channel_output() -> (mpsc::Sender<Vec<u8>>, PtyOutput) {
+        let (tx, chunks) = mpsc::channel();
+        let (recycle, _) = mpsc::channel();
+        (tx, PtyOutput { chunks, recycle })
+    }
+
+    #[test]
+    fn parses_all_output_before_exiting() {
+        let terminal = Arc::new(Mutex::new(Terminal::new(Size::new(80, 24))));
+        let (tx, output) = channel_output();
+        let worker = ParserWorker::spawn(output, &terminal, None);
+
+        tx.send(b"hello ".to_vec()).unwrap();
+        tx.send(b"\x1b[1mworld".to_vec()).unwrap();
+        drop(tx);
+        let state = Arc::clone(&worker.state);
+        worker.join();
+
+        assert!(state.updated.load(Ordering::Acquire));
+        let lines = terminal.lock().unwrap().all_lines_text();
+        assert!(lines.iter().any(|(_, line)| line.contains("hello world")));
+    }
+
+    #[test]
+    fn exits_when_terminal_dropped() {
+        let terminal = Arc::new(Mutex::new(Terminal::new(Size::new(80, 24))));
+        let (_tx, output) = channel_output();
+        let worker = ParserWorker::spawn(output, &terminal, None);
+        assert!(worker.is_idle());
+
+        drop(terminal);
+        // Returns within one IDLE_POLL even though the PTY never closed
+        worker.join();
+    }
+
+    #[test]
+    fn applies_sync_update_the_program_never_ends() {
+        let terminal = Arc::new(Mutex::new(Terminal::new(Size::new(80, 24))));
+        let (tx, output) = channel_output();
+        let _worker = ParserWorker::spawn(output, &terminal, None);
+
+        tx.send(b"\x1b[?2026hstuck update".to_vec()).unwrap();
+        let start = Instant::now();
+        let applied = || {
+            let lines = terminal.lock().unwrap().all_lines_text();
+            lines.iter().any(|(_, line)| line.contains("stuck update"))
+        };
+        while !applied() && start.elapsed() < Duration::from_secs(2) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(applied());
+        assert!(!terminal.lock().unwrap().in_sync_update());
+    }
+
+    #[test]
+    fn wakes_once_per_update_and_on_exit() {
+        use std::sync::atomic::AtomicUsize;
+
+        let terminal = Arc::new(Mutex::new(Terminal::new(Size::new(80, 24))));
+        let (tx, output) = channel_output();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&wakes);
+        let wakeup = Wakeup::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let worker = ParserWorker::spawn(output, &terminal, Some(wakeup));
+
+        tx.send(b"one".to_vec()).unwrap();
+        while !worker.has_update() {
+            thread::yield_now();
+        }
+        // Not taken yet, so more output doesn't wake again
+        tx.send(b"two".to_vec()).unwrap();
+        drop(tx);
+        let state = Arc::clone(&worker.state);
+        worker.join();
+
+        assert!(state.updated.load(Ordering::Acquire));
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,92 @@
+//! Semantic zones from OSC 133 marks
+//!
+//! Each mark starts a zone on the line the cursor is on. [`ZoneMap`] keys
+//! zones by absolute line (see [`crate::scrollback`]), so they stay with their
+//! lines as output scrolls and are dropped once those lines leave history.
+
+use std::collections::BTreeMap;
+
+use crate::SemanticZone;
+
+/// Semantic zones keyed by absolute line
+#[derive(Debug, Default)]
+pub(crate) struct ZoneMap {
+    zones: BTreeMap<usize, SemanticZone>,
+    /// A zone has been marked at some point
+    marked: bool,
+}
+
+impl ZoneMap {
+    /// Mark the zone starting on a line
+    pub(crate) fn insert(&mut self, line: usize, zone: SemanticZone) {
+        self.zones.insert(line, zone);
+        self.marked = true;
+    }
+
+    /// Zone marked on a line
+    pub(crate) fn get(&self, line: usize) -> SemanticZone {
+        self.zones
+            .get(&line)
+            .copied()
+            .unwrap_or(SemanticZone::Unknown)
+    }
+
+    /// Check if a zone has ever been marked, even one since dropped
+    pub(crate) fn is_marked(&self) -> bool {
+        self.marked
+    }
+
+    /// Drop zones on lines before `first`, which have left history
+    pub(crate) fn prune(&mut self, first: usize) {
+        self.zones = self.zones.split_off(&first);
+    }
+
+    /// Drop every zone, e.g. when the terminal is reset
+    pub(crate) fn clear(&mut self) {
+        self.zones.clear();
+    }
+
+    /// Lines with a zone
+    pub(crate) fn lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.zones.keys().copied()
+    }
+
+    /// Move zones to the lines a resize put them on, dropping those it lost
+    pub(crate) fn relocate(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        self.zones = std::mem::take(&mut self.zones)
+            .into_iter()
+            .filter_map(|(line, zone)| Some((moved(line)?, zone)))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_drops_zones_that_left_history() {
+        let mut zones = ZoneMap::default();
+        zones.insert(0, SemanticZone::Prompt);
+        zones.insert(2, SemanticZone::Output);
+
+        zones.prune(2);
+        assert_eq!(zones.get(0), SemanticZone::Unknown);
+        assert_eq!(zones.get(2), SemanticZone::Output);
+
+        zones.clear();
+        assert_eq!(zones.lines().count(), 0);
+        assert!(zones.is_marked());
+    }
+
+    #[test]
+    fn relocate_moves_and_drops_zones() {
+        let mut zones = ZoneMap::default();
+        zones.insert(3, SemanticZone::Prompt);
+        zones.insert(7, SemanticZone::Input);
+
+        zones.relocate(|line| (line > 5).then(|| line - 2));
+        assert_eq!(zones.lines().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(zones.get(5), SemanticZone::Input);
+    }
+}
@@ -240,6 +240,8 @@ pub struct GlyphCache {
     staging_data: Vec<u8>,
     /// Pending uploads
     pending_uploads: Vec<(u32, u32, u32, u32, usize)>,
+    /// Bumped whenever the atlas is cleared, invalidating positioned glyphs
+    generation: u64,
 }
 
 impl GlyphCache {
@@ -363,6 +365,7 @@ impl GlyphCache {
             packer: AtlasPacker::new(atlas_width, atlas_height),
             staging_data: Vec::new(),
             pending_uploads: Vec::new(),
+            generation: 0,
         })
     }
 
@@ -447,6 +450,7 @@ impl GlyphCache {
                 self.packer.reset();
                 self.staging_data.clear();
                 self.pending_uploads.clear();
+                self.generation += 1;
                 // Retry allocation after reset — should always succeed
                 self.packer
                     .allocate(image.placement.width, image.placement.height)?
@@ -592,6 +596,11 @@ impl GlyphCache {
         self.cached_cell_width
     }
 
+    /// Atlas generation; glyphs positioned under an older one have stale UVs
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     /// Get baseline offset from top of cell
     pub fn baseline_offset(&self) -> f32 {
         self.baseline_offset
@@ -178,6 +178,13 @@ impl GridRenderer {
         }
     }
 
+    /// Add instances prepared earlier (e.g. cached from a previous frame)
+    pub fn push_instances(&mut self, instances: &[GlyphInstance]) {
+        let room = self.instance_capacity - self.instances.len();
+        self.instances
+            .extend_from_slice(&instances[..instances.len().min(room)]);
+    }
+
     /// Update screen size uniform (only writes if size changed)
     pub fn update_screen_size(&mut self, queue: &wgpu::Queue, width: f32, height: f32) {
         // Skip if size hasn't changed
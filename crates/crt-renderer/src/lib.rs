@@ -38,7 +38,7 @@ pub use headless::{HeadlessError, HeadlessRenderer};
 pub use glyph_cache::{
     CachedGlyph, FontVariants, GlyphCache, GlyphKey, GlyphStyle, PositionedGlyph,
 };
-pub use grid_renderer::GridRenderer;
+pub use grid_renderer::{GlyphInstance, GridRenderer};
 pub use shared_pipelines::SharedPipelines;
 pub use mock::{MockRenderer, RenderCall};
 pub use rect_renderer::RectRenderer;
@@ -445,6 +445,12 @@ impl CrtPipeline {
         self.enabled
     }
 
+    /// Check if the effect changes over time (flicker), so it needs frames
+    /// even when nothing else does
+    pub fn is_animated(&self) -> bool {
+        self.enabled && self.params.flicker > 0.0
+    }
+
     pub fn create_bind_group(
         &self,
         device: &wgpu::Device,
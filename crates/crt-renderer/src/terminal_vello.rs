@@ -119,6 +119,12 @@ impl TerminalVelloRenderer {
         }
     }
 
+    /// When the cursor next blinks, if blinking is enabled
+    pub fn next_blink_at(&self) -> Option<Instant> {
+        self.blink_enabled
+            .then(|| self.last_blink_toggle + self.blink_interval)
+    }
+
     /// Reset blink state (cursor becomes visible, timer resets)
     /// Call this when cursor moves or user types
     pub fn reset_blink(&mut self) {
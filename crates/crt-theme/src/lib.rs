@@ -1013,6 +1013,24 @@ impl Default for HighlightStyle {
     }
 }
 
+/// Folded command output appearance (the summary row)
+#[derive(Debug, Clone, Copy)]
+pub struct CommandFoldStyle {
+    /// Background across the summary row
+    pub background: Color,
+    /// Text color of the summary
+    pub foreground: Color,
+}
+
+impl Default for CommandFoldStyle {
+    fn default() -> Self {
+        Self {
+            background: Color::from_hex_alpha(0x8080a033), // Faint slate band
+            foreground: Color::from_hex(0x9090b0),
+        }
+    }
+}
+
 /// Tab bar styling
 #[derive(Debug, Clone, Copy)]
 pub struct TabBarStyle {
@@ -2002,6 +2020,7 @@ pub struct Theme {
     // States
     pub selection: SelectionStyle,
     pub highlight: HighlightStyle,
+    pub command_fold: CommandFoldStyle,
     pub cursor_color: Color,
     pub cursor_glow: Option<TextShadow>,
 
@@ -2059,6 +2078,7 @@ impl Theme {
             palette: AnsiPalette::default(),
             selection: SelectionStyle::default(),
             highlight: HighlightStyle::default(),
+            command_fold: CommandFoldStyle::default(),
             cursor_color: Color::from_hex(0x00ffff),
             cursor_glow: None,
             text_shadow: Some(TextShadow::default()),
@@ -2093,6 +2113,7 @@ impl Theme {
             palette: AnsiPalette::default(),
             selection: SelectionStyle::default(),
             highlight: HighlightStyle::default(),
+            command_fold: CommandFoldStyle::default(),
             cursor_color: Color::from_hex(0xffffff),
             cursor_glow: None,
             text_shadow: None,
@@ -728,6 +728,9 @@ fn apply_properties(
         ":terminal::highlight" | "terminal::highlight" => {
             apply_highlight_properties(theme, standard, custom)?;
         }
+        ":terminal::command-fold" | "terminal::command-fold" => {
+            apply_command_fold_properties(theme, standard)?;
+        }
         ":terminal::cursor" | "terminal::cursor" => {
             apply_cursor_properties(theme, standard)?;
         }
@@ -905,6 +908,19 @@ fn apply_highlight_properties(
     Ok(())
 }
 
+fn apply_command_fold_properties(
+    theme: &mut Theme,
+    standard: &HashMap<String, String>,
+) -> Result<(), ThemeParseError> {
+    if let Some(bg) = standard.get("background") {
+        theme.command_fold.background = parse_color(bg)?;
+    }
+    if let Some(fg) = standard.get("color") {
+        theme.command_fold.foreground = parse_color(fg)?;
+    }
+    Ok(())
+}
+
 fn apply_cursor_properties(
     theme: &mut Theme,
     standard: &HashMap<String, String>,
@@ -2324,6 +2340,23 @@ mod tests {
         assert!((glow.color.a - 0.8).abs() < 0.01);
     }
 
+    #[test]
+    fn test_parse_command_fold() {
+        let css = r#"
+            :terminal::command-fold {
+                background: rgba(255, 0, 255, 0.2);
+                color: #00ffff;
+            }
+        "#;
+
+        let theme = parse_theme(css).unwrap();
+        let fold = theme.command_fold;
+        assert!((fold.background.r - 1.0).abs() < 0.01);
+        assert!((fold.background.a - 0.2).abs() < 0.01);
+        assert!((fold.foreground.g - 1.0).abs() < 0.01);
+        assert!((fold.foreground.r - 0.0).abs() < 0.01);
+    }
+
     // ========== Color Parsing Edge Cases ==========
 
     #[test]
@@ -67,6 +67,7 @@ if [ -d "$PROJECT_ROOT/assets/shell" ]; then
     cp -r "$PROJECT_ROOT/assets/shell/"* "$CONFIG_DIR/shell/"
     echo "  - crt-zsh-init/"
     echo "  - crt-bash-init"
+    echo "  - crt-fish-init.fish"
 fi
 
 echo ""
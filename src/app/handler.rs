@@ -6,20 +6,21 @@ use std::time::Instant;
 
 use crate::input::{
     drag::{self, TabDragState},
-    handle_cursor_moved, handle_keyboard_input, handle_mouse_input, handle_mouse_wheel,
-    handle_resize, handle_tab_click, KeyboardAction,
+    handle_cursor_moved, handle_key_release, handle_keyboard_input, handle_mouse_input,
+    handle_mouse_wheel, handle_resize, handle_tab_click, KeyboardAction,
 };
 use super::initialization::{DetachPayload, MergePayload};
-use crate::render::render_frame;
+use crate::render::{process_background_shells, render_frame};
 use crate::window;
 use winit::{
     application::ApplicationHandler,
     event::{ElementState, WindowEvent},
-    event_loop::ActiveEventLoop,
+    event_loop::{ActiveEventLoop, ControlFlow},
     window::WindowId,
 };
 
 use super::initialization::handle_scale_factor_change;
+use super::scheduler;
 use super::App;
 use super::FONT_SCALE_STEP;
 
@@ -77,8 +78,23 @@ impl ApplicationHandler for App {
             return;
         };
 
+        // Input keeps backdrop animation running (see pause_animation_after_secs)
+        if matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::Focused(true)
+        ) {
+            state.render.last_activity = Instant::now();
+        }
+
         match event {
             WindowEvent::CloseRequested => {
+                if !state.confirm_close_window() {
+                    return;
+                }
                 self.windows.remove(&id);
                 if self.focused_window == Some(id) {
                     self.focused_window = self.windows.keys().next().copied();
@@ -143,17 +159,15 @@ impl ApplicationHandler for App {
                 };
 
                 // Delegate to keyboard handler
-                let action = handle_keyboard_input(
-                    state,
-                    &event.logical_key,
-                    event.text.as_ref().map(|s| s.as_str()),
-                    &self.modifiers,
-                    &self.config.keybindings,
-                );
+                let action =
+                    handle_keyboard_input(state, &event, &self.modifiers, &self.config.keybindings);
 
                 // Handle actions that require App-level access
                 match action {
                     KeyboardAction::Quit => {
+                        self.request_quit(event_loop);
+                    }
+                    KeyboardAction::ForceQuit => {
                         event_loop.exit();
                     }
                     KeyboardAction::CloseWindow => {
@@ -183,6 +197,9 @@ impl ApplicationHandler for App {
                     KeyboardAction::OpenConfig => {
                         self.open_config_file();
                     }
+                    KeyboardAction::RespawnShell(tab_id) => {
+                        self.respawn_shell(id, tab_id);
+                    }
                     KeyboardAction::Handled
                     | KeyboardAction::NotHandled
                     | KeyboardAction::Scroll(_)
@@ -193,12 +210,21 @@ impl ApplicationHandler for App {
                     | KeyboardAction::SearchNavigate { .. }
                     | KeyboardAction::PrevTab
                     | KeyboardAction::NextTab
-                    | KeyboardAction::SelectTab(_) => {
+                    | KeyboardAction::SelectTab(_)
+                    | KeyboardAction::PrevPrompt
+                    | KeyboardAction::NextPrompt
+                    | KeyboardAction::SelectCommandOutput
+                    | KeyboardAction::CopyCommandOutput
+                    | KeyboardAction::ToggleCommandFold => {
                         // Already handled by keyboard module or no action needed
                     }
                 }
             }
 
+            WindowEvent::KeyboardInput { event, .. } => {
+                handle_key_release(state, &event, &self.modifiers);
+            }
+
             WindowEvent::Resized(size) => {
                 let shared = self.shared_gpu.as_ref().unwrap();
                 handle_resize(state, shared, size.width, size.height);
@@ -281,7 +307,7 @@ impl ApplicationHandler for App {
                         state.window.request_redraw();
                     }
                 }
-                handle_cursor_moved(state, position.x as f32, position.y as f32);
+                handle_cursor_moved(state, position.x as f32, position.y as f32, &self.modifiers);
             }
 
             WindowEvent::MouseInput {
@@ -471,7 +497,7 @@ impl ApplicationHandler for App {
             }
 
             WindowEvent::MouseWheel { delta, .. } => {
-                handle_mouse_wheel(state, delta);
+                handle_mouse_wheel(state, delta, &self.modifiers);
             }
 
             WindowEvent::RedrawRequested => {
@@ -517,6 +543,11 @@ impl ApplicationHandler for App {
 
                 let shared = self.shared_gpu.as_mut().unwrap();
                 render_frame(state, shared);
+
+                // The last tab's shell exited and `on_exit` didn't hold it
+                if state.close_requested {
+                    self.close_window(id);
+                }
             }
 
             _ => {}
@@ -555,7 +586,7 @@ impl ApplicationHandler for App {
                 let tab_id = payload.tab.id;
                 target.gpu.tab_bar.insert_existing_tab(payload.tab, payload.insert_index);
                 let mut shell = payload.shell;
-                shell.resize(crt_core::Size::new(target.cols, target.rows));
+                shell.resize(target.terminal_size());
                 target.shells.insert(tab_id, shell);
                 target.content_hashes.insert(tab_id, payload.content_hash);
                 target.gpu.tab_bar.select_tab(tab_id);
@@ -595,54 +626,52 @@ impl ApplicationHandler for App {
             self.create_window(event_loop);
         }
 
-        // FRAME THROTTLING - Critical fix for Metal/wgpu memory leak (November 2024)
-        //
-        // WHY: wgpu/Metal on macOS has a bug where IOAccelerator drawable allocations grow
-        // unboundedly when frames are rendered at high rates. With ControlFlow::Poll and
-        // continuous request_redraw(), we were seeing 1500+ GPU allocations per second,
-        // causing memory to balloon from ~130MB to 4-9GB within minutes.
-        //
-        // WHAT: Limits redraws to ~60fps by only calling request_redraw() when at least
-        // 16.6ms has elapsed since the last frame. This keeps IOAccelerator regions
-        // at ~500 instead of 40,000+ and memory stable at ~150-220MB.
-        //
-        // RE-EVALUATE WHEN:
-        // - wgpu updates to 24.x+ (check if Metal backend fixes drawable allocation)
-        // - Testing on non-macOS platforms (this may not be needed on Windows/Linux)
-        // - If we need variable refresh rate support (would need smarter throttling)
-        // - If Apple fixes the IOAccelerator memory management in a future macOS version
-        //
-        // Related: https://github.com/gfx-rs/wgpu/issues/3292 (Metal memory growth issues)
-        const TARGET_FRAME_TIME: std::time::Duration =
-            std::time::Duration::from_micros(16666); // ~60fps
-        let elapsed = self.last_frame_time.elapsed();
-
-        // Focused window: 60fps for smooth effects
-        if elapsed >= TARGET_FRAME_TIME {
-            self.last_frame_time = Instant::now();
-
-            if let Some(focused_id) = self.focused_window
-                && let Some(state) = self.windows.get(&focused_id)
-                && !state.render.occluded
-            {
-                state.window.request_redraw();
-            }
+        // Tell programs that asked (mode 1004) about window focus and tab switches
+        for state in self.windows.values_mut() {
+            state.sync_focus_reports();
         }
 
-        // Unfocused windows: 10fps for PTY output updates (saves GPU work)
-        const UNFOCUSED_FRAME_TIME: std::time::Duration =
-            std::time::Duration::from_millis(100);
-        let unfocused_elapsed = self.last_unfocused_frame_time.elapsed();
+        // Background tabs and occluded windows aren't drawn, but their programs
+        // still expect replies and their exits still need handling
+        for state in self.windows.values_mut() {
+            process_background_shells(state);
+        }
+        let exited: Vec<_> = self
+            .windows
+            .iter()
+            .filter(|(_, state)| state.close_requested)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in exited {
+            self.close_window(id);
+        }
 
-        if unfocused_elapsed >= UNFOCUSED_FRAME_TIME {
-            self.last_unfocused_frame_time = Instant::now();
+        // FRAME SCHEDULING - sleep until some window needs a frame, capped at
+        // max_fps (focused) / unfocused_fps. The cap also keeps the wgpu/Metal
+        // drawable leak in check; see app::scheduler before raising it.
+        let now = Instant::now();
+        let window_config = &self.config.window;
+        let animation_pause = window_config.animation_pause();
+        let mut wake_at: Option<Instant> = None;
+        for (id, state) in self.windows.iter_mut() {
+            let poll_at = scheduler::poll_shells(state, now);
+            wake_at = Some(wake_at.map_or(poll_at, |wake_at| wake_at.min(poll_at)));
+            if state.render.occluded {
+                continue;
+            }
+            scheduler::update_activity(state, now, animation_pause);
 
-            for (id, state) in self.windows.iter() {
-                // Skip focused window (handled above) and occluded windows
-                if Some(*id) != self.focused_window && !state.render.occluded {
-                    state.window.request_redraw();
-                }
+            let fps = if Some(*id) == self.focused_window {
+                window_config.max_fps
+            } else {
+                window_config.unfocused_fps
+            };
+            match scheduler::next_frame_at(&state.render, scheduler::frame_interval(fps)) {
+                Some(at) if at <= now => state.window.request_redraw(),
+                Some(at) => wake_at = Some(wake_at.map_or(at, |wake_at| wake_at.min(at))),
+                None => {}
             }
         }
+        event_loop.set_control_flow(wake_at.map_or(ControlFlow::Wait, ControlFlow::WaitUntil));
     }
 }
@@ -9,7 +9,7 @@ use crate::config::Config;
 use crate::font;
 use crate::gpu::{SharedGpuState, WindowGpuState};
 use crate::window::{self, WindowState};
-use crt_core::{ShellTerminal, Size, SpawnOptions};
+use crt_core::{ShellTerminal, Size};
 use crt_renderer::{
     BackgroundImagePipeline, BackgroundImageState, CrtPipeline, EffectsRenderer, GlyphCache,
     GridEffect, GridRenderer, MatrixEffect, ParticleEffect, RainEffect, RectRenderer, ShapeEffect,
@@ -328,13 +328,10 @@ impl App {
             .and_then(|state| state.active_shell_cwd())
             .or_else(|| self.config.shell.working_directory.clone());
 
-        let spawn_options = SpawnOptions {
-            shell: self.config.shell.program.clone(),
-            cwd,
-            semantic_prompts: self.config.shell.semantic_prompts,
-            shell_assets_dir: Config::shell_assets_dir(),
-        };
-        if let Ok(shell) = ShellTerminal::with_options(Size::new(cols, rows), spawn_options) {
+        let spawn_options = self.shell_spawn_options(cwd);
+        let size = Size::new(cols, rows)
+            .with_cell_size(gpu.glyph_cache.cell_width(), gpu.glyph_cache.line_height());
+        if let Ok(shell) = ShellTerminal::with_options(size, spawn_options) {
             log::info!(
                 "Shell spawned for initial tab {} (semantic_prompts={})",
                 initial_tab_id,
@@ -353,14 +350,7 @@ impl App {
             rows,
             scale_factor,
             font_scale: 1.0,
-            render: window::RenderState {
-                dirty: true,
-                frame_count: 0,
-                occluded: false,
-                focused: true,
-                cached: Default::default(),
-                paste_pending: false,
-            },
+            render: Default::default(),
             interaction: Default::default(),
             ui: window::UiState {
                 search: Default::default(),
@@ -379,9 +369,14 @@ impl App {
                 copy_indicator: Default::default(),
                 toast: Default::default(),
                 window_rename: Default::default(),
+                confirm: Default::default(),
                 overrides: Default::default(),
                 pending_theme: None,
             },
+            clipboard: self.config.clipboard.clone(),
+            exit_behavior: self.config.shell.on_exit,
+            confirm_close_allowlist: self.config.shell.confirm_close_allowlist.clone(),
+            close_requested: false,
             custom_title: None,
             theme: theme.clone(),
             theme_name: theme_name.to_string(),
@@ -425,7 +420,7 @@ impl App {
 
             // Resize shell to match new window dimensions
             let mut shell = payload.shell;
-            shell.resize(Size::new(state.cols, state.rows));
+            shell.resize(state.terminal_size());
             state.shells.insert(tab_id, shell);
             state.content_hashes.insert(tab_id, payload.content_hash);
 
@@ -456,8 +451,6 @@ pub(crate) fn handle_scale_factor_change(
     config: &Config,
     new_scale: f32,
 ) {
-    use crt_core::Size;
-
     // Update scale factor
     state.scale_factor = new_scale;
 
@@ -526,8 +519,9 @@ pub(crate) fn handle_scale_factor_change(
     state.rows = new_rows;
 
     // Resize all shells
+    let terminal_size = state.terminal_size();
     for shell in state.shells.values_mut() {
-        shell.resize(Size::new(new_cols, new_rows));
+        shell.resize(terminal_size);
     }
 
     // Mark as dirty and invalidate content hashes
@@ -36,15 +36,21 @@ impl App {
                 let should_close = if let Some(state) = self.focused_window_mut() {
                     if state.gpu.tab_bar.tab_count() > 1 {
                         if let Some(id) = state.gpu.tab_bar.active_tab_id() {
-                            state.gpu.tab_bar.close_tab(id);
-                            state.remove_shell_for_tab(id);
-                            // Force redraw of new active tab to clear stale cached render state
-                            state.force_active_tab_redraw();
-                            state.window.request_redraw();
+                            if let Some(name) = state.busy_process(id) {
+                                state.ui.confirm.show(
+                                    format!("{name} is running. Close this tab?"),
+                                    crate::window::ConfirmAction::CloseTab(id),
+                                );
+                                state.render.dirty = true;
+                                state.window.request_redraw();
+                            } else {
+                                // close_tab forces a redraw of the new active tab
+                                state.close_tab(id);
+                            }
                         }
                         false
                     } else {
-                        true
+                        state.confirm_close_window()
                     }
                 } else {
                     false
@@ -54,11 +60,16 @@ impl App {
                 }
             }
             MenuAction::CloseWindow => {
-                if let Some(id) = self.focused_window {
+                if let Some(id) = self.focused_window
+                    && self
+                        .windows
+                        .get_mut(&id)
+                        .is_some_and(|state| state.confirm_close_window())
+                {
                     self.close_window(id);
                 }
             }
-            MenuAction::Quit => event_loop.exit(),
+            MenuAction::Quit => self.request_quit(event_loop),
             MenuAction::ToggleFullScreen => self.toggle_fullscreen_focused(),
             MenuAction::IncreaseFontSize => self.adjust_font_scale(FONT_SCALE_STEP),
             MenuAction::DecreaseFontSize => self.adjust_font_scale(-FONT_SCALE_STEP),
@@ -88,15 +99,23 @@ impl App {
                     state.ui.copy_indicator.trigger();
                 }
             }
+            MenuAction::ClearScrollback => {
+                if let Some(state) = self.focused_window_mut()
+                    && let Some(tab_id) = state.gpu.tab_bar.active_tab_id()
+                    && let Some(shell) = state.shells.get_mut(&tab_id)
+                {
+                    shell.clear_history();
+                    state.force_active_tab_redraw();
+                    state.window.request_redraw();
+                }
+            }
             MenuAction::Find => {
                 if let Some(state) = self.focused_window_mut() {
-                    // Toggle search mode
-                    state.ui.search.active = !state.ui.search.active;
-                    if !state.ui.search.active {
-                        // Clear search when closing
-                        state.ui.search.query.clear();
-                        state.ui.search.matches.clear();
-                        state.ui.search.current_match = 0;
+                    // Toggle search mode, clearing the query when closing
+                    if state.ui.search.active {
+                        state.ui.search.close();
+                    } else {
+                        state.ui.search.active = true;
                     }
                     state.force_active_tab_redraw();
                     state.window.request_redraw();
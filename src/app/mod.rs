@@ -7,20 +7,22 @@ mod effects;
 mod handler;
 mod initialization;
 mod menu_actions;
+mod scheduler;
 
 use std::collections::HashMap;
-use std::time::Instant;
+use std::path::PathBuf;
 
 use crate::config::{Config, ConfigPaths};
 use crate::gpu::SharedGpuState;
 use crate::input::drag::TabDragState;
 use crate::theme_registry::ThemeRegistry;
 use crate::watcher;
-use crate::window::WindowState;
+use crate::window::{ConfirmAction, TabId, WindowState};
 use crt_renderer::{
     BackgroundImageState, SpriteAnimationState, SpriteConfig, SpriteMotion, SpritePosition,
 };
 use crt_theme::Theme;
+use winit::event_loop::EventLoopProxy;
 use winit::window::WindowId;
 
 #[cfg(target_os = "macos")]
@@ -46,10 +48,8 @@ pub(crate) struct App {
     pub(crate) modifiers: winit::event::Modifiers,
     pub(crate) pending_new_window: bool,
     pub(crate) config_watcher: Option<watcher::ConfigWatcher>,
-    /// Last frame time for throttling focused window redraws (~60fps)
-    pub(crate) last_frame_time: Instant,
-    /// Last frame time for unfocused windows (~1fps for PTY updates)
-    pub(crate) last_unfocused_frame_time: Instant,
+    /// Wakes the event loop when a shell's parser thread has new output
+    pub(crate) event_loop_proxy: EventLoopProxy<()>,
     /// Global tab ID counter — ensures IDs are unique across all windows
     pub(crate) next_tab_id: u64,
     /// Active tab drag state (lives on App for cross-window visibility)
@@ -69,9 +69,14 @@ pub(crate) struct App {
 }
 
 impl App {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(event_loop_proxy: EventLoopProxy<()>) -> Self {
         let config = Config::load();
-        let config_watcher = watcher::ConfigWatcher::new();
+        let config_watcher = watcher::ConfigWatcher::new({
+            let proxy = event_loop_proxy.clone();
+            move || {
+                let _ = proxy.send_event(());
+            }
+        });
 
         // Initialize theme registry from themes directory
         let theme_registry = ConfigPaths::from_env_or_default()
@@ -91,8 +96,7 @@ impl App {
             modifiers: winit::event::Modifiers::default(),
             pending_new_window: false,
             config_watcher,
-            last_frame_time: Instant::now(),
-            last_unfocused_frame_time: Instant::now(),
+            event_loop_proxy,
             next_tab_id: 0,
             drag_state: None,
             pending_detach: None,
@@ -371,6 +375,9 @@ impl App {
         // Apply other config changes to all windows
         log::debug!("Applying config to {} windows", self.windows.len());
         for state in self.windows.values_mut() {
+            state.clipboard = self.config.clipboard.clone();
+            state.exit_behavior = self.config.shell.on_exit;
+            state.confirm_close_allowlist = self.config.shell.confirm_close_allowlist.clone();
             // Force redraw
             state.render.dirty = true;
             for hash in state.content_hashes.values_mut() {
@@ -434,8 +441,6 @@ impl App {
     /// showing the zoom indicator. Cross-platform (used by both the macOS menu
     /// and configurable keybindings).
     pub(crate) fn adjust_font_scale(&mut self, delta: f32) {
-        use crt_core::Size;
-
         let base_font_size = self.config.font.size;
         let focused_id = match self.focused_window {
             Some(id) => id,
@@ -494,8 +499,9 @@ impl App {
             state.rows = new_rows;
 
             // Resize all shells to match new grid size
+            let terminal_size = state.terminal_size();
             for shell in state.shells.values_mut() {
-                shell.resize(Size::new(new_cols, new_rows));
+                shell.resize(terminal_size);
             }
 
             // Trigger zoom indicator
@@ -546,17 +552,46 @@ impl App {
         }
     }
 
+    /// Spawn options for a new shell from the `[shell]` config
+    pub(crate) fn shell_spawn_options(&self, cwd: Option<PathBuf>) -> crt_core::SpawnOptions {
+        let shell = &self.config.shell;
+        // Configured variables go last so they can override ours
+        let env = std::iter::once((
+            "TERM_PROGRAM_VERSION".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ))
+        .chain(shell.env.clone())
+        .collect();
+        crt_core::SpawnOptions {
+            shell: shell.program.clone(),
+            cwd,
+            semantic_prompts: shell.semantic_prompts,
+            shell_assets_dir: Config::shell_assets_dir(),
+            args: shell.args.clone(),
+            env,
+            login_shell: shell.login_shell,
+            wakeup: Some(crt_core::Wakeup::new({
+                let proxy = self.event_loop_proxy.clone();
+                move || {
+                    // Fails only once the event loop has exited
+                    let _ = proxy.send_event(());
+                }
+            })),
+        }
+    }
+
     /// Open a new tab in the focused window, spawning a shell in the active
     /// tab's working directory and selecting the new tab. Shared by the
     /// keyboard shortcut, the macOS menu, and the tab bar "+" button.
     pub(crate) fn open_new_tab(&mut self) {
-        let shell_program = self.config.shell.program.clone();
-        let semantic_prompts = self.config.shell.semantic_prompts;
-        let shell_assets_dir = Config::shell_assets_dir();
         let new_tab_id = self.next_tab_id();
+        let focused_cwd = self
+            .focused_window
+            .and_then(|id| self.windows.get(&id))
+            .and_then(|state| state.active_shell_cwd());
+        let spawn_options = self.shell_spawn_options(focused_cwd);
 
         if let Some(state) = self.focused_window_mut() {
-            let cwd = state.active_shell_cwd();
             let tab_num = state.gpu.tab_bar.tab_count() + 1;
             state
                 .gpu
@@ -566,18 +601,49 @@ impl App {
                 .gpu
                 .tab_bar
                 .select_tab_index(state.gpu.tab_bar.tab_count() - 1);
-            let spawn_options = crt_core::SpawnOptions {
-                shell: shell_program,
-                cwd,
-                semantic_prompts,
-                shell_assets_dir,
-            };
             state.create_shell_for_tab(new_tab_id, spawn_options);
             state.render.dirty = true;
             state.window.request_redraw();
         }
     }
 
+    /// Quit, unless a program is running in some tab; then ask first in the
+    /// focused window.
+    pub(crate) fn request_quit(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let busy = self
+            .windows
+            .values()
+            .find_map(|state| state.any_busy_process());
+        match (busy, self.focused_window_mut()) {
+            (Some(name), Some(state)) => {
+                state
+                    .ui
+                    .confirm
+                    .show(format!("{name} is running. Quit CRT?"), ConfirmAction::Quit);
+                state.render.dirty = true;
+                state.window.request_redraw();
+            }
+            _ => event_loop.exit(),
+        }
+    }
+
+    /// Restart the exited shell of a held tab in the directory it last
+    /// reported, keeping the tab and its title.
+    pub(crate) fn respawn_shell(&mut self, window_id: WindowId, tab_id: TabId) {
+        let cwd = self
+            .windows
+            .get(&window_id)
+            .and_then(|state| state.shell_cwd(tab_id));
+        let spawn_options = self.shell_spawn_options(cwd);
+
+        if let Some(state) = self.windows.get_mut(&window_id) {
+            log::info!("Respawning shell for tab {}", tab_id);
+            state.create_shell_for_tab(tab_id, spawn_options);
+            state.force_active_tab_redraw();
+            state.window.request_redraw();
+        }
+    }
+
     /// Open the user's config file in the system default editor, creating a
     /// starter file if none exists. Surfaces failures as a toast.
     pub(crate) fn open_config_file(&mut self) {
@@ -0,0 +1,166 @@
+//! Event-driven frame scheduling.
+//!
+//! The event loop sleeps until a window needs a frame instead of polling.
+//! Input arrives as window events and PTY output as a wakeup from the
+//! shell's parser thread; everything else that changes over time is recorded
+//! by `render_frame` as either "animating" (backdrop effects, fades) or a
+//! deadline (cursor blink, toast fade, override expiry). Content changes are
+//! held while the program is in a synchronized update (DEC mode 2026) so a
+//! half-drawn screen is never presented. Frames are capped at
+//! the configured rate: `max_fps` for the focused window, `unfocused_fps`
+//! for the rest. Shells are also polled on a schedule of their own, between
+//! frames: for their foreground process, which titles tabs, and for the
+//! exit of a shell whose PTY has closed.
+//!
+//! The cap matters beyond saving power. wgpu/Metal on macOS grows
+//! IOAccelerator drawable allocations without bound when frames are rendered
+//! at high rates; uncapped, memory went from ~130MB to 4-9GB within minutes
+//! (see https://github.com/gfx-rs/wgpu/issues/3292). Re-evaluate the default
+//! cap if wgpu's Metal backend or macOS fixes drawable allocation.
+
+use std::time::{Duration, Instant};
+
+use crate::window::{RenderState, WindowState};
+
+/// How often to check for a shell's exit once its PTY has closed
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often tabs are retitled after their foreground process
+const PROCESS_TITLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time between frames at `fps` (at least 1)
+pub(crate) fn frame_interval(fps: u32) -> Duration {
+    Duration::from_secs(1) / fps.max(1)
+}
+
+/// When a window should draw its next frame, or `None` if nothing will
+/// change until the next event
+pub(crate) fn next_frame_at(render: &RenderState, interval: Duration) -> Option<Instant> {
+    let earliest = render.last_frame.map(|last| last + interval);
+    let content_due = render.dirty && render.sync_update_until.is_none();
+    if content_due || render.animating {
+        return Some(earliest.unwrap_or_else(Instant::now));
+    }
+    let wake_at = match (render.wake_at, render.sync_update_until) {
+        (Some(wake_at), Some(sync_until)) => wake_at.min(sync_until),
+        (wake_at, sync_until) => wake_at.or(sync_until)?,
+    };
+    Some(earliest.map_or(wake_at, |earliest| earliest.max(wake_at)))
+}
+
+/// Run the shell polls that are due, returning when the next one is
+///
+/// These wake the event loop without drawing a frame; exits are seen as
+/// the shells are processed each time it wakes.
+pub(crate) fn poll_shells(state: &mut WindowState, now: Instant) -> Instant {
+    // Tabs without a program-set title show their foreground process
+    if now >= state.render.title_refresh_at {
+        state.refresh_process_titles();
+        state.render.title_refresh_at = now + PROCESS_TITLE_INTERVAL;
+    }
+    let mut next = state.render.title_refresh_at;
+    if state.shells.values().any(|shell| shell.awaiting_exit()) {
+        next = next.min(now + EXIT_POLL_INTERVAL);
+    }
+    next
+}
+
+/// Fold shell activity and idle time into a window's render state before
+/// it's scheduled
+pub(crate) fn update_activity(state: &mut WindowState, now: Instant, pause: Option<Duration>) {
+    let active_tab = state.gpu.tab_bar.active_tab_id();
+    let shell = active_tab.and_then(|tab_id| state.shells.get(&tab_id));
+    state.render.sync_update_until = shell.and_then(|shell| shell.sync_update_deadline());
+    if shell.is_some_and(|shell| shell.has_pending_output()) {
+        state.render.dirty = true;
+        state.render.last_activity = now;
+    }
+
+    let paused = pause.is_some_and(|pause| now.duration_since(state.render.last_activity) >= pause);
+    if state.render.animation_paused && !paused {
+        // Nothing else may be due; restart the backdrop now
+        state.render.dirty = true;
+    }
+    state.render.animation_paused = paused;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_render() -> RenderState {
+        RenderState {
+            dirty: false,
+            ..RenderState::default()
+        }
+    }
+
+    #[test]
+    fn frame_interval_caps_rate() {
+        assert_eq!(frame_interval(10), Duration::from_millis(100));
+        assert_eq!(frame_interval(0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn idle_window_sleeps() {
+        let mut render = idle_render();
+        render.last_frame = Some(Instant::now());
+        assert_eq!(next_frame_at(&render, frame_interval(60)), None);
+    }
+
+    #[test]
+    fn dirty_window_waits_for_frame_interval() {
+        let last = Instant::now();
+        let mut render = idle_render();
+        render.last_frame = Some(last);
+        render.dirty = true;
+        let interval = frame_interval(60);
+        assert_eq!(next_frame_at(&render, interval), Some(last + interval));
+
+        render.dirty = false;
+        render.animating = true;
+        assert_eq!(next_frame_at(&render, interval), Some(last + interval));
+    }
+
+    #[test]
+    fn first_frame_is_immediate() {
+        let before = Instant::now();
+        let render = RenderState::default();
+        let at = next_frame_at(&render, frame_interval(60)).unwrap();
+        assert!(at >= before && at <= Instant::now());
+    }
+
+    #[test]
+    fn sync_update_holds_content_until_it_ends() {
+        let last = Instant::now();
+        let mut render = idle_render();
+        render.last_frame = Some(last);
+        render.dirty = true;
+        let deadline = last + Duration::from_millis(150);
+        render.sync_update_until = Some(deadline);
+        let interval = frame_interval(60);
+        assert_eq!(next_frame_at(&render, interval), Some(deadline));
+
+        // Animation carries on regardless
+        render.animating = true;
+        assert_eq!(next_frame_at(&render, interval), Some(last + interval));
+    }
+
+    #[test]
+    fn wake_deadline_respects_frame_cap() {
+        let last = Instant::now();
+        let mut render = idle_render();
+        render.last_frame = Some(last);
+        let interval = frame_interval(10);
+
+        render.schedule_wake(last + Duration::from_millis(530));
+        render.schedule_wake(last + Duration::from_secs(4));
+        assert_eq!(
+            next_frame_at(&render, interval),
+            Some(last + Duration::from_millis(530))
+        );
+
+        // A deadline sooner than the cap allows waits for the cap
+        render.schedule_wake(last + Duration::from_millis(1));
+        assert_eq!(next_frame_at(&render, interval), Some(last + interval));
+    }
+}
@@ -6,6 +6,7 @@
 //! - `ConfigPaths` for programmatic control (useful for testing)
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Starter config written when a user opens Settings without an existing file.
@@ -27,11 +28,18 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r#"# CRT configuration
 [window]
 # columns = 80
 # rows = 24
+# max_fps = 60
+# unfocused_fps = 10
+# pause_animation_after_secs = 0   # 0 = backdrop effects never pause
 
 [cursor]
 # style = "block"   # block | bar | underline
 # blink = true
 
+[clipboard]
+# osc52 = "write-only"   # write-only | read-write | prompt | deny
+# max_osc52_bytes = 1048576   # largest OSC 52 payload, in decoded bytes
+
 # Command used to open a Cmd/Ctrl-clicked file path. Leave unset to use the OS
 # default app. Placeholders: {file}, {line}, {col}.
 # open_file_command = "code -g {file}:{line}:{col}"
@@ -91,10 +99,33 @@ impl ConfigPaths {
     }
 }
 
+/// What happens to a tab when its shell exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitBehavior {
+    /// Close the tab (the window, if it was the last tab)
+    Close,
+    /// Keep the tab open with an exit banner; Enter restarts the shell
+    Hold,
+    /// Close after a zero exit code, hold after anything else
+    #[default]
+    HoldOnError,
+}
+
+impl ExitBehavior {
+    /// Whether a shell that exited with `code` keeps its tab open
+    pub fn holds(self, code: i32) -> bool {
+        match self {
+            Self::Close => false,
+            Self::Hold => true,
+            Self::HoldOnError => code != 0,
+        }
+    }
+}
+
 /// Shell configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[derive(Default)]
 pub struct ShellConfig {
     /// Shell program to run (default: user's login shell or /bin/zsh)
     pub program: Option<String>,
@@ -103,9 +134,35 @@ pub struct ShellConfig {
     /// Working directory (default: user's home)
     pub working_directory: Option<PathBuf>,
     /// Enable semantic prompt markers (OSC 133) for command success/fail detection
-    /// When enabled, CRT injects shell hooks for bash/zsh to emit OSC 133 sequences.
+    /// When enabled, CRT injects shell hooks for bash/zsh/fish to emit OSC 133 sequences.
     /// Not needed if using starship, oh-my-zsh, or other tools with OSC 133 support.
     pub semantic_prompts: bool,
+    /// Start the shell as a login shell (`-l`)
+    pub login_shell: bool,
+    /// Extra environment variables for the shell (`[shell.env]`)
+    pub env: BTreeMap<String, String>,
+    /// What happens to the tab when the shell exits
+    pub on_exit: ExitBehavior,
+    /// Programs that may be closed without confirmation; any other program
+    /// running in a tab prompts before closing the tab, window, or app
+    pub confirm_close_allowlist: Vec<String>,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            program: None,
+            args: Vec::new(),
+            working_directory: None,
+            semantic_prompts: false,
+            login_shell: true,
+            env: BTreeMap::new(),
+            on_exit: ExitBehavior::default(),
+            confirm_close_allowlist: ["bash", "zsh", "fish", "sh", "tmux", "screen"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
 }
 
 /// Font configuration
@@ -149,6 +206,13 @@ pub struct WindowConfig {
     pub title: String,
     /// Start in fullscreen mode
     pub fullscreen: bool,
+    /// Frame rate cap for the focused window
+    pub max_fps: u32,
+    /// Frame rate cap for windows without focus
+    pub unfocused_fps: u32,
+    /// Stop animating backdrop effects after this many seconds without
+    /// input or output (0 = keep animating)
+    pub pause_animation_after_secs: u64,
 }
 
 impl Default for WindowConfig {
@@ -158,10 +222,21 @@ impl Default for WindowConfig {
             rows: 24,
             title: "CRT Terminal".to_string(),
             fullscreen: false,
+            max_fps: 60,
+            unfocused_fps: 10,
+            pause_animation_after_secs: 0,
         }
     }
 }
 
+impl WindowConfig {
+    /// Backdrop idle time before animation pauses, if it ever does
+    pub fn animation_pause(&self) -> Option<std::time::Duration> {
+        (self.pause_animation_after_secs > 0)
+            .then(|| std::time::Duration::from_secs(self.pause_animation_after_secs))
+    }
+}
+
 /// Theme configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -236,6 +311,47 @@ impl Default for BellConfig {
     }
 }
 
+/// Policy for programs accessing the clipboard through OSC 52
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Osc52Policy {
+    /// Programs may write the clipboard but never read it
+    #[default]
+    WriteOnly,
+    /// Programs may write and read the clipboard
+    ReadWrite,
+    /// Writes are allowed; each read asks for confirmation first
+    Prompt,
+    /// OSC 52 is ignored entirely
+    Deny,
+}
+
+impl Osc52Policy {
+    /// Whether programs may write the clipboard
+    pub fn allows_write(self) -> bool {
+        !matches!(self, Self::Deny)
+    }
+}
+
+/// Clipboard configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// OSC 52 access policy for programs running in the terminal
+    pub osc52: Osc52Policy,
+    /// Largest OSC 52 payload (decoded bytes) accepted in either direction
+    pub max_osc52_bytes: usize,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            osc52: Osc52Policy::default(),
+            max_osc52_bytes: 1024 * 1024,
+        }
+    }
+}
+
 /// Keybinding action
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -261,6 +377,11 @@ pub enum KeyAction {
     Paste,
     Quit,
     OpenConfig,
+    PrevPrompt,
+    NextPrompt,
+    SelectCommandOutput,
+    CopyCommandOutput,
+    ToggleCommandFold,
 }
 
 impl KeyAction {
@@ -409,6 +530,22 @@ impl Default for KeybindingsConfig {
                     mods: vec!["super".to_string()],
                     action: KeyAction::OpenConfig,
                 },
+                // Shell integration (OSC 133)
+                Keybinding {
+                    key: "up".to_string(),
+                    mods: vec!["super".to_string(), "shift".to_string()],
+                    action: KeyAction::PrevPrompt,
+                },
+                Keybinding {
+                    key: "down".to_string(),
+                    mods: vec!["super".to_string(), "shift".to_string()],
+                    action: KeyAction::NextPrompt,
+                },
+                Keybinding {
+                    key: "e".to_string(),
+                    mods: vec!["super".to_string(), "shift".to_string()],
+                    action: KeyAction::ToggleCommandFold,
+                },
             ],
         }
     }
@@ -424,6 +561,7 @@ pub struct Config {
     pub theme: ThemeConfig,
     pub cursor: CursorConfig,
     pub bell: BellConfig,
+    pub clipboard: ClipboardConfig,
     pub keybindings: KeybindingsConfig,
     /// Command used to open a Cmd/Ctrl-clicked file path. When `None`, the OS
     /// default application is used (`open`/`xdg-open`). When set, the string is
@@ -806,6 +944,49 @@ columns = 123
         assert!((config.flash_intensity - 0.5).abs() < 0.001);
     }
 
+    // ========== Clipboard Config Tests ==========
+
+    #[test]
+    fn test_clipboard_config_default() {
+        let clipboard = ClipboardConfig::default();
+        assert_eq!(clipboard.osc52, Osc52Policy::WriteOnly);
+        assert_eq!(clipboard.max_osc52_bytes, 1024 * 1024);
+    }
+
+    #[test]
+    fn test_clipboard_config_serde() {
+        let config: ClipboardConfig = toml::from_str(
+            r#"
+            osc52 = "read-write"
+            max_osc52_bytes = 4096
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.osc52, Osc52Policy::ReadWrite);
+        assert_eq!(config.max_osc52_bytes, 4096);
+    }
+
+    #[test]
+    fn test_osc52_policy_all_variants() {
+        for (name, expected) in [
+            ("write-only", Osc52Policy::WriteOnly),
+            ("read-write", Osc52Policy::ReadWrite),
+            ("prompt", Osc52Policy::Prompt),
+            ("deny", Osc52Policy::Deny),
+        ] {
+            let config: ClipboardConfig = toml::from_str(&format!("osc52 = \"{name}\"")).unwrap();
+            assert_eq!(config.osc52, expected);
+        }
+        assert!(!Osc52Policy::Deny.allows_write());
+        assert!(Osc52Policy::Prompt.allows_write());
+    }
+
+    #[test]
+    fn test_osc52_policy_invalid_rejected() {
+        let result: Result<ClipboardConfig, _> = toml::from_str(r#"osc52 = "always""#);
+        assert!(result.is_err());
+    }
+
     // ========== Font Config Tests ==========
 
     #[test]
@@ -872,6 +1053,9 @@ columns = 123
         assert_eq!(window.rows, 24);
         assert_eq!(window.title, "CRT Terminal");
         assert!(!window.fullscreen);
+        assert_eq!(window.max_fps, 60);
+        assert_eq!(window.unfocused_fps, 10);
+        assert_eq!(window.animation_pause(), None);
     }
 
     #[test]
@@ -891,6 +1075,24 @@ columns = 123
         assert!(config.fullscreen);
     }
 
+    #[test]
+    fn test_window_config_frame_pacing() {
+        let config: WindowConfig = toml::from_str(
+            r#"
+            max_fps = 120
+            unfocused_fps = 2
+            pause_animation_after_secs = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.max_fps, 120);
+        assert_eq!(config.unfocused_fps, 2);
+        assert_eq!(
+            config.animation_pause(),
+            Some(std::time::Duration::from_secs(30))
+        );
+    }
+
     // ========== Theme Config Tests ==========
 
     #[test]
@@ -942,6 +1144,16 @@ columns = 123
         )
         .unwrap();
         assert_eq!(binding.action, KeyAction::IncreaseFontSize);
+
+        let binding: Keybinding = toml::from_str(
+            r#"
+            key = "o"
+            mods = ["super", "shift"]
+            action = "copy_command_output"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(binding.action, KeyAction::CopyCommandOutput);
     }
 
     #[test]
@@ -1236,6 +1448,65 @@ action = "quit"
         assert!(config.semantic_prompts);
     }
 
+    #[test]
+    fn test_shell_config_login_shell_default_true() {
+        assert!(ShellConfig::default().login_shell);
+        let config: ShellConfig = toml::from_str("login_shell = false").unwrap();
+        assert!(!config.login_shell);
+    }
+
+    #[test]
+    fn test_shell_config_env_table() {
+        let config: Config = toml::from_str(
+            r#"
+            [shell.env]
+            EDITOR = "nvim"
+            LANG = "en_US.UTF-8"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.shell.env["EDITOR"], "nvim");
+        assert_eq!(config.shell.env.len(), 2);
+    }
+
+    #[test]
+    fn test_shell_on_exit() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.shell.on_exit, ExitBehavior::HoldOnError);
+
+        let config: Config = toml::from_str(
+            r#"
+            [shell]
+            on_exit = "hold"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.shell.on_exit, ExitBehavior::Hold);
+    }
+
+    #[test]
+    fn test_shell_confirm_close_allowlist() {
+        let allowlist = Config::default().shell.confirm_close_allowlist;
+        assert!(allowlist.iter().any(|name| name == "tmux"));
+
+        let config: Config = toml::from_str(
+            r#"
+            [shell]
+            confirm_close_allowlist = ["htop"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.shell.confirm_close_allowlist, ["htop"]);
+    }
+
+    #[test]
+    fn test_exit_behavior_holds() {
+        assert!(!ExitBehavior::Close.holds(1));
+        assert!(ExitBehavior::Hold.holds(0));
+        assert!(!ExitBehavior::HoldOnError.holds(0));
+        assert!(ExitBehavior::HoldOnError.holds(130));
+    }
+
     #[test]
     fn test_all_key_actions_deserialize() {
         let actions = [
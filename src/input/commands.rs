@@ -0,0 +1,343 @@
+//! Prompt navigation and command output (OSC 133)
+//!
+//! Shells with integration enabled mark their prompts and each command's
+//! output, and crt-core keeps a record of every command. This module jumps
+//! the viewport between prompts, selects or copies a command's output, and
+//! folds output away behind a one-line summary.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crt_core::{Column, CommandRecord, Line, Point, Scroll, SelectionType};
+
+use crate::window::WindowState;
+
+use super::{refresh_search_matches, screen_to_cell, set_clipboard_content};
+
+/// Scroll delta that brings the previous (or next) prompt to the top of the
+/// viewport, or `None` when there is no prompt that way.
+///
+/// `prompts` are grid lines, oldest first. Pure function.
+pub fn prompt_scroll_delta(prompts: &[i32], display_offset: usize, previous: bool) -> Option<i32> {
+    let top = -(display_offset as i32);
+    let prompt = if previous {
+        prompts.iter().rev().find(|&&line| line < top)
+    } else {
+        prompts.iter().find(|&&line| line > top)
+    }?;
+    // Prompts on the screen's last page are all reached at the bottom
+    let offset = (-prompt).max(0);
+    Some(offset - display_offset as i32).filter(|&delta| delta != 0)
+}
+
+/// The command whose prompt, input or output is on a grid line
+///
+/// Pure function.
+pub fn command_at(commands: &[CommandRecord], line: i32) -> Option<&CommandRecord> {
+    commands
+        .iter()
+        .rev()
+        .find(|command| command.prompt_line <= line)
+        .filter(|command| line < command.output.end.max(command.output.start))
+}
+
+/// Summary shown in place of a folded command's output, e.g.
+/// `▸ cargo test · exit 101 · 3.2s · 4213 lines`
+///
+/// Pure function.
+pub fn fold_summary(command: &CommandRecord) -> String {
+    let mut parts = vec![format!("▸ {}", command.command)];
+    if let Some(code) = command.exit_code {
+        parts.push(format!("exit {code}"));
+    }
+    parts.push(format_duration(command.duration()));
+    parts.push(match command.output.len() {
+        1 => "1 line".to_string(),
+        lines => format!("{lines} lines"),
+    });
+    parts.join(" · ")
+}
+
+/// Duration to the precision worth reading: `850ms`, `3.2s`, `4m 05s`, `2h 10m`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.as_millis()),
+        1..60 => format!("{:.1}s", duration.as_secs_f64()),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Grid line under a point in the window, if it's over the terminal
+fn grid_line_at(state: &WindowState, x: f32, y: f32) -> Option<i32> {
+    let (_, line) = screen_to_cell(state, x, y)?;
+    let tab_id = state.gpu.tab_bar.active_tab_id()?;
+    let shell = state.shells.get(&tab_id)?;
+    Some(line as i32 - shell.display_offset() as i32)
+}
+
+/// Id of the command whose output is at a point in the window, if any
+pub(super) fn command_output_at(state: &WindowState, x: f32, y: f32) -> Option<u64> {
+    let line = grid_line_at(state, x, y)?;
+    let tab_id = state.gpu.tab_bar.active_tab_id()?;
+    let commands = state.shells.get(&tab_id)?.commands();
+    command_at(&commands, line)
+        .filter(|command| command.output.contains(&line))
+        .map(|command| command.id)
+}
+
+/// Scroll the active terminal to the previous or next prompt
+pub(super) fn scroll_to_prompt(state: &mut WindowState, previous: bool) {
+    let Some(tab_id) = state.gpu.tab_bar.active_tab_id() else {
+        return;
+    };
+    let Some(shell) = state.shells.get_mut(&tab_id) else {
+        return;
+    };
+
+    let delta = {
+        let terminal = shell.terminal();
+        prompt_scroll_delta(
+            &terminal.prompt_lines(),
+            terminal.display_offset(),
+            previous,
+        )
+    };
+    if let Some(delta) = delta {
+        shell.scroll(Scroll::Delta(delta));
+        state.render.dirty = true;
+        state.content_hashes.insert(tab_id, 0);
+        state.window.request_redraw();
+    }
+}
+
+/// Select the output of the command under the mouse, or of the last command
+pub(super) fn select_command_output(state: &mut WindowState) {
+    let (x, y) = state.interaction.cursor_position;
+    let line = grid_line_at(state, x, y);
+    let Some(tab_id) = state.gpu.tab_bar.active_tab_id() else {
+        return;
+    };
+    let Some(shell) = state.shells.get_mut(&tab_id) else {
+        return;
+    };
+
+    let commands = shell.commands();
+    let Some(command) = line
+        .and_then(|line| command_at(&commands, line))
+        .or(commands.last())
+    else {
+        return;
+    };
+    let output = command.output.clone();
+    if output.is_empty() {
+        return;
+    }
+
+    let last_column = shell.terminal().columns() - 1;
+    shell.start_selection(
+        Point::new(Line(output.start), Column(0)),
+        SelectionType::Lines,
+    );
+    shell.update_selection(Point::new(Line(output.end - 1), Column(last_column)));
+    state.render.dirty = true;
+    state.window.request_redraw();
+}
+
+/// Fold or unfold the command under the mouse, or the last one to finish
+pub(super) fn toggle_command_fold(state: &mut WindowState) {
+    let (x, y) = state.interaction.cursor_position;
+    let line = grid_line_at(state, x, y);
+    let Some(shell) = state
+        .gpu
+        .tab_bar
+        .active_tab_id()
+        .and_then(|tab_id| state.shells.get(&tab_id))
+    else {
+        return;
+    };
+
+    let commands = shell.commands();
+    let command = line
+        .and_then(|line| command_at(&commands, line))
+        .or_else(|| commands.iter().rev().find(|command| !command.is_running()));
+    if let Some(command) = command.cloned() {
+        toggle_fold(state, &command);
+    }
+}
+
+/// Fold or unfold the command beside a click in the gutter on a viewport
+/// line, returning whether there was one to toggle
+pub(super) fn toggle_command_fold_at(state: &mut WindowState, viewport_line: usize) -> bool {
+    let Some(shell) = state
+        .gpu
+        .tab_bar
+        .active_tab_id()
+        .and_then(|tab_id| state.shells.get(&tab_id))
+    else {
+        return false;
+    };
+
+    let line = viewport_line as i32 - shell.display_offset() as i32;
+    let commands = shell.commands();
+    match command_at(&commands, line).cloned() {
+        Some(command) => toggle_fold(state, &command),
+        None => false,
+    }
+}
+
+/// Fold a command's output, or unfold it if it's folded
+///
+/// Folding moves rows without damaging them, so the whole view is redrawn,
+/// and search matches are found again on the rows now shown.
+fn toggle_fold(state: &mut WindowState, command: &CommandRecord) -> bool {
+    let Some(tab_id) = state.gpu.tab_bar.active_tab_id() else {
+        return false;
+    };
+    let Some(shell) = state.shells.get_mut(&tab_id) else {
+        return false;
+    };
+
+    let toggled = {
+        let mut terminal = shell.terminal_mut();
+        if command.folded {
+            terminal.unfold_command(command.prompt_line)
+        } else {
+            terminal.fold_command(command.prompt_line, &fold_summary(command))
+        }
+    };
+    if toggled {
+        if state.ui.search.active {
+            refresh_search_matches(state);
+        }
+        state.force_active_tab_redraw();
+        state.window.request_redraw();
+    }
+    toggled
+}
+
+/// Copy the output of the last command to clipboard
+pub(super) fn copy_last_command_output(state: &mut WindowState) {
+    let output = state
+        .gpu
+        .tab_bar
+        .active_tab_id()
+        .and_then(|tab_id| state.shells.get(&tab_id))
+        .and_then(|shell| shell.commands().pop())
+        .map(|command| command.output);
+    if let Some(output) = output {
+        copy_command_output(state, output);
+    }
+}
+
+/// Copy the output of the command with id `id` to clipboard, wherever its
+/// lines have scrolled to since it was picked
+pub(super) fn copy_output_of_command(state: &mut WindowState, id: u64) {
+    let output = state
+        .gpu
+        .tab_bar
+        .active_tab_id()
+        .and_then(|tab_id| state.shells.get(&tab_id))
+        .and_then(|shell| {
+            shell
+                .commands()
+                .into_iter()
+                .find(|command| command.id == id)
+        })
+        .map(|command| command.output);
+    if let Some(output) = output {
+        copy_command_output(state, output);
+    }
+}
+
+/// Copy the text of a command's output lines to clipboard
+fn copy_command_output(state: &mut WindowState, output: Range<i32>) {
+    let Some(tab_id) = state.gpu.tab_bar.active_tab_id() else {
+        return;
+    };
+    let Some(shell) = state.shells.get(&tab_id) else {
+        return;
+    };
+
+    let text = shell.terminal().lines_text(output);
+    if !text.is_empty() {
+        set_clipboard_content(&text);
+        state.ui.copy_indicator.trigger();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn command(prompt_line: i32, output: Range<i32>) -> CommandRecord {
+        CommandRecord {
+            id: 1,
+            prompt_line,
+            command: "cargo test".to_string(),
+            started: Instant::now(),
+            finished: Some(Instant::now()),
+            exit_code: Some(101),
+            cwd: None,
+            output,
+            folded: false,
+        }
+    }
+
+    #[test]
+    fn prompt_scroll_moves_prompt_to_top() {
+        let prompts = [-120, -40, -3, 10];
+        // At the bottom, the previous prompt is the last one above the screen
+        assert_eq!(prompt_scroll_delta(&prompts, 0, true), Some(3));
+        // From there, further back and then forward again
+        assert_eq!(prompt_scroll_delta(&prompts, 3, true), Some(37));
+        assert_eq!(prompt_scroll_delta(&prompts, 40, false), Some(-37));
+    }
+
+    #[test]
+    fn prompt_scroll_stops_at_the_ends() {
+        let prompts = [-40, 10];
+        assert_eq!(prompt_scroll_delta(&prompts, 40, true), None);
+        // The prompt on screen is shown by returning to the bottom
+        assert_eq!(prompt_scroll_delta(&prompts, 40, false), Some(-40));
+        assert_eq!(prompt_scroll_delta(&prompts, 0, false), None);
+        assert_eq!(prompt_scroll_delta(&[], 0, true), None);
+    }
+
+    #[test]
+    fn fold_summary_lists_command_status_time_and_lines() {
+        let mut record = command(-30, -29..-10);
+        record.finished = Some(record.started + Duration::from_millis(3240));
+        assert_eq!(
+            fold_summary(&record),
+            "▸ cargo test · exit 101 · 3.2s · 19 lines"
+        );
+
+        record.exit_code = None;
+        record.output = -29..-28;
+        record.finished = Some(record.started + Duration::from_secs(245));
+        assert_eq!(fold_summary(&record), "▸ cargo test · 4m 05s · 1 line");
+    }
+
+    #[test]
+    fn durations_shown_to_a_useful_precision() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_millis(12_340)), "12.3s");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59m 59s");
+        assert_eq!(format_duration(Duration::from_secs(7800)), "2h 10m");
+    }
+
+    #[test]
+    fn command_at_covers_prompt_through_output() {
+        let commands = [command(-30, -29..-10), command(-10, -9..-9)];
+        assert_eq!(command_at(&commands, -31), None);
+        assert_eq!(command_at(&commands, -30).unwrap().prompt_line, -30);
+        assert_eq!(command_at(&commands, -11).unwrap().prompt_line, -30);
+        // A command without output still owns its prompt
+        assert_eq!(command_at(&commands, -10).unwrap().prompt_line, -10);
+        // The prompt after the last command has no command yet
+        assert_eq!(command_at(&commands, -9), None);
+    }
+}
@@ -3,11 +3,19 @@
 //! Converts winit keyboard events to terminal escape sequences using termwiz's
 //! KeyCode::encode() method. This provides comprehensive key handling without
 //! maintaining manual escape sequence mappings.
+//!
+//! Programs that opt in to the kitty keyboard protocol (`CSI > flags u`) get
+//! keys encoded by `encode_kitty_key` instead, which termwiz doesn't implement.
+//! See <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>.
 
+use crt_core::TermMode;
 use termwiz::input::{
     KeyCode, KeyCodeEncodeModes, KeyboardEncoding, Modifiers as TermwizModifiers,
 };
-use winit::keyboard::{Key, NamedKey};
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{
+    Key, KeyCode as PhysicalKeyCode, KeyLocation, ModifiersState, NamedKey, PhysicalKey,
+};
 
 /// Convert a winit Key to a termwiz KeyCode
 fn winit_to_termwiz_keycode(key: &Key) -> Option<KeyCode> {
@@ -84,20 +92,22 @@ fn build_modifiers(ctrl: bool, shift: bool, alt: bool) -> TermwizModifiers {
 
 /// Encode a winit key event to terminal escape sequence bytes
 ///
+/// `mode` is the shell's terminal mode; with application cursor keys
+/// (DECCKM) set, unmodified arrows and Home/End use their SS3 forms.
 /// Returns the bytes to send to the PTY, or None if the key cannot be encoded.
 pub fn encode_key(
     key: &Key,
     ctrl_pressed: bool,
     shift_pressed: bool,
     alt_pressed: bool,
+    mode: TermMode,
 ) -> Option<Vec<u8>> {
     let keycode = winit_to_termwiz_keycode(key)?;
     let modifiers = build_modifiers(ctrl_pressed, shift_pressed, alt_pressed);
 
-    // Default encoding modes for a standard terminal
     let modes = KeyCodeEncodeModes {
         encoding: KeyboardEncoding::Xterm,
-        application_cursor_keys: false,
+        application_cursor_keys: mode.contains(TermMode::APP_CURSOR),
         newline_mode: false,
         modify_other_keys: None,
     };
@@ -118,19 +128,480 @@ pub fn encode_key(
     }
 }
 
+/// Encode a numeric keypad key in application keypad mode (DECKPAM)
+///
+/// Returns the key's SS3 sequence, or None outside application keypad mode
+/// or for keys with no keypad function, which then encode as usual.
+pub fn encode_keypad_key(key: &Key, mode: TermMode) -> Option<Vec<u8>> {
+    if !mode.contains(TermMode::APP_KEYPAD) {
+        return None;
+    }
+    let c = match key {
+        Key::Named(NamedKey::Enter) => 'M',
+        Key::Character(chars) => match chars.chars().next()? {
+            // 0-9 map to p-y
+            digit @ '0'..='9' => char::from(b'p' + (digit as u8 - b'0')),
+            '*' => 'j',
+            '+' => 'k',
+            ',' => 'l',
+            '-' => 'm',
+            '.' => 'n',
+            '/' => 'o',
+            '=' => 'X',
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(format!("\x1bO{c}").into_bytes())
+}
+
+/// Whether a key event is a press, an auto-repeat, or a release
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEventType {
+    Press,
+    Repeat,
+    Release,
+}
+
+/// A key event with the details the kitty keyboard protocol reports
+#[derive(Debug, Clone, Copy)]
+pub struct KittyKeyEvent<'a> {
+    /// Key with modifiers applied (`A` for Shift+a)
+    pub key: &'a Key,
+    /// Key with no modifiers applied (`a` for Shift+a)
+    pub unmodified_key: &'a Key,
+    /// Key position, for the US layout alternate key
+    pub physical_key: PhysicalKey,
+    /// Distinguishes keypad keys from their main keyboard twins
+    pub location: KeyLocation,
+    /// Text the key produces, if any
+    pub text: Option<&'a str>,
+    pub event_type: KeyEventType,
+}
+
+impl<'a> KittyKeyEvent<'a> {
+    /// Describe a winit key event; `unmodified_key` is its
+    /// `key_without_modifiers()`
+    pub fn from_winit(event: &'a KeyEvent, unmodified_key: &'a Key) -> Self {
+        let event_type = match (event.state, event.repeat) {
+            (ElementState::Released, _) => KeyEventType::Release,
+            (ElementState::Pressed, true) => KeyEventType::Repeat,
+            (ElementState::Pressed, false) => KeyEventType::Press,
+        };
+        Self {
+            key: &event.logical_key,
+            unmodified_key,
+            physical_key: event.physical_key,
+            location: event.location,
+            text: event.text.as_deref(),
+            event_type,
+        }
+    }
+}
+
+/// How a key is numbered in the kitty protocol: `CSI number ; mods final`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KittyCode {
+    number: u32,
+    terminator: char,
+}
+
+impl KittyCode {
+    const fn u(number: u32) -> Self {
+        Self {
+            number,
+            terminator: 'u',
+        }
+    }
+
+    const fn tilde(number: u32) -> Self {
+        Self {
+            number,
+            terminator: '~',
+        }
+    }
+
+    /// Legacy `CSI 1 ; mods letter` keys (arrows, Home/End, F1/F2/F4)
+    const fn letter(terminator: char) -> Self {
+        Self {
+            number: 1,
+            terminator,
+        }
+    }
+}
+
+// Kitty's private-use key numbers
+const KP_0: u32 = 57399;
+const KP_DECIMAL: u32 = 57409;
+const KP_DIVIDE: u32 = 57410;
+const KP_MULTIPLY: u32 = 57411;
+const KP_SUBTRACT: u32 = 57412;
+const KP_ADD: u32 = 57413;
+const KP_ENTER: u32 = 57414;
+const KP_EQUAL: u32 = 57415;
+const KP_SEPARATOR: u32 = 57416;
+const F13: u32 = 57376;
+const LEFT_SHIFT: u32 = 57441;
+const RIGHT_SHIFT: u32 = 57447;
+/// Right-hand modifier numbers are this far past the left-hand ones
+const RIGHT_MODIFIER_OFFSET: u32 = RIGHT_SHIFT - LEFT_SHIFT;
+
+// Modifier bits; the encoded value is one more than their sum
+const MOD_SHIFT: u8 = 1;
+const MOD_ALT: u8 = 2;
+const MOD_CTRL: u8 = 4;
+const MOD_SUPER: u8 = 8;
+
+/// Number a named (non-text) key
+fn kitty_named_code(named: NamedKey, location: KeyLocation) -> Option<KittyCode> {
+    let keypad = location == KeyLocation::Numpad;
+    let code = match named {
+        NamedKey::Enter if keypad => KittyCode::u(KP_ENTER),
+        NamedKey::ArrowLeft if keypad => KittyCode::u(57417),
+        NamedKey::ArrowRight if keypad => KittyCode::u(57418),
+        NamedKey::ArrowUp if keypad => KittyCode::u(57419),
+        NamedKey::ArrowDown if keypad => KittyCode::u(57420),
+        NamedKey::PageUp if keypad => KittyCode::u(57421),
+        NamedKey::PageDown if keypad => KittyCode::u(57422),
+        NamedKey::Home if keypad => KittyCode::u(57423),
+        NamedKey::End if keypad => KittyCode::u(57424),
+        NamedKey::Insert if keypad => KittyCode::u(57425),
+        NamedKey::Delete if keypad => KittyCode::u(57426),
+        NamedKey::Clear if keypad => KittyCode::u(57427),
+
+        NamedKey::Escape => KittyCode::u(27),
+        NamedKey::Enter => KittyCode::u(13),
+        NamedKey::Tab => KittyCode::u(9),
+        NamedKey::Backspace => KittyCode::u(127),
+        NamedKey::Space => KittyCode::u(32),
+        NamedKey::Insert => KittyCode::tilde(2),
+        NamedKey::Delete => KittyCode::tilde(3),
+        NamedKey::PageUp => KittyCode::tilde(5),
+        NamedKey::PageDown => KittyCode::tilde(6),
+        NamedKey::ArrowUp => KittyCode::letter('A'),
+        NamedKey::ArrowDown => KittyCode::letter('B'),
+        NamedKey::ArrowRight => KittyCode::letter('C'),
+        NamedKey::ArrowLeft => KittyCode::letter('D'),
+        NamedKey::Home => KittyCode::letter('H'),
+        NamedKey::End => KittyCode::letter('F'),
+
+        NamedKey::F1 => KittyCode::letter('P'),
+        NamedKey::F2 => KittyCode::letter('Q'),
+        // Not `CSI R`, which reads as a cursor position report
+        NamedKey::F3 => KittyCode::tilde(13),
+        NamedKey::F4 => KittyCode::letter('S'),
+        NamedKey::F5 => KittyCode::tilde(15),
+        NamedKey::F6 => KittyCode::tilde(17),
+        NamedKey::F7 => KittyCode::tilde(18),
+        NamedKey::F8 => KittyCode::tilde(19),
+        NamedKey::F9 => KittyCode::tilde(20),
+        NamedKey::F10 => KittyCode::tilde(21),
+        NamedKey::F11 => KittyCode::tilde(23),
+        NamedKey::F12 => KittyCode::tilde(24),
+        NamedKey::F13 => KittyCode::u(F13),
+        NamedKey::F14 => KittyCode::u(F13 + 1),
+        NamedKey::F15 => KittyCode::u(F13 + 2),
+        NamedKey::F16 => KittyCode::u(F13 + 3),
+        NamedKey::F17 => KittyCode::u(F13 + 4),
+        NamedKey::F18 => KittyCode::u(F13 + 5),
+        NamedKey::F19 => KittyCode::u(F13 + 6),
+        NamedKey::F20 => KittyCode::u(F13 + 7),
+        NamedKey::F21 => KittyCode::u(F13 + 8),
+        NamedKey::F22 => KittyCode::u(F13 + 9),
+        NamedKey::F23 => KittyCode::u(F13 + 10),
+        NamedKey::F24 => KittyCode::u(F13 + 11),
+        NamedKey::F25 => KittyCode::u(F13 + 12),
+
+        NamedKey::CapsLock => KittyCode::u(57358),
+        NamedKey::ScrollLock => KittyCode::u(57359),
+        NamedKey::NumLock => KittyCode::u(57360),
+        NamedKey::PrintScreen => KittyCode::u(57361),
+        NamedKey::Pause => KittyCode::u(57362),
+        NamedKey::ContextMenu => KittyCode::u(57363),
+
+        NamedKey::MediaPlay => KittyCode::u(57428),
+        NamedKey::MediaPause => KittyCode::u(57429),
+        NamedKey::MediaPlayPause => KittyCode::u(57430),
+        NamedKey::MediaStop => KittyCode::u(57432),
+        NamedKey::MediaFastForward => KittyCode::u(57433),
+        NamedKey::MediaRewind => KittyCode::u(57434),
+        NamedKey::MediaTrackNext => KittyCode::u(57435),
+        NamedKey::MediaTrackPrevious => KittyCode::u(57436),
+        NamedKey::MediaRecord => KittyCode::u(57437),
+        NamedKey::AudioVolumeDown => KittyCode::u(57438),
+        NamedKey::AudioVolumeUp => KittyCode::u(57439),
+        NamedKey::AudioVolumeMute => KittyCode::u(57440),
+
+        NamedKey::Shift => kitty_modifier_code(0, location),
+        NamedKey::Control => kitty_modifier_code(1, location),
+        NamedKey::Alt => kitty_modifier_code(2, location),
+        NamedKey::Super => kitty_modifier_code(3, location),
+        NamedKey::Hyper => kitty_modifier_code(4, location),
+        NamedKey::Meta => kitty_modifier_code(5, location),
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Number a modifier key, `index` counting from Shift in kitty's order
+fn kitty_modifier_code(index: u32, location: KeyLocation) -> KittyCode {
+    let side = if location == KeyLocation::Right {
+        RIGHT_MODIFIER_OFFSET
+    } else {
+        0
+    };
+    KittyCode::u(LEFT_SHIFT + index + side)
+}
+
+/// Number a keypad key that produces a character
+fn kitty_keypad_code(c: char) -> Option<u32> {
+    let code = match c {
+        '0'..='9' => KP_0 + (c as u32 - '0' as u32),
+        '.' => KP_DECIMAL,
+        '/' => KP_DIVIDE,
+        '*' => KP_MULTIPLY,
+        '-' => KP_SUBTRACT,
+        '+' => KP_ADD,
+        '=' => KP_EQUAL,
+        ',' => KP_SEPARATOR,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// The character a key position produces on a US layout
+fn us_layout_char(code: PhysicalKeyCode) -> Option<char> {
+    use PhysicalKeyCode::*;
+    let c = match code {
+        KeyA => 'a',
+        KeyB => 'b',
+        KeyC => 'c',
+        KeyD => 'd',
+        KeyE => 'e',
+        KeyF => 'f',
+        KeyG => 'g',
+        KeyH => 'h',
+        KeyI => 'i',
+        KeyJ => 'j',
+        KeyK => 'k',
+        KeyL => 'l',
+        KeyM => 'm',
+        KeyN => 'n',
+        KeyO => 'o',
+        KeyP => 'p',
+        KeyQ => 'q',
+        KeyR => 'r',
+        KeyS => 's',
+        KeyT => 't',
+        KeyU => 'u',
+        KeyV => 'v',
+        KeyW => 'w',
+        KeyX => 'x',
+        KeyY => 'y',
+        KeyZ => 'z',
+        Digit0 => '0',
+        Digit1 => '1',
+        Digit2 => '2',
+        Digit3 => '3',
+        Digit4 => '4',
+        Digit5 => '5',
+        Digit6 => '6',
+        Digit7 => '7',
+        Digit8 => '8',
+        Digit9 => '9',
+        Minus => '-',
+        Equal => '=',
+        BracketLeft => '[',
+        BracketRight => ']',
+        Backslash => '\\',
+        Semicolon => ';',
+        Quote => '\'',
+        Backquote => '`',
+        Comma => ',',
+        Period => '.',
+        Slash => '/',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Modifier bits for an event, counting a modifier key's own press
+fn kitty_modifiers(event: &KittyKeyEvent, modifiers: ModifiersState) -> u8 {
+    let mut bits = 0;
+    for (state, bit) in [
+        (ModifiersState::SHIFT, MOD_SHIFT),
+        (ModifiersState::ALT, MOD_ALT),
+        (ModifiersState::CONTROL, MOD_CTRL),
+        (ModifiersState::SUPER, MOD_SUPER),
+    ] {
+        if modifiers.contains(state) {
+            bits |= bit;
+        }
+    }
+
+    // The modifier state may not include the key being pressed or released yet
+    let own = match event.key {
+        Key::Named(NamedKey::Shift) => MOD_SHIFT,
+        Key::Named(NamedKey::Alt) => MOD_ALT,
+        Key::Named(NamedKey::Control) => MOD_CTRL,
+        Key::Named(NamedKey::Super) => MOD_SUPER,
+        _ => 0,
+    };
+    if event.event_type == KeyEventType::Release {
+        bits & !own
+    } else {
+        bits | own
+    }
+}
+
+/// Encode a key event with the kitty keyboard protocol
+///
+/// `flags` are the enhancements the program has pushed (the kitty bits of
+/// its `TermMode`). Keys the legacy encoding already reports unambiguously
+/// (plain text, unmodified Enter/Tab/Backspace) keep their legacy bytes
+/// unless every key is to be reported as an escape code. Returns None when
+/// the event isn't reported, such as a release without `REPORT_EVENT_TYPES`.
+pub fn encode_kitty_key(
+    event: &KittyKeyEvent,
+    modifiers: ModifiersState,
+    flags: TermMode,
+) -> Option<Vec<u8>> {
+    let report_all = flags.contains(TermMode::REPORT_ALL_KEYS_AS_ESC);
+    let report_events = flags.contains(TermMode::REPORT_EVENT_TYPES);
+    let release = event.event_type == KeyEventType::Release;
+    if release && !report_events {
+        return None;
+    }
+
+    let keypad = event.location == KeyLocation::Numpad;
+    let (code, shifted) = match event.key {
+        Key::Named(named) => (kitty_named_code(*named, event.location)?, None),
+        Key::Character(chars) => {
+            let c = chars.chars().next()?;
+            match kitty_keypad_code(c).filter(|_| keypad) {
+                Some(number) => (KittyCode::u(number), None),
+                None => {
+                    let base = match event.unmodified_key {
+                        Key::Character(unmodified) => unmodified.chars().next()?,
+                        _ => c,
+                    };
+                    let base = base.to_lowercase().next().unwrap_or(base);
+                    (KittyCode::u(base as u32), Some(c as u32))
+                }
+            }
+        }
+        _ => return None,
+    };
+
+    let mods = kitty_modifiers(event, modifiers);
+    let text = event
+        .text
+        .filter(|text| !text.is_empty() && !text.chars().any(char::is_control));
+    let is_modifier_key = (LEFT_SHIFT..=RIGHT_SHIFT + 5).contains(&code.number);
+
+    if !report_all {
+        // Lone modifier presses are only reported with every other key
+        if is_modifier_key {
+            return None;
+        }
+        let legacy = match code.number {
+            13 => Some("\r"),
+            9 => Some("\t"),
+            127 => Some("\x7f"),
+            _ => None,
+        };
+        if let Some(legacy) = legacy.filter(|_| code.terminator == 'u') {
+            // These never report release, so a program that crashes with the
+            // protocol enabled still leaves a shell one can type `reset` into
+            if release {
+                return None;
+            }
+            if mods == 0 {
+                return Some(legacy.as_bytes().to_vec());
+            }
+        }
+        if let Some(text) = text
+            && !release
+            && !keypad
+            && mods & !MOD_SHIFT == 0
+        {
+            return Some(text.as_bytes().to_vec());
+        }
+    }
+
+    let mut key = code.number.to_string();
+    if flags.contains(TermMode::REPORT_ALTERNATE_KEYS) && code.terminator == 'u' {
+        let shifted = shifted.filter(|&shifted| mods & MOD_SHIFT != 0 && shifted != code.number);
+        let base = match event.physical_key {
+            PhysicalKey::Code(physical) if !is_modifier_key => us_layout_char(physical)
+                .map(|c| c as u32)
+                .filter(|&base| base != code.number),
+            _ => None,
+        };
+        if shifted.is_some() || base.is_some() {
+            key.push(':');
+            if let Some(shifted) = shifted {
+                key.push_str(&shifted.to_string());
+            }
+        }
+        if let Some(base) = base {
+            key.push(':');
+            key.push_str(&base.to_string());
+        }
+    }
+
+    let event_type = match event.event_type {
+        KeyEventType::Repeat if report_events => Some(2),
+        KeyEventType::Release => Some(3),
+        _ => None,
+    };
+    let associated_text =
+        text.filter(|_| report_all && !release && flags.contains(TermMode::REPORT_ASSOCIATED_TEXT));
+
+    let mut params = String::new();
+    if mods != 0 || event_type.is_some() || associated_text.is_some() {
+        params.push_str(&format!(";{}", mods + 1));
+        if let Some(event_type) = event_type {
+            params.push_str(&format!(":{event_type}"));
+        }
+    }
+    if let Some(text) = associated_text {
+        let codepoints: Vec<String> = text.chars().map(|c| (c as u32).to_string()).collect();
+        params.push_str(&format!(";{}", codepoints.join(":")));
+    }
+
+    // `CSI 1 A` is written `CSI A` when there's nothing after the number
+    if code.terminator != 'u' && code.terminator != '~' && params.is_empty() {
+        key.clear();
+    }
+    Some(format!("\x1b[{key}{params}{}", code.terminator).into_bytes())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_encode_tab() {
-        let result = encode_key(&Key::Named(NamedKey::Tab), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Tab),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, Some(b"\t".to_vec()));
     }
 
     #[test]
     fn test_encode_shift_tab() {
-        let result = encode_key(&Key::Named(NamedKey::Tab), false, true, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Tab),
+            false,
+            true,
+            false,
+            TermMode::NONE,
+        );
         // Shift+Tab should produce backtab escape sequence
         assert!(result.is_some());
         let bytes = result.unwrap();
@@ -139,31 +610,67 @@ mod tests {
 
     #[test]
     fn test_encode_arrow_keys() {
-        let up = encode_key(&Key::Named(NamedKey::ArrowUp), false, false, false);
+        let up = encode_key(
+            &Key::Named(NamedKey::ArrowUp),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(up, Some(b"\x1b[A".to_vec()));
 
-        let down = encode_key(&Key::Named(NamedKey::ArrowDown), false, false, false);
+        let down = encode_key(
+            &Key::Named(NamedKey::ArrowDown),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(down, Some(b"\x1b[B".to_vec()));
     }
 
     #[test]
     fn test_encode_function_keys() {
-        let f1 = encode_key(&Key::Named(NamedKey::F1), false, false, false);
+        let f1 = encode_key(
+            &Key::Named(NamedKey::F1),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(f1.is_some());
 
-        let f12 = encode_key(&Key::Named(NamedKey::F12), false, false, false);
+        let f12 = encode_key(
+            &Key::Named(NamedKey::F12),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(f12.is_some());
     }
 
     #[test]
     fn test_encode_character() {
-        let a = encode_key(&Key::Character("a".into()), false, false, false);
+        let a = encode_key(
+            &Key::Character("a".into()),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(a, Some(b"a".to_vec()));
     }
 
     #[test]
     fn test_encode_ctrl_c() {
-        let ctrl_c = encode_key(&Key::Character("c".into()), true, false, false);
+        let ctrl_c = encode_key(
+            &Key::Character("c".into()),
+            true,
+            false,
+            false,
+            TermMode::NONE,
+        );
         // Ctrl+C should produce ETX (0x03)
         assert_eq!(ctrl_c, Some(vec![0x03]));
     }
@@ -173,14 +680,26 @@ mod tests {
         // Note: Home/End are now handled explicitly in handle_shell_input with PC-style
         // sequences (\x1b[1~ and \x1b[4~) because they work more universally with shells.
         // This test verifies termwiz behavior for reference.
-        let home = encode_key(&Key::Named(NamedKey::Home), false, false, false);
+        let home = encode_key(
+            &Key::Named(NamedKey::Home),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(
             home,
             Some(b"\x1b[H".to_vec()),
             "Home key encoding (termwiz)"
         );
 
-        let end = encode_key(&Key::Named(NamedKey::End), false, false, false);
+        let end = encode_key(
+            &Key::Named(NamedKey::End),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(end, Some(b"\x1b[F".to_vec()), "End key encoding (termwiz)");
     }
 
@@ -188,25 +707,49 @@ mod tests {
 
     #[test]
     fn encode_enter_produces_cr() {
-        let result = encode_key(&Key::Named(NamedKey::Enter), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Enter),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, Some(b"\r".to_vec()));
     }
 
     #[test]
     fn encode_escape_produces_esc() {
-        let result = encode_key(&Key::Named(NamedKey::Escape), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Escape),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, Some(b"\x1b".to_vec()));
     }
 
     #[test]
     fn encode_backspace_produces_del() {
-        let result = encode_key(&Key::Named(NamedKey::Backspace), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Backspace),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, Some(vec![0x7f])); // DEL character
     }
 
     #[test]
     fn encode_delete_key() {
-        let result = encode_key(&Key::Named(NamedKey::Delete), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Delete),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(result.is_some());
         let bytes = result.unwrap();
         // Delete should produce an escape sequence (ESC [ 3 ~)
@@ -215,7 +758,13 @@ mod tests {
 
     #[test]
     fn encode_insert_key() {
-        let result = encode_key(&Key::Named(NamedKey::Insert), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Insert),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(result.is_some());
         let bytes = result.unwrap();
         assert!(bytes.starts_with(b"\x1b["));
@@ -223,18 +772,42 @@ mod tests {
 
     #[test]
     fn encode_page_up_down() {
-        let pgup = encode_key(&Key::Named(NamedKey::PageUp), false, false, false);
+        let pgup = encode_key(
+            &Key::Named(NamedKey::PageUp),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(pgup.is_some());
-        let pgdn = encode_key(&Key::Named(NamedKey::PageDown), false, false, false);
+        let pgdn = encode_key(
+            &Key::Named(NamedKey::PageDown),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert!(pgdn.is_some());
         assert_ne!(pgup, pgdn);
     }
 
     #[test]
     fn encode_left_right_arrows() {
-        let left = encode_key(&Key::Named(NamedKey::ArrowLeft), false, false, false);
+        let left = encode_key(
+            &Key::Named(NamedKey::ArrowLeft),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(left, Some(b"\x1b[D".to_vec()));
-        let right = encode_key(&Key::Named(NamedKey::ArrowRight), false, false, false);
+        let right = encode_key(
+            &Key::Named(NamedKey::ArrowRight),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(right, Some(b"\x1b[C".to_vec()));
     }
 
@@ -242,7 +815,13 @@ mod tests {
     fn encode_ctrl_a_through_z() {
         // Ctrl+A = 0x01, Ctrl+Z = 0x1A
         for (i, ch) in ('a'..='z').enumerate() {
-            let result = encode_key(&Key::Character(ch.to_string().into()), true, false, false);
+            let result = encode_key(
+                &Key::Character(ch.to_string().into()),
+                true,
+                false,
+                false,
+                TermMode::NONE,
+            );
             let expected = (i as u8) + 1;
             assert_eq!(
                 result,
@@ -263,7 +842,7 @@ mod tests {
         ];
         let results: Vec<_> = named_keys
             .iter()
-            .map(|k| encode_key(&Key::Named(*k), false, false, false))
+            .map(|k| encode_key(&Key::Named(*k), false, false, false, TermMode::NONE))
             .collect();
         // All should produce some output
         for (i, r) in results.iter().enumerate() {
@@ -279,13 +858,25 @@ mod tests {
 
     #[test]
     fn encode_space() {
-        let result = encode_key(&Key::Named(NamedKey::Space), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::Space),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, Some(b" ".to_vec()));
     }
 
     #[test]
     fn encode_alt_character() {
-        let result = encode_key(&Key::Character("a".into()), false, false, true);
+        let result = encode_key(
+            &Key::Character("a".into()),
+            false,
+            false,
+            true,
+            TermMode::NONE,
+        );
         assert!(result.is_some());
         let bytes = result.unwrap();
         // Alt+a should produce ESC + a
@@ -295,16 +886,346 @@ mod tests {
     #[test]
     fn encode_unknown_named_key_returns_none() {
         // CapsLock has no terminal encoding
-        let result = encode_key(&Key::Named(NamedKey::CapsLock), false, false, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::CapsLock),
+            false,
+            false,
+            false,
+            TermMode::NONE,
+        );
         assert_eq!(result, None);
     }
 
     #[test]
     fn encode_shift_arrows_produce_modified_sequences() {
-        let result = encode_key(&Key::Named(NamedKey::ArrowUp), false, true, false);
+        let result = encode_key(
+            &Key::Named(NamedKey::ArrowUp),
+            false,
+            true,
+            false,
+            TermMode::NONE,
+        );
         assert!(result.is_some());
         let bytes = result.unwrap();
         // Shift+Up should produce a modified sequence (different from plain Up)
         assert_ne!(bytes, b"\x1b[A".to_vec());
     }
+
+    // ── Application cursor and keypad modes ───────────────────────
+
+    #[test]
+    fn encode_application_cursor_keys() {
+        let mode = TermMode::APP_CURSOR;
+        let cases = [
+            (NamedKey::ArrowUp, "\x1bOA"),
+            (NamedKey::ArrowDown, "\x1bOB"),
+            (NamedKey::ArrowRight, "\x1bOC"),
+            (NamedKey::ArrowLeft, "\x1bOD"),
+            (NamedKey::Home, "\x1bOH"),
+            (NamedKey::End, "\x1bOF"),
+        ];
+        for (key, expected) in cases {
+            let result = encode_key(&Key::Named(key), false, false, false, mode);
+            assert_eq!(result, Some(expected.as_bytes().to_vec()), "{key:?}");
+        }
+
+        // Modified keys keep the CSI form
+        let ctrl_up = encode_key(&Key::Named(NamedKey::ArrowUp), true, false, false, mode);
+        assert_eq!(ctrl_up, Some(b"\x1b[1;5A".to_vec()));
+    }
+
+    #[test]
+    fn encode_application_keypad() {
+        let mode = TermMode::APP_KEYPAD;
+        let cases = [
+            (Key::Character("0".into()), "\x1bOp"),
+            (Key::Character("5".into()), "\x1bOu"),
+            (Key::Character("9".into()), "\x1bOy"),
+            (Key::Character("+".into()), "\x1bOk"),
+            (Key::Character("-".into()), "\x1bOm"),
+            (Key::Character("*".into()), "\x1bOj"),
+            (Key::Character("/".into()), "\x1bOo"),
+            (Key::Character(".".into()), "\x1bOn"),
+            (Key::Named(NamedKey::Enter), "\x1bOM"),
+        ];
+        for (key, expected) in cases {
+            let result = encode_keypad_key(&key, mode);
+            assert_eq!(result, Some(expected.as_bytes().to_vec()), "{key:?}");
+        }
+
+        // Numeric keypad mode sends the keys as typed
+        assert_eq!(
+            encode_keypad_key(&Key::Character("5".into()), TermMode::NONE),
+            None
+        );
+        // Arrows on the keypad follow the cursor key mode instead
+        assert_eq!(
+            encode_keypad_key(&Key::Named(NamedKey::ArrowUp), mode),
+            None
+        );
+    }
+
+    // ── Kitty keyboard protocol ───────────────────────────────────
+
+    const DISAMBIGUATE: TermMode = TermMode::DISAMBIGUATE_ESC_CODES;
+
+    fn kitty_event<'a>(
+        key: &'a Key,
+        unmodified_key: &'a Key,
+        text: Option<&'a str>,
+    ) -> KittyKeyEvent<'a> {
+        KittyKeyEvent {
+            key,
+            unmodified_key,
+            physical_key: PhysicalKey::Code(PhysicalKeyCode::KeyA),
+            location: KeyLocation::Standard,
+            text,
+            event_type: KeyEventType::Press,
+        }
+    }
+
+    fn encode_kitty(
+        key: &Key,
+        text: Option<&str>,
+        modifiers: ModifiersState,
+        flags: TermMode,
+    ) -> Option<String> {
+        let event = kitty_event(key, key, text);
+        encode_kitty_key(&event, modifiers, flags).map(|bytes| String::from_utf8(bytes).unwrap())
+    }
+
+    #[test]
+    fn kitty_plain_text_stays_text() {
+        let a = Key::Character("a".into());
+        assert_eq!(
+            encode_kitty(&a, Some("a"), ModifiersState::empty(), DISAMBIGUATE).as_deref(),
+            Some("a")
+        );
+
+        let shifted = Key::Character("A".into());
+        let unmodified = Key::Character("a".into());
+        let event = kitty_event(&shifted, &unmodified, Some("A"));
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::SHIFT, DISAMBIGUATE),
+            Some(b"A".to_vec())
+        );
+    }
+
+    #[test]
+    fn kitty_disambiguates_ctrl_and_alt() {
+        let a = Key::Character("a".into());
+        assert_eq!(
+            encode_kitty(&a, None, ModifiersState::CONTROL, DISAMBIGUATE).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        assert_eq!(
+            encode_kitty(&a, None, ModifiersState::ALT, DISAMBIGUATE).as_deref(),
+            Some("\x1b[97;3u")
+        );
+
+        // Ctrl+Shift+a reports the unshifted key
+        let shifted = Key::Character("A".into());
+        let event = kitty_event(&shifted, &a, None);
+        let mods = ModifiersState::CONTROL | ModifiersState::SHIFT;
+        assert_eq!(
+            encode_kitty_key(&event, mods, DISAMBIGUATE),
+            Some(b"\x1b[97;6u".to_vec())
+        );
+    }
+
+    #[test]
+    fn kitty_escape_enter_tab_backspace() {
+        let none = ModifiersState::empty();
+        let esc = Key::Named(NamedKey::Escape);
+        assert_eq!(
+            encode_kitty(&esc, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x1b[27u")
+        );
+
+        // Unmodified, these keep their legacy bytes so `reset` can always be typed
+        let enter = Key::Named(NamedKey::Enter);
+        assert_eq!(
+            encode_kitty(&enter, Some("\r"), none, DISAMBIGUATE).as_deref(),
+            Some("\r")
+        );
+        let tab = Key::Named(NamedKey::Tab);
+        assert_eq!(
+            encode_kitty(&tab, Some("\t"), none, DISAMBIGUATE).as_deref(),
+            Some("\t")
+        );
+        let backspace = Key::Named(NamedKey::Backspace);
+        assert_eq!(
+            encode_kitty(&backspace, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x7f")
+        );
+
+        assert_eq!(
+            encode_kitty(&enter, None, ModifiersState::CONTROL, DISAMBIGUATE).as_deref(),
+            Some("\x1b[13;5u")
+        );
+        assert_eq!(
+            encode_kitty(&tab, None, ModifiersState::CONTROL, DISAMBIGUATE).as_deref(),
+            Some("\x1b[9;5u")
+        );
+        assert_eq!(
+            encode_kitty(&tab, None, ModifiersState::SHIFT, DISAMBIGUATE).as_deref(),
+            Some("\x1b[9;2u")
+        );
+    }
+
+    #[test]
+    fn kitty_functional_keys() {
+        let none = ModifiersState::empty();
+        let up = Key::Named(NamedKey::ArrowUp);
+        assert_eq!(
+            encode_kitty(&up, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x1b[A")
+        );
+        assert_eq!(
+            encode_kitty(&up, None, ModifiersState::CONTROL, DISAMBIGUATE).as_deref(),
+            Some("\x1b[1;5A")
+        );
+
+        let f1 = Key::Named(NamedKey::F1);
+        assert_eq!(
+            encode_kitty(&f1, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x1b[P")
+        );
+        let f3 = Key::Named(NamedKey::F3);
+        assert_eq!(
+            encode_kitty(&f3, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x1b[13~")
+        );
+        let delete = Key::Named(NamedKey::Delete);
+        assert_eq!(
+            encode_kitty(&delete, None, ModifiersState::SHIFT, DISAMBIGUATE).as_deref(),
+            Some("\x1b[3;2~")
+        );
+        let f13 = Key::Named(NamedKey::F13);
+        assert_eq!(
+            encode_kitty(&f13, None, none, DISAMBIGUATE).as_deref(),
+            Some("\x1b[57376u")
+        );
+    }
+
+    #[test]
+    fn kitty_keypad_keys_are_distinct() {
+        let five = Key::Character("5".into());
+        let mut event = kitty_event(&five, &five, Some("5"));
+        event.location = KeyLocation::Numpad;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), DISAMBIGUATE),
+            Some(b"\x1b[57404u".to_vec())
+        );
+
+        let enter = Key::Named(NamedKey::Enter);
+        let mut event = kitty_event(&enter, &enter, Some("\r"));
+        event.location = KeyLocation::Numpad;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), DISAMBIGUATE),
+            Some(b"\x1b[57414u".to_vec())
+        );
+    }
+
+    #[test]
+    fn kitty_release_and_repeat_events() {
+        let flags = DISAMBIGUATE | TermMode::REPORT_EVENT_TYPES;
+        let a = Key::Character("a".into());
+        let mut event = kitty_event(&a, &a, Some("a"));
+
+        event.event_type = KeyEventType::Repeat;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::CONTROL, flags),
+            Some(b"\x1b[97;5:2u".to_vec())
+        );
+        event.event_type = KeyEventType::Release;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), flags),
+            Some(b"\x1b[97;1:3u".to_vec())
+        );
+
+        // Releases need the flag
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), DISAMBIGUATE),
+            None
+        );
+
+        // Enter, Tab and Backspace only report release with every key as escapes
+        let enter = Key::Named(NamedKey::Enter);
+        let mut event = kitty_event(&enter, &enter, None);
+        event.event_type = KeyEventType::Release;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), flags),
+            None
+        );
+        let flags = flags | TermMode::REPORT_ALL_KEYS_AS_ESC;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), flags),
+            Some(b"\x1b[13;1:3u".to_vec())
+        );
+
+        let up = Key::Named(NamedKey::ArrowUp);
+        let mut event = kitty_event(&up, &up, None);
+        event.event_type = KeyEventType::Release;
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::empty(), flags),
+            Some(b"\x1b[1;1:3A".to_vec())
+        );
+    }
+
+    #[test]
+    fn kitty_alternate_keys() {
+        let flags = DISAMBIGUATE | TermMode::REPORT_ALTERNATE_KEYS;
+        let shifted = Key::Character("A".into());
+        let a = Key::Character("a".into());
+        let event = kitty_event(&shifted, &a, None);
+        let mods = ModifiersState::CONTROL | ModifiersState::SHIFT;
+        assert_eq!(
+            encode_kitty_key(&event, mods, flags),
+            Some(b"\x1b[97:65;6u".to_vec())
+        );
+
+        // A non-US layout reports the US key at the same position
+        let cyrillic = Key::Character("ф".into());
+        let event = kitty_event(&cyrillic, &cyrillic, None);
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::CONTROL, flags),
+            Some(b"\x1b[1092::97;5u".to_vec())
+        );
+    }
+
+    #[test]
+    fn kitty_report_all_keys_as_escapes() {
+        let flags = TermMode::REPORT_ALL_KEYS_AS_ESC;
+        let none = ModifiersState::empty();
+        let a = Key::Character("a".into());
+        assert_eq!(
+            encode_kitty(&a, Some("a"), none, flags).as_deref(),
+            Some("\x1b[97u")
+        );
+        let enter = Key::Named(NamedKey::Enter);
+        assert_eq!(
+            encode_kitty(&enter, Some("\r"), none, flags).as_deref(),
+            Some("\x1b[13u")
+        );
+
+        // Lone modifiers count their own press
+        let shift = Key::Named(NamedKey::Shift);
+        let mut event = kitty_event(&shift, &shift, None);
+        event.location = KeyLocation::Left;
+        assert_eq!(
+            encode_kitty_key(&event, none, flags),
+            Some(b"\x1b[57441;2u".to_vec())
+        );
+        assert_eq!(encode_kitty_key(&event, none, DISAMBIGUATE), None);
+
+        // Associated text rides along with the key
+        let flags = flags | TermMode::REPORT_ASSOCIATED_TEXT;
+        let shifted = Key::Character("A".into());
+        let event = kitty_event(&shifted, &a, Some("A"));
+        assert_eq!(
+            encode_kitty_key(&event, ModifiersState::SHIFT, flags),
+            Some(b"\x1b[97;2;65u".to_vec())
+        );
+    }
 }
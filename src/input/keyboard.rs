@@ -3,16 +3,21 @@
 //! Extracts keyboard event handling logic from main.rs for better modularity.
 //! Returns actions that main.rs applies, keeping ownership/lifetime concerns there.
 
-use crt_core::Scroll;
-use winit::event::Modifiers;
+use crt_core::{Scroll, SearchOptions, TermMode};
+use winit::event::{KeyEvent, Modifiers};
 use winit::keyboard::{Key, NamedKey};
+use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
 
 use crate::config::{KeyAction, KeybindingsConfig};
-use crate::window::{TabId, WindowState};
+use crate::window::{ConfirmAction, TabId, WindowState};
 
+use super::commands::{
+    copy_last_command_output, scroll_to_prompt, select_command_output, toggle_command_fold,
+};
 use super::{
-    TabEditResult, clear_terminal_selection, get_clipboard_content, get_terminal_selection_text,
-    handle_shell_input, handle_tab_editing, paste_to_terminal, set_clipboard_content,
+    KittyKeyEvent, TabEditResult, answer_clipboard_read, clear_terminal_selection,
+    encode_kitty_key, get_clipboard_content, get_terminal_selection_text, handle_shell_input,
+    handle_tab_editing, paste_to_terminal, set_clipboard_content,
 };
 
 /// Result of keyboard event handling
@@ -31,8 +36,10 @@ pub enum KeyboardAction {
     NewWindow,
     /// Request a new tab (main.rs handles creation with spawn options)
     NewTab,
-    /// Quit the application
+    /// Quit the application (main.rs asks first if a program is running)
     Quit,
+    /// Quit without asking again (the user confirmed)
+    ForceQuit,
     /// Scroll the terminal
     Scroll(Scroll),
     /// Copy selection to clipboard
@@ -59,6 +66,18 @@ pub enum KeyboardAction {
     ToggleFullscreen,
     /// Open the config file in the default editor
     OpenConfig,
+    /// Restart the exited shell in a held tab (main.rs supplies spawn options)
+    RespawnShell(TabId),
+    /// Scroll to the previous shell prompt
+    PrevPrompt,
+    /// Scroll to the next shell prompt
+    NextPrompt,
+    /// Select the output of the command under the mouse, or the last command
+    SelectCommandOutput,
+    /// Copy the last command's output to clipboard
+    CopyCommandOutput,
+    /// Fold or unfold the output of the command under the mouse, or the last command
+    ToggleCommandFold,
 }
 
 /// Read-only context for keyboard action determination.
@@ -182,6 +201,8 @@ fn event_key_token(key: &Key) -> Option<String> {
         Key::Named(NamedKey::Space) => Some("space".to_string()),
         Key::Named(NamedKey::Tab) => Some("tab".to_string()),
         Key::Named(NamedKey::Enter) => Some("enter".to_string()),
+        Key::Named(NamedKey::ArrowUp) => Some("up".to_string()),
+        Key::Named(NamedKey::ArrowDown) => Some("down".to_string()),
         Key::Named(named) => {
             // Function keys (F1..F35) serialize as "F1", "F2", ... via Debug.
             let s = format!("{named:?}");
@@ -250,6 +271,11 @@ pub fn key_action_to_keyboard_action(action: &KeyAction, ctx: &InputContext) ->
         KeyAction::ResetFontSize => KeyboardAction::ResetFontSize,
         KeyAction::ToggleFullscreen => KeyboardAction::ToggleFullscreen,
         KeyAction::OpenConfig => KeyboardAction::OpenConfig,
+        KeyAction::PrevPrompt => KeyboardAction::PrevPrompt,
+        KeyAction::NextPrompt => KeyboardAction::NextPrompt,
+        KeyAction::SelectCommandOutput => KeyboardAction::SelectCommandOutput,
+        KeyAction::CopyCommandOutput => KeyboardAction::CopyCommandOutput,
+        KeyAction::ToggleCommandFold => KeyboardAction::ToggleCommandFold,
     }
 }
 
@@ -310,19 +336,20 @@ pub fn determine_scroll_action(
 /// Returns the action that main.rs should take, if any.
 pub fn handle_keyboard_input(
     state: &mut WindowState,
-    key: &Key,
-    text: Option<&str>,
+    event: &KeyEvent,
     modifiers: &Modifiers,
     keybindings: &KeybindingsConfig,
 ) -> KeyboardAction {
+    let key = &event.logical_key;
+    // Set again below if this press goes to the shell
+    state.interaction.keys_sent.remove(&event.physical_key);
+
     #[cfg(target_os = "macos")]
     let mod_pressed = modifiers.state().super_key();
     #[cfg(not(target_os = "macos"))]
     let mod_pressed = modifiers.state().control_key();
 
     let shift_pressed = modifiers.state().shift_key();
-    let ctrl_pressed = modifiers.state().control_key();
-    let alt_pressed = modifiers.state().alt_key();
 
     // Handle scroll shortcuts (Shift+PageUp/PageDown/Home/End)
     if let Some(action) = handle_scroll_shortcuts(state, key, mod_pressed, shift_pressed) {
@@ -363,6 +390,11 @@ pub fn handle_keyboard_input(
         }
     }
 
+    // Handle confirmation prompt (consumes all keys while showing)
+    if state.ui.confirm.is_active() {
+        return handle_confirm_input(state, key);
+    }
+
     // Handle tab editing
     if let TabEditResult::Handled = handle_tab_editing(state, key, mod_pressed) {
         return KeyboardAction::Handled;
@@ -417,7 +449,7 @@ pub fn handle_keyboard_input(
     }
 
     // Handle search input when search is active
-    if let Some(action) = handle_search_input(state, key, mod_pressed) {
+    if let Some(action) = handle_search_input(state, event, modifiers, mod_pressed) {
         return action;
     }
 
@@ -432,22 +464,89 @@ pub fn handle_keyboard_input(
         return action;
     }
 
+    // The shell in a held tab has exited: Enter restarts it, other keys are dropped
+    if let Some(tab_id) = state.gpu.tab_bar.active_tab_id()
+        && state
+            .shells
+            .get(&tab_id)
+            .is_some_and(|shell| shell.exit_code().is_some())
+    {
+        if matches!(key, Key::Named(NamedKey::Enter)) {
+            return KeyboardAction::RespawnShell(tab_id);
+        }
+        return KeyboardAction::Handled;
+    }
+
     // Send to shell (clears selection on input)
-    if handle_shell_input(
-        state,
-        key,
-        text,
-        mod_pressed,
-        ctrl_pressed,
-        shift_pressed,
-        alt_pressed,
-    ) {
+    if handle_shell_input(state, event, modifiers, mod_pressed) {
         clear_terminal_selection(state);
     }
 
     KeyboardAction::Handled
 }
 
+/// Handle a key release
+///
+/// Releases are only reported to programs that asked for them through the
+/// kitty keyboard protocol, and only for keys whose press went to the shell;
+/// letting go of Cmd+T doesn't send the program a stray `t` release.
+pub fn handle_key_release(state: &mut WindowState, event: &KeyEvent, modifiers: &Modifiers) {
+    if !state.interaction.keys_sent.remove(&event.physical_key) {
+        return;
+    }
+    let Some(shell) = state
+        .gpu
+        .tab_bar
+        .active_tab_id()
+        .and_then(|tab_id| state.shells.get(&tab_id))
+    else {
+        return;
+    };
+
+    let flags = shell.terminal().mode() & TermMode::KITTY_KEYBOARD_PROTOCOL;
+    let unmodified_key = event.key_without_modifiers();
+    let kitty_event = KittyKeyEvent::from_winit(event, &unmodified_key);
+    if let Some(bytes) = encode_kitty_key(&kitty_event, modifiers.state(), flags) {
+        shell.send_input(&bytes);
+        log::debug!("Sent key release: {:?}", bytes);
+    }
+}
+
+/// Handle keys while a confirmation prompt is showing
+///
+/// Enter or `y` accepts, Escape or `n` declines; other keys are swallowed.
+fn handle_confirm_input(state: &mut WindowState, key: &Key) -> KeyboardAction {
+    let accepted = match key {
+        Key::Named(NamedKey::Enter) => true,
+        Key::Character(c) if c.eq_ignore_ascii_case("y") => true,
+        Key::Named(NamedKey::Escape) => false,
+        Key::Character(c) if c.eq_ignore_ascii_case("n") => false,
+        _ => return KeyboardAction::Handled,
+    };
+
+    let action = state.ui.confirm.take();
+    state.render.dirty = true;
+    state.window.request_redraw();
+    if !accepted {
+        return KeyboardAction::Handled;
+    }
+
+    match action {
+        Some(ConfirmAction::ClipboardRead { tab_id, formatter }) => {
+            answer_clipboard_read(state, tab_id, &formatter);
+        }
+        Some(ConfirmAction::CloseTab(tab_id)) if state.gpu.tab_bar.tab_count() > 1 => {
+            state.close_tab(tab_id);
+        }
+        Some(ConfirmAction::CloseTab(_) | ConfirmAction::CloseWindow) => {
+            return KeyboardAction::CloseWindow;
+        }
+        Some(ConfirmAction::Quit) => return KeyboardAction::ForceQuit,
+        None => {}
+    }
+    KeyboardAction::Handled
+}
+
 /// Handle scroll shortcuts (Shift+PageUp/PageDown/Home/End)
 fn handle_scroll_shortcuts(
     state: &mut WindowState,
@@ -469,32 +568,60 @@ fn handle_scroll_shortcuts(
     Some(KeyboardAction::Handled)
 }
 
+/// Search options after Alt plus a key while searching: `c` toggles case
+/// sensitivity, `w` whole words and `r` regex. `None` for any other key.
+///
+/// Pure function.
+pub fn toggle_search_option(key: &Key, mut options: SearchOptions) -> Option<SearchOptions> {
+    let Key::Character(c) = key else {
+        return None;
+    };
+    match c.to_lowercase().as_str() {
+        "c" => options.case_sensitive = !options.case_sensitive,
+        "w" => options.whole_word = !options.whole_word,
+        "r" => options.regex = !options.regex,
+        _ => return None,
+    }
+    Some(options)
+}
+
 /// Handle search mode input
 fn handle_search_input(
     state: &mut WindowState,
-    key: &Key,
+    event: &KeyEvent,
+    modifiers: &Modifiers,
     mod_pressed: bool,
 ) -> Option<KeyboardAction> {
     if !state.ui.search.active {
         return None;
     }
 
-    match key {
+    // Alt+C, Alt+W and Alt+R toggle the options; Alt changes the character
+    // typed on macOS, so look at the key itself
+    if modifiers.state().alt_key()
+        && !mod_pressed
+        && let Some(options) =
+            toggle_search_option(&event.key_without_modifiers(), state.ui.search.options)
+    {
+        state.ui.search.options = options;
+        super::update_search_matches(state);
+        state.force_active_tab_redraw();
+        state.window.request_redraw();
+        return Some(KeyboardAction::Handled);
+    }
+
+    match &event.logical_key {
         Key::Named(NamedKey::Escape) => {
             // Close search
-            state.ui.search.active = false;
-            state.ui.search.query.clear();
-            state.ui.search.matches.clear();
-            state.ui.search.current_match = 0;
+            state.ui.search.close();
             state.force_active_tab_redraw();
             state.window.request_redraw();
             Some(KeyboardAction::Handled)
         }
         Key::Named(NamedKey::Enter) => {
-            // Next match on Enter
+            // Next match on Enter, previous on Shift+Enter
             if !state.ui.search.matches.is_empty() {
-                state.ui.search.current_match =
-                    (state.ui.search.current_match + 1) % state.ui.search.matches.len();
+                state.ui.search.navigate(modifiers.state().shift_key());
                 super::scroll_to_current_match(state);
                 state.force_active_tab_redraw();
                 state.window.request_redraw();
@@ -581,18 +708,24 @@ fn apply_keyboard_action(state: &mut WindowState, action: KeyboardAction) -> Key
             KeyboardAction::Handled
         }
         KeyboardAction::CloseTab(tab_id) => {
-            state.gpu.tab_bar.close_tab(tab_id);
-            state.remove_shell_for_tab(tab_id);
-            state.force_active_tab_redraw();
-            state.window.request_redraw();
+            if let Some(name) = state.busy_process(tab_id) {
+                state.ui.confirm.show(
+                    format!("{name} is running. Close this tab?"),
+                    ConfirmAction::CloseTab(tab_id),
+                );
+                state.render.dirty = true;
+                state.window.request_redraw();
+            } else {
+                state.close_tab(tab_id);
+            }
             KeyboardAction::Handled
         }
+        KeyboardAction::CloseWindow if !state.confirm_close_window() => KeyboardAction::Handled,
         KeyboardAction::ToggleSearch => {
-            state.ui.search.active = !state.ui.search.active;
-            if !state.ui.search.active {
-                state.ui.search.query.clear();
-                state.ui.search.matches.clear();
-                state.ui.search.current_match = 0;
+            if state.ui.search.active {
+                state.ui.search.close();
+            } else {
+                state.ui.search.active = true;
             }
             state.force_active_tab_redraw();
             state.window.request_redraw();
@@ -600,16 +733,7 @@ fn apply_keyboard_action(state: &mut WindowState, action: KeyboardAction) -> Key
         }
         KeyboardAction::SearchNavigate { reverse } => {
             if !state.ui.search.matches.is_empty() {
-                if reverse {
-                    if state.ui.search.current_match == 0 {
-                        state.ui.search.current_match = state.ui.search.matches.len() - 1;
-                    } else {
-                        state.ui.search.current_match -= 1;
-                    }
-                } else {
-                    state.ui.search.current_match =
-                        (state.ui.search.current_match + 1) % state.ui.search.matches.len();
-                }
+                state.ui.search.navigate(reverse);
                 super::scroll_to_current_match(state);
                 state.force_active_tab_redraw();
                 state.window.request_redraw();
@@ -634,6 +758,23 @@ fn apply_keyboard_action(state: &mut WindowState, action: KeyboardAction) -> Key
             state.window.request_redraw();
             KeyboardAction::Handled
         }
+        KeyboardAction::PrevPrompt | KeyboardAction::NextPrompt => {
+            let previous = matches!(action, KeyboardAction::PrevPrompt);
+            scroll_to_prompt(state, previous);
+            KeyboardAction::Handled
+        }
+        KeyboardAction::SelectCommandOutput => {
+            select_command_output(state);
+            KeyboardAction::Handled
+        }
+        KeyboardAction::CopyCommandOutput => {
+            copy_last_command_output(state);
+            KeyboardAction::Handled
+        }
+        KeyboardAction::ToggleCommandFold => {
+            toggle_command_fold(state);
+            KeyboardAction::Handled
+        }
         // Actions that don't need local side effects (handled by caller)
         other => other,
     }
@@ -728,6 +869,27 @@ mod tests {
         assert_eq!(resolve_keybinding(&kb, &key, &primary_mods()), None);
     }
 
+    #[test]
+    fn test_default_binding_prompt_navigation() {
+        let kb = KeybindingsConfig::default();
+        let up = Key::Named(NamedKey::ArrowUp);
+        let down = Key::Named(NamedKey::ArrowDown);
+        assert_eq!(
+            resolve_keybinding(&kb, &up, &primary_shift_mods()),
+            Some(KeyAction::PrevPrompt)
+        );
+        assert_eq!(
+            resolve_keybinding(&kb, &down, &primary_shift_mods()),
+            Some(KeyAction::NextPrompt)
+        );
+        // Plain arrows still go to the shell
+        assert_eq!(resolve_keybinding(&kb, &up, &Modifiers::default()), None);
+        assert_eq!(
+            resolve_keybinding(&kb, &Key::Character("E".into()), &primary_shift_mods()),
+            Some(KeyAction::ToggleCommandFold)
+        );
+    }
+
     #[test]
     fn test_no_modifier_does_not_match_primary_binding() {
         let kb = KeybindingsConfig::default();
@@ -793,6 +955,21 @@ mod tests {
         assert!(matches!(result, Some(KeyboardAction::NewWindow)));
     }
 
+    #[test]
+    fn test_search_option_toggles() {
+        let toggle = |c: &str, options| toggle_search_option(&Key::Character(c.into()), options);
+        let options = SearchOptions::default();
+        let case = toggle("c", options).unwrap();
+        assert!(case.case_sensitive && !case.whole_word && !case.regex);
+        let word = toggle("W", case).unwrap();
+        assert!(word.case_sensitive && word.whole_word);
+        assert!(toggle("r", options).unwrap().regex);
+        assert_eq!(toggle("c", case), Some(options));
+        assert_eq!(toggle("x", options), None);
+        let enter = Key::Named(NamedKey::Enter);
+        assert_eq!(toggle_search_option(&enter, options), None);
+    }
+
     #[test]
     fn test_hardcoded_cmd_f_returns_toggle_search() {
         let ctx = default_ctx();
@@ -2,32 +2,39 @@
 //!
 //! Keyboard and mouse input processing for terminal and tab bar.
 
+mod commands;
 pub mod drag;
 mod key_encoder;
 mod keyboard;
 mod mouse;
+mod osc52;
 
-pub use key_encoder::encode_key;
-pub use keyboard::{KeyboardAction, handle_keyboard_input};
+pub use key_encoder::{KittyKeyEvent, encode_key, encode_keypad_key, encode_kitty_key};
+pub use keyboard::{KeyboardAction, handle_key_release, handle_keyboard_input};
 pub use mouse::{
     GridLayout, MouseClickTarget, compute_click_count, determine_click_target,
     handle_cursor_moved, handle_mouse_input, handle_mouse_wheel, normalize_scroll_delta,
     screen_to_grid_position,
 };
+pub use osc52::{answer_clipboard_read, handle_clipboard_requests};
 
 use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
-use crt_core::{Column, Line, Point, SelectionType, ShellTerminal, TermMode};
+use crt_core::{
+    Column, FileUri, Line, MouseModes, Point, Search, SelectionType, ShellTerminal, TermMode,
+};
 use regex::Regex;
-use winit::keyboard::{Key, NamedKey};
+use winit::event::{KeyEvent, Modifiers};
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
 
-use crate::window::WindowState;
+use crate::window::{ConfirmAction, SearchMatch, WindowState};
 
 /// Detected URL with its position in the terminal (supports multi-line spans)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DetectedUrl {
     /// The URL string
     pub url: String,
@@ -39,6 +46,8 @@ pub struct DetectedUrl {
     pub line: usize,
     /// End line number (same as line for single-line URLs)
     pub end_line: usize,
+    /// Set for OSC 8 hyperlinks, whose target is not the text on screen
+    pub explicit: bool,
 }
 
 /// Get the URL regex (compiled once)
@@ -110,6 +119,7 @@ pub fn detect_urls_in_line(line_text: &str, line_num: usize) -> Vec<DetectedUrl>
                 end_col,
                 line: line_num,
                 end_line: line_num, // Same line initially, may be extended by merge_wrapped_urls
+                explicit: false,
             }
         })
         .collect()
@@ -213,6 +223,57 @@ pub fn merge_wrapped_urls(urls: &mut Vec<DetectedUrl>, line_texts: &BTreeMap<i32
     }
 }
 
+/// Add one OSC 8 hyperlink cell to the spans collected for the viewport.
+///
+/// Cells arrive in row-major order. A cell extends the previous span when it
+/// carries the same URI and directly follows it, including the wrap from the
+/// last column of one line to column 0 of the next.
+pub fn extend_hyperlink_spans(
+    spans: &mut Vec<DetectedUrl>,
+    uri: &str,
+    col: usize,
+    line: usize,
+    cols: usize,
+) {
+    if let Some(last) = spans.last_mut()
+        && last.url == uri
+    {
+        let adjacent = last.end_line == line && last.end_col == col;
+        let wrapped = last.end_line + 1 == line && last.end_col >= cols && col == 0;
+        if adjacent || wrapped {
+            last.end_line = line;
+            last.end_col = col + 1;
+            return;
+        }
+    }
+    spans.push(DetectedUrl {
+        url: uri.to_string(),
+        start_col: col,
+        end_col: col + 1,
+        line,
+        end_line: line,
+        explicit: true,
+    });
+}
+
+/// Merge OSC 8 hyperlinks into the URLs detected from screen text.
+///
+/// The program's explicit target wins: any detected URL overlapping a
+/// hyperlink is dropped so the span is only underlined (and opened) once.
+pub fn merge_hyperlinks(urls: &mut Vec<DetectedUrl>, hyperlinks: Vec<DetectedUrl>) {
+    if hyperlinks.is_empty() {
+        return;
+    }
+    urls.retain(|url| !hyperlinks.iter().any(|link| spans_overlap(url, link)));
+    urls.splice(0..0, hyperlinks);
+}
+
+/// Whether two (possibly multi-line) URL spans share a cell
+fn spans_overlap(a: &DetectedUrl, b: &DetectedUrl) -> bool {
+    (a.line, a.start_col) < (b.end_line, b.end_col)
+        && (b.line, b.start_col) < (a.end_line, a.end_col)
+}
+
 /// Find where URL-like characters end in a continuation line
 fn find_url_continuation_end(text: &str) -> usize {
     // URL characters: anything except whitespace and certain special chars
@@ -557,6 +618,85 @@ pub fn open_file(
     }
 }
 
+/// Resolve a `file://host/path` URI to a local path and optional line/col.
+///
+/// The host must be empty, `localhost`, or `local_host`; a file on another
+/// machine (e.g. a link printed over ssh) can't be opened here. The line can
+/// be given as a `:line[:col]` suffix or a `#L<line>` / `#<line>` fragment.
+pub fn parse_file_uri(
+    uri: &str,
+    local_host: Option<&str>,
+) -> Option<(PathBuf, Option<usize>, Option<usize>)> {
+    let (uri, fragment) = match uri.split_once('#') {
+        Some((uri, fragment)) => (uri, Some(fragment)),
+        None => (uri, None),
+    };
+    let file = FileUri::parse(uri).filter(|file| file.is_local(local_host))?;
+    let fragment_line = fragment.and_then(|f| f.strip_prefix('L').unwrap_or(f).parse().ok());
+    if fragment_line.is_some() {
+        return Some((file.path, fragment_line, None));
+    }
+    let path = file.path.to_string_lossy();
+    let (path, line, col) = parse_path_suffix(&path);
+    Some((PathBuf::from(path), line, col))
+}
+
+/// This machine's hostname, for matching `file://host/` URIs (looked up once)
+pub fn local_hostname() -> Option<&'static str> {
+    static HOSTNAME: OnceLock<Option<String>> = OnceLock::new();
+    HOSTNAME
+        .get_or_init(|| {
+            let name = std::env::var("HOSTNAME")
+                .ok()
+                .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+                .or_else(|| {
+                    std::process::Command::new("hostname")
+                        .output()
+                        .ok()
+                        .and_then(|out| String::from_utf8(out.stdout).ok())
+                })?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| name.to_string())
+        })
+        .as_deref()
+}
+
+/// URL schemes a clicked link may open in the browser or mail client
+const OPENABLE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Check if a link is safe to hand to the OS to open.
+///
+/// Programs choose OSC 8 link targets, so only web and mail links (and bare
+/// `www.` addresses) are opened; other schemes could launch any registered
+/// handler. Pure function.
+pub fn is_openable_url(url: &str) -> bool {
+    if url.starts_with("www.") {
+        return true;
+    }
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        OPENABLE_SCHEMES
+            .iter()
+            .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+    })
+}
+
+/// Open a clicked link.
+///
+/// Local `file://` URIs go through [`open_file`] so `open_file_command` and
+/// line numbers apply; web and mail links are handed to the OS. Anything
+/// else, `file://` URIs on other hosts included, is ignored.
+pub fn open_link(url: &str, open_file_command: Option<&str>) {
+    if let Some((path, line, col)) = parse_file_uri(url, local_hostname()) {
+        log::info!("Opening file: {}", path.display());
+        open_file(&path, line, col, open_file_command);
+    } else if is_openable_url(url) {
+        log::info!("Opening URL: {}", url);
+        open_url(url);
+    } else {
+        log::warn!("Not opening link {:?}: scheme not allowed", url);
+    }
+}
+
 /// Threshold for multi-click detection
 const MULTI_CLICK_THRESHOLD: Duration = Duration::from_millis(400);
 /// Maximum distance (in cells) for multi-click to register
@@ -571,56 +711,192 @@ pub const MOUSE_BUTTON_MOTION: u8 = 32;
 pub const MOUSE_BUTTON_SCROLL_UP: u8 = 64;
 pub const MOUSE_BUTTON_SCROLL_DOWN: u8 = 65;
 
-/// Check if the terminal has mouse reporting enabled
-pub fn should_report_mouse(shell: &ShellTerminal) -> bool {
-    let mode = shell.terminal().inner().mode();
-    mode.intersects(
-        TermMode::MOUSE_REPORT_CLICK
-            | TermMode::MOUSE_DRAG
-            | TermMode::MOUSE_MOTION
-            | TermMode::SGR_MOUSE,
-    )
+// Modifier bits added to a button code
+const MOUSE_MOD_SHIFT: u8 = 4;
+const MOUSE_MOD_ALT: u8 = 8;
+const MOUSE_MOD_CTRL: u8 = 16;
+
+/// Largest 1-indexed coordinate the normal encoding fits in a byte (255 - 32)
+const MOUSE_NORMAL_LIMIT: usize = 223;
+/// Largest 1-indexed coordinate the UTF-8 encoding fits in two bytes (2047 - 32)
+const MOUSE_UTF8_LIMIT: usize = 2015;
+
+/// How mouse events are encoded for the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEncoding {
+    /// `CSI M` followed by one byte each for button, column and row
+    Normal,
+    /// Like `Normal`, with coordinates as UTF-8 characters (1005)
+    Utf8,
+    /// `CSI b ; col ; row M` with the button offset by 32 (1015)
+    Urxvt,
+    /// `CSI < b ; col ; row M`, or `m` for a release (1006)
+    Sgr,
+    /// `Sgr` with pixel coordinates in place of cells (1016)
+    SgrPixels,
+}
+
+/// Where a mouse event happened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MousePosition {
+    /// Column (0-indexed)
+    pub col: usize,
+    /// Line (0-indexed)
+    pub line: usize,
+    /// Pixels from the left edge of the text area
+    pub x: usize,
+    /// Pixels from the top edge of the text area
+    pub y: usize,
+}
+
+/// Mouse reporting requested by the program running in a terminal
+#[derive(Debug, Clone, Copy)]
+pub struct MouseProtocol {
+    mode: TermMode,
+    /// X10 compatibility mode (9) without a newer tracking mode
+    x10: bool,
+    pub encoding: MouseEncoding,
 }
 
-/// Check if the terminal is tracking mouse motion
-pub fn should_report_motion(shell: &ShellTerminal, button_pressed: bool) -> bool {
-    let mode = shell.terminal().inner().mode();
-    // MOUSE_MOTION: report all motion
-    // MOUSE_DRAG: report motion only when button is pressed
-    mode.contains(TermMode::MOUSE_MOTION) || (mode.contains(TermMode::MOUSE_DRAG) && button_pressed)
+impl MouseProtocol {
+    pub fn new(mode: TermMode, modes: MouseModes) -> Self {
+        // SGR is preferred when a program enables several encodings
+        let encoding = if modes.sgr_pixels {
+            MouseEncoding::SgrPixels
+        } else if mode.contains(TermMode::SGR_MOUSE) {
+            MouseEncoding::Sgr
+        } else if modes.urxvt {
+            MouseEncoding::Urxvt
+        } else if mode.contains(TermMode::UTF8_MOUSE) {
+            MouseEncoding::Utf8
+        } else {
+            MouseEncoding::Normal
+        };
+        Self {
+            mode,
+            x10: modes.x10 && !mode.intersects(TermMode::MOUSE_MODE),
+            encoding,
+        }
+    }
+
+    /// The protocol a shell's program has set up
+    pub fn of(shell: &ShellTerminal) -> Self {
+        let terminal = shell.terminal();
+        Self::new(terminal.mode(), terminal.mouse_modes())
+    }
+
+    /// Check if button presses are reported
+    pub fn reports_buttons(&self) -> bool {
+        self.x10 || self.mode.intersects(TermMode::MOUSE_MODE)
+    }
+
+    /// Check if pointer motion is reported
+    pub fn reports_motion(&self, button_pressed: bool) -> bool {
+        // MOUSE_MOTION: report all motion
+        // MOUSE_DRAG: report motion only when button is pressed
+        self.mode.contains(TermMode::MOUSE_MOTION)
+            || (self.mode.contains(TermMode::MOUSE_DRAG) && button_pressed)
+    }
+
+    /// Report for a button, motion or wheel event
+    ///
+    /// Returns `None` if the protocol leaves the event out or can't encode
+    /// its position.
+    pub fn report(
+        &self,
+        button: u8,
+        modifiers: ModifiersState,
+        position: MousePosition,
+        pressed: bool,
+    ) -> Option<Vec<u8>> {
+        if self.x10 {
+            // X10 reports presses alone, without modifiers
+            if !pressed || button & MOUSE_BUTTON_MOTION != 0 {
+                return None;
+            }
+            return mouse_report(button, position, pressed, self.encoding);
+        }
+        let button = button | mouse_modifier_bits(modifiers);
+        mouse_report(button, position, pressed, self.encoding)
+    }
 }
 
-/// Check if SGR extended mouse mode is enabled
-pub fn is_sgr_mouse_mode(shell: &ShellTerminal) -> bool {
-    shell
-        .terminal()
-        .inner()
-        .mode()
-        .contains(TermMode::SGR_MOUSE)
+/// Button code bits for the held modifiers
+pub fn mouse_modifier_bits(modifiers: ModifiersState) -> u8 {
+    [
+        (modifiers.shift_key(), MOUSE_MOD_SHIFT),
+        (modifiers.alt_key(), MOUSE_MOD_ALT),
+        (modifiers.control_key(), MOUSE_MOD_CTRL),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .fold(0, |bits, (_, bit)| bits | bit)
 }
 
 /// Generate mouse escape sequence for terminal
 ///
 /// # Arguments
-/// * `button` - Mouse button code (0=left, 1=middle, 2=right, 3=release, 32+=motion, 64/65=scroll)
-/// * `col` - Column (0-indexed)
-/// * `line` - Line (0-indexed)
-/// * `pressed` - Whether this is a press event (for SGR mode)
-/// * `sgr_mode` - Whether to use SGR extended encoding
-pub fn mouse_report(button: u8, col: usize, line: usize, pressed: bool, sgr_mode: bool) -> Vec<u8> {
-    if sgr_mode {
+/// * `button` - Button code (0=left, 1=middle, 2=right, 3=release, +32 for motion,
+///   64/65=scroll), plus modifier bits
+/// * `position` - Cell and pixel position of the pointer
+/// * `pressed` - Whether this is a press event (releases are `m` in SGR, button 3 otherwise)
+/// * `encoding` - Encoding the program asked for
+///
+/// Returns `None` if the position is beyond what `encoding` can carry.
+pub fn mouse_report(
+    button: u8,
+    position: MousePosition,
+    pressed: bool,
+    encoding: MouseEncoding,
+) -> Option<Vec<u8>> {
+    if let MouseEncoding::Sgr | MouseEncoding::SgrPixels = encoding {
         // SGR extended mode: \x1b[<Btn;Col;RowM (press) or m (release)
         // Uses 1-indexed coordinates
+        let (x, y) = if encoding == MouseEncoding::SgrPixels {
+            (position.x, position.y)
+        } else {
+            (position.col, position.line)
+        };
         let suffix = if pressed { 'M' } else { 'm' };
-        format!("\x1b[<{};{};{}{}", button, col + 1, line + 1, suffix).into_bytes()
+        return Some(format!("\x1b[<{};{};{}{}", button, x + 1, y + 1, suffix).into_bytes());
+    }
+
+    // The other encodings can't say which button was released
+    let button = if pressed {
+        button
     } else {
-        // Legacy X10 mode: \x1b[M<btn+32><col+33><row+33>
-        // Coordinates are offset by 32 and limited to 223 (255 - 32)
-        let mut seq = vec![0x1b, b'[', b'M'];
-        seq.push(button + 32);
-        seq.push(((col + 33).min(255)) as u8);
-        seq.push(((line + 33).min(255)) as u8);
-        seq
+        button | MOUSE_BUTTON_RELEASE
+    };
+    let col = position.col + 1;
+    let line = position.line + 1;
+    match encoding {
+        MouseEncoding::Urxvt => {
+            Some(format!("\x1b[{};{};{}M", u32::from(button) + 32, col, line).into_bytes())
+        }
+        MouseEncoding::Utf8 => {
+            if col > MOUSE_UTF8_LIMIT || line > MOUSE_UTF8_LIMIT {
+                return None;
+            }
+            let mut seq = String::from("\x1b[M");
+            for value in [usize::from(button), col, line] {
+                seq.push(char::from_u32(value as u32 + 32)?);
+            }
+            Some(seq.into_bytes())
+        }
+        _ => {
+            // Legacy mode: \x1b[M<btn+32><col+32><row+32>
+            if col > MOUSE_NORMAL_LIMIT || line > MOUSE_NORMAL_LIMIT {
+                return None;
+            }
+            Some(vec![
+                0x1b,
+                b'[',
+                b'M',
+                button + 32,
+                (col + 32) as u8,
+                (line + 32) as u8,
+            ])
+        }
     }
 }
 
@@ -698,15 +974,13 @@ pub fn handle_tab_editing(state: &mut WindowState, key: &Key, mod_pressed: bool)
 ///
 /// Uses termwiz for key-to-escape-sequence encoding, with platform-specific
 /// overrides for macOS word navigation. Falls back to the event's text field
-/// for any keys termwiz doesn't handle.
+/// for any keys termwiz doesn't handle. Programs that enabled the kitty
+/// keyboard protocol get `encode_kitty_key` instead.
 pub fn handle_shell_input(
     state: &mut WindowState,
-    key: &Key,
-    text: Option<&str>,
+    event: &KeyEvent,
+    modifiers: &Modifiers,
     mod_pressed: bool,
-    ctrl_pressed: bool,
-    shift_pressed: bool,
-    alt_pressed: bool,
 ) -> bool {
     let tab_id = state.gpu.tab_bar.active_tab_id();
     let Some(tab_id) = tab_id else { return false };
@@ -714,6 +988,12 @@ pub fn handle_shell_input(
         return false;
     };
 
+    let key = &event.logical_key;
+    let text = event.text.as_deref();
+    let ctrl_pressed = modifiers.state().control_key();
+    let shift_pressed = modifiers.state().shift_key();
+    let alt_pressed = modifiers.state().alt_key();
+
     log::debug!(
         "Shell input: key={:?} text={:?} mod={} ctrl={} shift={} alt={}",
         key,
@@ -726,15 +1006,34 @@ pub fn handle_shell_input(
 
     let mut input_sent = false;
 
+    // Programs using the kitty keyboard protocol get keys as pressed, without
+    // the readline and macOS navigation rewrites below
+    let mode = shell.terminal().mode();
+    let kitty_flags = mode & TermMode::KITTY_KEYBOARD_PROTOCOL;
+    if !kitty_flags.is_empty() {
+        let unmodified_key = event.key_without_modifiers();
+        let kitty_event = KittyKeyEvent::from_winit(event, &unmodified_key);
+        if !mod_pressed
+            && let Some(bytes) = encode_kitty_key(&kitty_event, modifiers.state(), kitty_flags)
+        {
+            shell.send_input(&bytes);
+            input_sent = true;
+            log::debug!("Sent via kitty protocol: {:?}", bytes);
+        }
+    }
+
     // Handle Home/End keys explicitly using readline's native bindings
     // Ctrl-A (0x01) = beginning of line, Ctrl-E (0x05) = end of line
-    // These work universally in bash, zsh, and other readline-based shells
+    // These work universally in bash, zsh, and other readline-based shells;
+    // programs that set application cursor mode get the SS3 forms instead
+    let readline_keys =
+        kitty_flags.is_empty() && !mode.contains(TermMode::APP_CURSOR) && !shift_pressed;
     match key {
-        Key::Named(NamedKey::Home) if !shift_pressed => {
+        Key::Named(NamedKey::Home) if readline_keys => {
             shell.send_input(b"\x01"); // Ctrl-A = beginning of line
             input_sent = true;
         }
-        Key::Named(NamedKey::End) if !shift_pressed => {
+        Key::Named(NamedKey::End) if readline_keys => {
             shell.send_input(b"\x05"); // Ctrl-E = end of line
             input_sent = true;
         }
@@ -744,7 +1043,7 @@ pub fn handle_shell_input(
     // macOS-specific word/line navigation shortcuts (Option+Arrow, Cmd+Arrow, Option+Backspace)
     // These override standard encoding because macOS users expect this behavior
     #[cfg(target_os = "macos")]
-    if !input_sent {
+    if !input_sent && kitty_flags.is_empty() {
         match key {
             Key::Named(NamedKey::Backspace) if alt_pressed => {
                 shell.send_input(b"\x1b\x7f"); // ESC DEL = delete word backward
@@ -774,10 +1073,14 @@ pub fn handle_shell_input(
     }
 
     // If not handled by platform-specific code, use termwiz encoding
-    if !input_sent {
+    if !input_sent && kitty_flags.is_empty() {
         // Don't send Cmd+key combinations to terminal (they're app shortcuts)
+        let keypad = event.location == KeyLocation::Numpad && !ctrl_pressed && !alt_pressed;
         if !mod_pressed
-            && let Some(bytes) = encode_key(key, ctrl_pressed, shift_pressed, alt_pressed)
+            && let Some(bytes) = keypad
+                .then(|| encode_keypad_key(key, mode))
+                .flatten()
+                .or_else(|| encode_key(key, ctrl_pressed, shift_pressed, alt_pressed, mode))
         {
             shell.send_input(&bytes);
             input_sent = true;
@@ -798,13 +1101,13 @@ pub fn handle_shell_input(
     }
 
     if input_sent {
+        state.interaction.keys_sent.insert(event.physical_key);
         // Scroll to bottom when user types (show live output)
         if shell.is_scrolled_back() {
             shell.scroll_to_bottom();
         }
-        // Always invalidate content hash when input is sent to ensure re-render
-        // even if PTY output hasn't arrived yet (handles TUI apps like Claude Code)
-        state.content_hashes.insert(tab_id, 0);
+        // Always redraw when input is sent, even if PTY output hasn't arrived
+        // yet (handles TUI apps like Claude Code); damage covers the echo
         state.render.dirty = true;
         state.window.request_redraw();
     }
@@ -835,9 +1138,18 @@ pub fn handle_tab_click(state: &mut WindowState, x: f32, y: f32, now: std::time:
     } else if let Some((tab_id, is_close)) = state.gpu.tab_bar.hit_test(x, y) {
         if is_close {
             if state.gpu.tab_bar.tab_count() > 1 {
-                state.gpu.tab_bar.close_tab(tab_id);
-                tab_closed = Some(tab_id);
-                tab_switched = true;
+                if let Some(name) = state.busy_process(tab_id) {
+                    state.ui.confirm.show(
+                        format!("{name} is running. Close this tab?"),
+                        ConfirmAction::CloseTab(tab_id),
+                    );
+                    state.render.dirty = true;
+                    state.window.request_redraw();
+                } else {
+                    state.gpu.tab_bar.close_tab(tab_id);
+                    tab_closed = Some(tab_id);
+                    tab_switched = true;
+                }
             }
         } else {
             let is_double_click = state
@@ -888,8 +1200,6 @@ pub fn handle_resize(
     new_width: u32,
     new_height: u32,
 ) {
-    use crt_core::Size;
-
     if new_width < 100 || new_height < 80 {
         return;
     }
@@ -913,8 +1223,9 @@ pub fn handle_resize(
     state.rows = new_rows;
 
     // Resize all shells in this window
+    let terminal_size = state.terminal_size();
     for shell in state.shells.values_mut() {
-        shell.resize(Size::new(new_cols, new_rows));
+        shell.resize(terminal_size);
     }
 
     // Update GPU resources
@@ -990,15 +1301,37 @@ pub fn screen_to_cell(state: &WindowState, x: f32, y: f32) -> Option<(usize, usi
     screen_to_grid_position(x, y, &layout)
 }
 
+/// Convert screen coordinates to a mouse report position
+///
+/// The pixel offset is measured from the text area inside the padding and
+/// kept within the grid, so it agrees with the cell.
+fn mouse_position(state: &WindowState, x: f32, y: f32, col: usize, line: usize) -> MousePosition {
+    let (offset_x, offset_y) = state.gpu.tab_bar.content_offset();
+    let padding = 10.0 * state.scale_factor;
+    let cell_width = state.gpu.glyph_cache.cell_width();
+    let line_height = state.gpu.glyph_cache.line_height();
+    let max_x = (state.cols as f32 * cell_width - 1.0).max(0.0);
+    let max_y = (state.rows as f32 * line_height - 1.0).max(0.0);
+    MousePosition {
+        col,
+        line,
+        x: (x - offset_x - padding).clamp(0.0, max_x) as usize,
+        y: (y - offset_y - padding).clamp(0.0, max_y) as usize,
+    }
+}
+
 /// Handle mouse press for terminal selection or mouse reporting
 /// Returns true if the press was handled (was in terminal area)
 #[allow(dead_code)]
 pub fn handle_terminal_mouse_press(state: &mut WindowState, x: f32, y: f32, now: Instant) -> bool {
-    handle_terminal_mouse_button(state, x, y, now, MOUSE_BUTTON_LEFT, true)
+    let modifiers = ModifiersState::empty();
+    handle_terminal_mouse_button(state, x, y, now, MOUSE_BUTTON_LEFT, true, modifiers)
 }
 
 /// Handle mouse button press/release for any button
 /// Returns true if the event was handled (was in terminal area)
+///
+/// Holding Shift selects text even when the program wants the mouse.
 pub fn handle_terminal_mouse_button(
     state: &mut WindowState,
     x: f32,
@@ -1006,6 +1339,7 @@ pub fn handle_terminal_mouse_button(
     now: Instant,
     button: u8,
     pressed: bool,
+    modifiers: ModifiersState,
 ) -> bool {
     // Check if click is in tab bar area first
     let tab_bar_height = state.gpu.tab_bar.height() * state.scale_factor;
@@ -1016,6 +1350,7 @@ pub fn handle_terminal_mouse_button(
     let Some((col, line)) = screen_to_cell(state, x, y) else {
         return false;
     };
+    let position = mouse_position(state, x, y, col, line);
 
     // Get the active shell
     let tab_id = state.gpu.tab_bar.active_tab_id();
@@ -1025,20 +1360,16 @@ pub fn handle_terminal_mouse_button(
     };
 
     // Check if we should report mouse events to the terminal
-    if should_report_mouse(shell) {
-        let sgr = is_sgr_mouse_mode(shell);
-        let report_button = if !pressed && !sgr {
-            // In legacy mode, release is button 3
-            MOUSE_BUTTON_RELEASE
-        } else {
-            button
-        };
-        let seq = mouse_report(report_button, col, line, pressed, sgr);
-        shell.send_input(&seq);
+    let protocol = MouseProtocol::of(shell);
+    if protocol.reports_buttons() && !modifiers.shift_key() {
+        if let Some(seq) = protocol.report(button, modifiers, position, pressed) {
+            shell.send_input(&seq);
+        }
 
         // Track button state for drag reporting
         if button == MOUSE_BUTTON_LEFT {
             state.interaction.mouse_pressed = pressed;
+            state.interaction.mouse_press_reported = pressed;
         }
 
         state.render.dirty = true;
@@ -1074,6 +1405,7 @@ pub fn handle_terminal_mouse_button(
         state.interaction.last_selection_click_time = Some(now);
         state.interaction.last_selection_click_pos = Some((col, line));
         state.interaction.mouse_pressed = true;
+        state.interaction.mouse_press_reported = false;
 
         // Convert viewport coordinates to grid coordinates
         // Grid coordinates: negative = scrollback history, 0+ = visible screen
@@ -1108,10 +1440,16 @@ pub fn handle_terminal_mouse_button(
 }
 
 /// Handle mouse move for terminal selection (dragging) or mouse motion reporting
-pub fn handle_terminal_mouse_move(state: &mut WindowState, x: f32, y: f32) {
+pub fn handle_terminal_mouse_move(
+    state: &mut WindowState,
+    x: f32,
+    y: f32,
+    modifiers: ModifiersState,
+) {
     let Some((col, line)) = screen_to_cell(state, x, y) else {
         return;
     };
+    let position = mouse_position(state, x, y, col, line);
 
     let tab_id = state.gpu.tab_bar.active_tab_id();
     let Some(tab_id) = tab_id else { return };
@@ -1119,17 +1457,25 @@ pub fn handle_terminal_mouse_move(state: &mut WindowState, x: f32, y: f32) {
         return;
     };
 
-    // Check if we should report motion to terminal
-    if should_report_motion(shell, state.interaction.mouse_pressed) {
-        let sgr = is_sgr_mouse_mode(shell);
+    // Check if we should report motion to terminal; a drag goes wherever its
+    // press went
+    let pressed = state.interaction.mouse_pressed;
+    let protocol = MouseProtocol::of(shell);
+    let report = if pressed {
+        state.interaction.mouse_press_reported
+    } else {
+        !modifiers.shift_key()
+    };
+    if report && protocol.reports_motion(pressed) {
         // Button code: 32 + button for motion with button, or just 35 for motion without
-        let button = if state.interaction.mouse_pressed {
+        let button = if pressed {
             MOUSE_BUTTON_MOTION + MOUSE_BUTTON_LEFT // 32 = motion with left button
         } else {
             MOUSE_BUTTON_MOTION + MOUSE_BUTTON_RELEASE // 35 = motion without button
         };
-        let seq = mouse_report(button, col, line, true, sgr);
-        shell.send_input(&seq);
+        if let Some(seq) = protocol.report(button, modifiers, position, true) {
+            shell.send_input(&seq);
+        }
 
         state.render.dirty = true;
         state.window.request_redraw();
@@ -1137,7 +1483,7 @@ pub fn handle_terminal_mouse_move(state: &mut WindowState, x: f32, y: f32) {
     }
 
     // Local selection handling
-    if !state.interaction.mouse_pressed {
+    if !pressed || state.interaction.mouse_press_reported {
         return;
     }
 
@@ -1152,11 +1498,18 @@ pub fn handle_terminal_mouse_move(state: &mut WindowState, x: f32, y: f32) {
 }
 
 /// Handle mouse release for terminal selection or mouse reporting
-pub fn handle_terminal_mouse_release(state: &mut WindowState, x: f32, y: f32) {
+pub fn handle_terminal_mouse_release(
+    state: &mut WindowState,
+    x: f32,
+    y: f32,
+    button: u8,
+    modifiers: ModifiersState,
+) {
     let Some((col, line)) = screen_to_cell(state, x, y) else {
         state.interaction.mouse_pressed = false;
         return;
     };
+    let position = mouse_position(state, x, y, col, line);
 
     let tab_id = state.gpu.tab_bar.active_tab_id();
     let Some(tab_id) = tab_id else {
@@ -1168,15 +1521,18 @@ pub fn handle_terminal_mouse_release(state: &mut WindowState, x: f32, y: f32) {
         return;
     };
 
-    // Check if we should report the release to terminal
-    if should_report_mouse(shell) {
-        let sgr = is_sgr_mouse_mode(shell);
-        let button = if sgr {
-            MOUSE_BUTTON_LEFT // SGR mode sends button with release suffix 'm'
-        } else {
-            MOUSE_BUTTON_RELEASE // Legacy mode sends button 3 for release
-        };
-        let seq = mouse_report(button, col, line, false, sgr);
+    // Check if we should report the release to terminal; a release goes
+    // wherever its press went
+    let protocol = MouseProtocol::of(shell);
+    let report = if button == MOUSE_BUTTON_LEFT && state.interaction.mouse_pressed {
+        state.interaction.mouse_press_reported
+    } else {
+        !modifiers.shift_key()
+    };
+    if report
+        && protocol.reports_buttons()
+        && let Some(seq) = protocol.report(button, modifiers, position, false)
+    {
         shell.send_input(&seq);
     }
 
@@ -1186,10 +1542,17 @@ pub fn handle_terminal_mouse_release(state: &mut WindowState, x: f32, y: f32) {
 
 /// Handle mouse scroll wheel for terminal scrollback or mouse reporting
 /// Returns true if the scroll was handled by mouse reporting
-pub fn handle_terminal_scroll(state: &mut WindowState, x: f32, y: f32, delta_y: f32) -> bool {
+pub fn handle_terminal_scroll(
+    state: &mut WindowState,
+    x: f32,
+    y: f32,
+    delta_y: f32,
+    modifiers: ModifiersState,
+) -> bool {
     let Some((col, line)) = screen_to_cell(state, x, y) else {
         return false;
     };
+    let position = mouse_position(state, x, y, col, line);
 
     let tab_id = state.gpu.tab_bar.active_tab_id();
     let Some(tab_id) = tab_id else { return false };
@@ -1198,25 +1561,52 @@ pub fn handle_terminal_scroll(state: &mut WindowState, x: f32, y: f32, delta_y:
     };
 
     // Check if we should report scroll to terminal
-    if should_report_mouse(shell) {
-        let sgr = is_sgr_mouse_mode(shell);
+    let protocol = MouseProtocol::of(shell);
+    if protocol.reports_buttons() {
         // Scroll up = 64, scroll down = 65
         let button = if delta_y > 0.0 {
             MOUSE_BUTTON_SCROLL_UP
         } else {
             MOUSE_BUTTON_SCROLL_DOWN
         };
-        let seq = mouse_report(button, col, line, true, sgr);
-        shell.send_input(&seq);
+        if let Some(seq) = protocol.report(button, modifiers, position, true) {
+            shell.send_input(&seq);
+        }
 
         state.render.dirty = true;
         state.window.request_redraw();
         return true;
     }
 
+    // Otherwise full-screen programs that leave the mouse alone (less, man)
+    // get the wheel as arrow keys
+    let mode = shell.terminal().mode();
+    if let Some(keys) = alternate_scroll_keys(mode, delta_y as i32) {
+        shell.send_input(&keys);
+        return true;
+    }
+
     false
 }
 
+/// Arrow keys for a wheel scroll of `lines` in alternate scroll mode (1007)
+///
+/// Only applies while the alternate screen is showing, where there's no
+/// scrollback to move through. Positive `lines` scroll up.
+pub fn alternate_scroll_keys(mode: TermMode, lines: i32) -> Option<Vec<u8>> {
+    if lines == 0 || !mode.contains(TermMode::ALT_SCREEN | TermMode::ALTERNATE_SCROLL) {
+        return None;
+    }
+    let prefix = if mode.contains(TermMode::APP_CURSOR) {
+        "\x1bO"
+    } else {
+        "\x1b["
+    };
+    let arrow = if lines > 0 { 'A' } else { 'B' };
+    let keys = format!("{prefix}{arrow}").repeat(lines.unsigned_abs() as usize);
+    Some(keys.into_bytes())
+}
+
 /// Clear terminal selection (e.g., when user types or presses Escape)
 pub fn clear_terminal_selection(state: &mut WindowState) {
     let tab_id = state.gpu.tab_bar.active_tab_id();
@@ -1406,9 +1796,13 @@ pub fn scroll_to_current_match(state: &mut WindowState) {
     let shell = active_tab_id.and_then(|id| state.shells.get_mut(&id));
     let Some(shell) = shell else { return };
 
-    let terminal = shell.terminal();
-    let screen_lines = terminal.screen_lines() as i32;
-    let display_offset = terminal.display_offset() as i32;
+    let (screen_lines, display_offset) = {
+        let terminal = shell.terminal();
+        (
+            terminal.screen_lines() as i32,
+            terminal.display_offset() as i32,
+        )
+    };
 
     // Calculate viewport line (what line would the match be at in current viewport)
     // viewport_line = grid_line + display_offset
@@ -1435,48 +1829,71 @@ pub fn scroll_to_current_match(state: &mut WindowState) {
     }
 }
 
-/// Update search matches based on current query
+/// Update search matches based on current query and options
+///
+/// Compiles the query and searches the whole terminal, then scrolls to the
+/// first match.
 pub fn update_search_matches(state: &mut WindowState) {
-    use crate::window::SearchMatch;
-
-    state.ui.search.matches.clear();
-    state.ui.search.current_match = 0;
+    let search = &mut state.ui.search;
+    search.search = if search.query.is_empty() {
+        None
+    } else {
+        Search::new(&search.query, search.options)
+    };
+    search.matches.clear();
+    search.current_match = 0;
+    refresh_search_matches(state);
 
-    let query = &state.ui.search.query;
-    if query.is_empty() {
-        return;
+    // Scroll to first match if any found
+    if !state.ui.search.matches.is_empty() {
+        scroll_to_current_match(state);
     }
+}
 
-    // Get active shell's terminal content
+/// Find the query's matches again after the text moved or new output
+/// arrived, leaving the view where it is
+///
+/// Only lines that may have changed are searched again, and the current
+/// match keeps its place as older matches leave history. Returns whether
+/// the matches changed.
+pub fn refresh_search_matches(state: &mut WindowState) -> bool {
     let active_tab_id = state.gpu.tab_bar.active_tab_id();
-    let shell = active_tab_id.and_then(|id| state.shells.get(&id));
-    let Some(shell) = shell else { return };
-
-    let terminal = shell.terminal();
-
-    // Get all lines including history
-    let all_lines = terminal.all_lines_text();
-
-    // Search each line for the query (case-insensitive)
-    let query_lower = query.to_lowercase();
-    for (line_idx, line_text) in &all_lines {
-        let line_lower = line_text.to_lowercase();
-        let mut start = 0;
-        while let Some(pos) = line_lower[start..].find(&query_lower) {
-            let match_start = start + pos;
-            state.ui.search.matches.push(SearchMatch {
-                line: *line_idx,
-                start_col: match_start,
-                end_col: match_start + query.len(),
-            });
-            start = match_start + 1;
+    let search = &mut state.ui.search;
+    // Matches found in another tab are no use here
+    if search.tab_id != active_tab_id {
+        search.tab_id = active_tab_id;
+        if search.search.is_some() {
+            search.search = Search::new(&search.query, search.options);
         }
     }
 
-    // Scroll to first match if any found
-    if !state.ui.search.matches.is_empty() {
-        scroll_to_current_match(state);
-    }
+    let shell = active_tab_id.and_then(|id| state.shells.get(&id));
+    let (Some(shell), Some(compiled)) = (shell, search.search.as_mut()) else {
+        let changed = !search.matches.is_empty();
+        search.matches.clear();
+        return changed;
+    };
+    let matches: Vec<SearchMatch> = {
+        let terminal = shell.terminal();
+        let dropped = terminal.update_search(compiled);
+        search.current_match = search.current_match.saturating_sub(dropped);
+        terminal
+            .search_matches(compiled)
+            .into_iter()
+            .map(|found| SearchMatch {
+                line: found.start().line.0,
+                start_col: found.start().column.0,
+                end_line: found.end().line.0,
+                end_col: found.end().column.0 + 1,
+            })
+            .collect()
+    };
+
+    let last = matches.len().saturating_sub(1);
+    search.current_match = search.current_match.min(last);
+    let changed = matches != search.matches;
+    search.matches = matches;
+    changed
 }
 
 #[cfg(test)]
@@ -2128,51 +2545,162 @@ mod tests {
 
     // ── Mouse report protocol tests ────────────────────────────────
 
+    fn cell(col: usize, line: usize) -> MousePosition {
+        MousePosition {
+            col,
+            line,
+            ..MousePosition::default()
+        }
+    }
+
     #[test]
     fn mouse_report_sgr_press() {
-        let seq = mouse_report(0, 5, 10, true, true);
+        let seq = mouse_report(0, cell(5, 10), true, MouseEncoding::Sgr).unwrap();
         let expected = "\x1b[<0;6;11M"; // SGR: 1-indexed, M for press
         assert_eq!(String::from_utf8(seq).unwrap(), expected);
     }
 
     #[test]
     fn mouse_report_sgr_release() {
-        let seq = mouse_report(0, 5, 10, false, true);
+        let seq = mouse_report(0, cell(5, 10), false, MouseEncoding::Sgr).unwrap();
         let expected = "\x1b[<0;6;11m"; // SGR: lowercase m for release
         assert_eq!(String::from_utf8(seq).unwrap(), expected);
     }
 
     #[test]
     fn mouse_report_legacy_press() {
-        let seq = mouse_report(0, 0, 0, true, false);
+        let seq = mouse_report(0, cell(0, 0), true, MouseEncoding::Normal).unwrap();
         // Legacy: ESC [ M <btn+32> <col+33> <row+33>
         assert_eq!(seq, vec![0x1b, b'[', b'M', 32, 33, 33]);
     }
 
     #[test]
     fn mouse_report_legacy_release() {
-        let seq = mouse_report(MOUSE_BUTTON_RELEASE, 0, 0, false, false);
-        assert_eq!(seq, vec![0x1b, b'[', b'M', 35, 33, 33]); // 3+32=35
+        // Any button is released as button 3, keeping modifier bits
+        let release = |button| mouse_report(button, cell(0, 0), false, MouseEncoding::Normal);
+        let middle = release(MOUSE_BUTTON_MIDDLE).unwrap();
+        assert_eq!(middle, vec![0x1b, b'[', b'M', 35, 33, 33]); // 3+32=35
+        let ctrl = release(MOUSE_MOD_CTRL).unwrap();
+        assert_eq!(ctrl, vec![0x1b, b'[', b'M', 51, 33, 33]); // 3+16+32=51
     }
 
     #[test]
     fn mouse_report_scroll_buttons() {
-        let up = mouse_report(MOUSE_BUTTON_SCROLL_UP, 10, 5, true, true);
-        let down = mouse_report(MOUSE_BUTTON_SCROLL_DOWN, 10, 5, true, true);
+        let scroll = |button| mouse_report(button, cell(10, 5), true, MouseEncoding::Sgr);
+        let up = scroll(MOUSE_BUTTON_SCROLL_UP);
+        let down = scroll(MOUSE_BUTTON_SCROLL_DOWN);
         assert_ne!(up, down);
-        let up_str = String::from_utf8(up).unwrap();
+        let up_str = String::from_utf8(up.unwrap()).unwrap();
         assert!(up_str.contains("<64;")); // scroll up = 64
-        let down_str = String::from_utf8(down).unwrap();
+        let down_str = String::from_utf8(down.unwrap()).unwrap();
         assert!(down_str.contains("<65;")); // scroll down = 65
     }
 
     #[test]
-    fn mouse_report_legacy_clamps_coordinates() {
-        // Legacy mode clamps to 255
-        let seq = mouse_report(0, 300, 300, true, false);
-        // col+33 and row+33 should be clamped to 255
-        assert_eq!(seq[4], 255);
-        assert_eq!(seq[5], 255);
+    fn mouse_report_legacy_drops_unencodable_coordinates() {
+        // Column 223 is the last a byte can carry
+        let press = |col, line| mouse_report(0, cell(col, line), true, MouseEncoding::Normal);
+        assert_eq!(press(222, 0).unwrap()[4], 255);
+        assert_eq!(press(223, 0), None);
+        assert_eq!(press(0, 300), None);
+    }
+
+    #[test]
+    fn mouse_report_utf8_encodes_large_coordinates() {
+        let press = |col| mouse_report(0, cell(col, 0), true, MouseEncoding::Utf8);
+        // 301 + 32 = 333 = U+014D
+        let seq = String::from_utf8(press(300).unwrap()).unwrap();
+        assert_eq!(seq, "\x1b[M \u{14d}!");
+        assert!(press(2014).is_some());
+        assert_eq!(press(2015), None);
+    }
+
+    #[test]
+    fn mouse_report_urxvt() {
+        let press = mouse_report(0, cell(299, 9), true, MouseEncoding::Urxvt).unwrap();
+        assert_eq!(String::from_utf8(press).unwrap(), "\x1b[32;300;10M");
+        let release = mouse_report(0, cell(299, 9), false, MouseEncoding::Urxvt).unwrap();
+        assert_eq!(String::from_utf8(release).unwrap(), "\x1b[35;300;10M");
+    }
+
+    #[test]
+    fn mouse_report_sgr_pixels() {
+        let position = MousePosition {
+            col: 2,
+            line: 1,
+            x: 25,
+            y: 40,
+        };
+        let seq = mouse_report(0, position, false, MouseEncoding::SgrPixels).unwrap();
+        assert_eq!(String::from_utf8(seq).unwrap(), "\x1b[<0;26;41m");
+    }
+
+    #[test]
+    fn mouse_protocol_prefers_sgr() {
+        let modes = MouseModes {
+            urxvt: true,
+            ..MouseModes::default()
+        };
+        let encoding = |mode, modes| MouseProtocol::new(mode, modes).encoding;
+        let mode = TermMode::MOUSE_REPORT_CLICK | TermMode::UTF8_MOUSE;
+        assert_eq!(encoding(mode, modes), MouseEncoding::Urxvt);
+        let mode = mode | TermMode::SGR_MOUSE;
+        assert_eq!(encoding(mode, modes), MouseEncoding::Sgr);
+        let modes = MouseModes {
+            sgr_pixels: true,
+            ..modes
+        };
+        assert_eq!(encoding(mode, modes), MouseEncoding::SgrPixels);
+        let none = MouseProtocol::new(TermMode::SGR_MOUSE, MouseModes::default());
+        assert!(!none.reports_buttons());
+    }
+
+    #[test]
+    fn mouse_protocol_adds_modifier_bits() {
+        let protocol = MouseProtocol::new(
+            TermMode::MOUSE_MOTION | TermMode::SGR_MOUSE,
+            MouseModes::default(),
+        );
+        let mods = ModifiersState::SHIFT | ModifiersState::CONTROL;
+        let wheel = protocol.report(MOUSE_BUTTON_SCROLL_UP, mods, cell(0, 0), true);
+        assert_eq!(wheel.as_deref(), Some(&b"\x1b[<84;1;1M"[..])); // 64+4+16
+        let motion = MOUSE_BUTTON_MOTION + MOUSE_BUTTON_RELEASE;
+        let moved = protocol.report(motion, ModifiersState::ALT, cell(0, 0), true);
+        assert_eq!(moved.as_deref(), Some(&b"\x1b[<43;1;1M"[..])); // 35+8
+    }
+
+    #[test]
+    fn mouse_protocol_x10_reports_presses_only() {
+        let modes = MouseModes {
+            x10: true,
+            ..MouseModes::default()
+        };
+        let protocol = MouseProtocol::new(TermMode::NONE, modes);
+        assert!(protocol.reports_buttons());
+        assert!(!protocol.reports_motion(true));
+        let ctrl = ModifiersState::CONTROL;
+        let middle = |pressed| protocol.report(MOUSE_BUTTON_MIDDLE, ctrl, cell(0, 0), pressed);
+        assert_eq!(middle(true), Some(vec![0x1b, b'[', b'M', 33, 33, 33]));
+        assert_eq!(middle(false), None);
+    }
+
+    #[test]
+    fn alternate_scroll_sends_arrows_on_alt_screen() {
+        let mode = TermMode::ALT_SCREEN | TermMode::ALTERNATE_SCROLL;
+        assert_eq!(
+            alternate_scroll_keys(mode, 3),
+            Some(b"\x1b[A\x1b[A\x1b[A".to_vec())
+        );
+        assert_eq!(alternate_scroll_keys(mode, -1), Some(b"\x1b[B".to_vec()));
+        assert_eq!(
+            alternate_scroll_keys(mode | TermMode::APP_CURSOR, 1),
+            Some(b"\x1bOA".to_vec())
+        );
+        assert_eq!(alternate_scroll_keys(mode, 0), None);
+
+        // The primary screen scrolls its history; the mode can be turned off
+        assert_eq!(alternate_scroll_keys(TermMode::ALTERNATE_SCROLL, 3), None);
+        assert_eq!(alternate_scroll_keys(TermMode::ALT_SCREEN, 3), None);
     }
 
     // ── URL merge tests ────────────────────────────────────────────
@@ -2215,4 +2743,101 @@ mod tests {
         // Should NOT merge — next line starts with https://
         assert_eq!(urls[0].end_line, 0);
     }
+
+    // ── OSC 8 hyperlink tests ──────────────────────────────────────
+
+    const LINK: &str = "https://a";
+
+    fn spans_for(cells: &[(&str, usize, usize)], cols: usize) -> Vec<DetectedUrl> {
+        let mut spans = Vec::new();
+        for &(uri, col, line) in cells {
+            extend_hyperlink_spans(&mut spans, uri, col, line, cols);
+        }
+        spans
+    }
+
+    #[test]
+    fn hyperlink_cells_join_into_one_span() {
+        let cells = [(LINK, 2, 0), (LINK, 3, 0), (LINK, 4, 0)];
+        let spans = spans_for(&cells, 80);
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].start_col, spans[0].end_col), (2, 5));
+        assert!(spans[0].explicit);
+    }
+
+    #[test]
+    fn hyperlink_span_continues_across_wrap() {
+        let cells = [(LINK, 8, 0), (LINK, 9, 0), (LINK, 0, 1)];
+        let spans = spans_for(&cells, 10);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].line, 0);
+        assert_eq!((spans[0].end_line, spans[0].end_col), (1, 1));
+    }
+
+    #[test]
+    fn hyperlink_gap_or_new_uri_starts_new_span() {
+        let cells = [(LINK, 0, 0), (LINK, 2, 0), ("https://b", 3, 0)];
+        let spans = spans_for(&cells, 80);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[2].url, "https://b");
+    }
+
+    #[test]
+    fn hyperlinks_replace_overlapping_detected_urls() {
+        let mut urls = detect_urls_in_line("see https://shown.com and https://other.com", 0);
+        let links = spans_for(&[("https://target", 4, 0), ("https://target", 5, 0)], 80);
+        merge_hyperlinks(&mut urls, links);
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].url, "https://target");
+        assert_eq!(urls[1].url, "https://other.com");
+        assert!(urls.iter().all(|u| u.url != "https://shown.com"));
+    }
+
+    // ── file:// URI tests ──────────────────────────────────────────
+
+    #[test]
+    fn file_uri_with_empty_or_local_host() {
+        let expected = Some((PathBuf::from("/tmp/a.rs"), None, None));
+        assert_eq!(parse_file_uri("file:///tmp/a.rs", None), expected);
+        assert_eq!(parse_file_uri("file://localhost/tmp/a.rs", None), expected);
+        assert_eq!(parse_file_uri("file://box/tmp/a.rs", Some("box")), expected);
+    }
+
+    #[test]
+    fn file_uri_on_other_host_is_not_local() {
+        assert_eq!(parse_file_uri("file://remote/tmp/a.rs", Some("box")), None);
+        assert_eq!(parse_file_uri("https://example.com/a.rs", None), None);
+    }
+
+    #[test]
+    fn only_web_and_mail_links_open() {
+        assert!(is_openable_url("https://example.com/a"));
+        assert!(is_openable_url("HTTP://example.com"));
+        assert!(is_openable_url("mailto:dev@example.com"));
+        assert!(is_openable_url("www.example.com/page"));
+        assert!(!is_openable_url("file://remote/tmp/a.rs"));
+        assert!(!is_openable_url("smb://server/share"));
+        assert!(!is_openable_url("javascript:alert(1)"));
+        assert!(!is_openable_url("no-scheme"));
+    }
+
+    #[test]
+    fn file_uri_line_numbers() {
+        assert_eq!(
+            parse_file_uri("file:///src/main.rs:42:7", None),
+            Some((PathBuf::from("/src/main.rs"), Some(42), Some(7)))
+        );
+        assert_eq!(
+            parse_file_uri("file:///src/main.rs#L12", None),
+            Some((PathBuf::from("/src/main.rs"), Some(12), None))
+        );
+    }
+
+    #[test]
+    fn file_uri_percent_decoded() {
+        assert_eq!(
+            parse_file_uri("file:///tmp/my%20notes.txt", None),
+            Some((PathBuf::from("/tmp/my notes.txt"), None, None))
+        );
+    }
 }
@@ -4,6 +4,7 @@
 //!
 //! Pure decision functions (no side effects, fully testable):
 //! - `screen_to_grid_position` — pixel-to-cell coordinate conversion
+//! - `gutter_line_at` — which line a click in the left padding is beside
 //! - `determine_click_target` — decides what a mouse click hits
 //! - `compute_click_count` — multi-click detection (single/double/triple)
 //! - `normalize_scroll_delta` — converts pixel scroll delta to line count
@@ -15,12 +16,13 @@ use winit::event::{ElementState, Modifiers, MouseButton, MouseScrollDelta};
 
 use crate::window::{ContextMenuItem, WindowState};
 
+use super::commands::{command_output_at, copy_output_of_command, toggle_command_fold_at};
 use super::{
     DetectedPath, DetectedUrl, MOUSE_BUTTON_LEFT, MOUSE_BUTTON_MIDDLE, MOUSE_BUTTON_RIGHT,
     find_path_at_position, find_path_index_at_position, find_url_at_position,
     find_url_index_at_position, get_clipboard_content, get_terminal_selection_text,
     handle_tab_click, handle_terminal_mouse_button, handle_terminal_mouse_move,
-    handle_terminal_mouse_release, handle_terminal_scroll, open_file, open_url, paste_to_terminal,
+    handle_terminal_mouse_release, handle_terminal_scroll, open_file, open_link, paste_to_terminal,
     set_clipboard_content,
 };
 
@@ -67,6 +69,20 @@ pub fn screen_to_grid_position(x: f32, y: f32, layout: &GridLayout) -> Option<(u
     Some((col, line))
 }
 
+/// Viewport line beside a point in the left padding (the gutter), or `None`
+/// if the point isn't in the gutter.
+pub fn gutter_line_at(x: f32, y: f32, layout: &GridLayout) -> Option<usize> {
+    let rel_x = x - layout.content_offset_x;
+    let rel_y = y - layout.content_offset_y - layout.padding;
+
+    if !(0.0..layout.padding).contains(&rel_x) || rel_y < 0.0 {
+        return None;
+    }
+
+    let line = (rel_y / layout.line_height) as usize;
+    (line < layout.max_rows).then_some(line)
+}
+
 /// What a mouse click targets
 #[derive(Debug, Clone, PartialEq)]
 pub enum MouseClickTarget {
@@ -224,7 +240,7 @@ pub fn normalize_scroll_delta(delta: &MouseScrollDelta, line_height: f32) -> f32
 /// Handle cursor moved event
 ///
 /// Updates cursor position, context menu hover, URL hover, and selection drag.
-pub fn handle_cursor_moved(state: &mut WindowState, x: f32, y: f32) {
+pub fn handle_cursor_moved(state: &mut WindowState, x: f32, y: f32, modifiers: &Modifiers) {
     state.interaction.cursor_position = (x, y);
 
     // Update context menu hover state
@@ -238,7 +254,7 @@ pub fn handle_cursor_moved(state: &mut WindowState, x: f32, y: f32) {
     }
 
     // Update selection if dragging
-    handle_terminal_mouse_move(state, x, y);
+    handle_terminal_mouse_move(state, x, y, modifiers.state());
 
     // Check for URL / file-path hover and update underline state
     let layout = grid_layout_from_state(state);
@@ -305,8 +321,7 @@ pub fn handle_mouse_input(
             };
             if let Some((url, path)) = link {
                 if let Some(url) = url {
-                    log::info!("Opening URL: {}", url);
-                    open_url(&url);
+                    open_link(&url, open_file_command);
                     return true;
                 }
                 if let Some((token, target_line, target_col)) = path {
@@ -359,6 +374,7 @@ pub fn handle_mouse_input(
             }
             (MouseButton::Right, ElementState::Pressed) => {
                 // Right-click while menu is open moves the menu
+                state.ui.context_menu.command = command_output_at(state, x, y);
                 state.ui.context_menu.show(x, y);
                 state.render.dirty = true;
                 state.window.request_redraw();
@@ -370,12 +386,22 @@ pub fn handle_mouse_input(
 
     // Right-click shows context menu
     if button == MouseButton::Right && button_state == ElementState::Pressed {
+        state.ui.context_menu.command = command_output_at(state, x, y);
         state.ui.context_menu.show(x, y);
         state.render.dirty = true;
         state.window.request_redraw();
         return true;
     }
 
+    // Left-click in the gutter folds or unfolds the command beside it
+    if button == MouseButton::Left
+        && button_state == ElementState::Pressed
+        && let Some(line) = gutter_line_at(x, y, &grid_layout_from_state(state))
+        && toggle_command_fold_at(state, line)
+    {
+        return true;
+    }
+
     let mouse_button = match button {
         MouseButton::Left => Some(MOUSE_BUTTON_LEFT),
         MouseButton::Middle => Some(MOUSE_BUTTON_MIDDLE),
@@ -387,14 +413,15 @@ pub fn handle_mouse_input(
         match button_state {
             ElementState::Pressed => {
                 // Try terminal (mouse reporting or selection) first, then tab bar
-                if !handle_terminal_mouse_button(state, x, y, Instant::now(), btn, true)
+                let mods = modifiers.state();
+                if !handle_terminal_mouse_button(state, x, y, Instant::now(), btn, true, mods)
                     && btn == MOUSE_BUTTON_LEFT
                 {
                     handle_tab_click(state, x, y, Instant::now());
                 }
             }
             ElementState::Released => {
-                handle_terminal_mouse_release(state, x, y);
+                handle_terminal_mouse_release(state, x, y, btn, modifiers.state());
             }
         }
     }
@@ -403,13 +430,13 @@ pub fn handle_mouse_input(
 }
 
 /// Handle mouse wheel event
-pub fn handle_mouse_wheel(state: &mut WindowState, delta: MouseScrollDelta) {
+pub fn handle_mouse_wheel(state: &mut WindowState, delta: MouseScrollDelta, modifiers: &Modifiers) {
     let (x, y) = state.interaction.cursor_position;
     let line_height = state.gpu.glyph_cache.line_height();
     let delta_y = normalize_scroll_delta(&delta, line_height);
 
     // Check if mouse reporting should handle this
-    if handle_terminal_scroll(state, x, y, delta_y) {
+    if handle_terminal_scroll(state, x, y, delta_y, modifiers.state()) {
         // Mouse reporting handled the scroll
         return;
     }
@@ -445,7 +472,7 @@ fn grid_layout_from_state(state: &WindowState) -> GridLayout {
     }
 }
 
-/// Handle context menu action (copy, paste, select all)
+/// Handle context menu action (copy, paste, select all, copy command output)
 pub(super) fn handle_context_menu_action(state: &mut WindowState, item: ContextMenuItem) {
     match item {
         ContextMenuItem::Copy => {
@@ -454,6 +481,11 @@ pub(super) fn handle_context_menu_action(state: &mut WindowState, item: ContextM
                 state.ui.copy_indicator.trigger();
             }
         }
+        ContextMenuItem::CopyCommandOutput => {
+            if let Some(id) = state.ui.context_menu.command {
+                copy_output_of_command(state, id);
+            }
+        }
         ContextMenuItem::Paste => {
             if let Some(content) = get_clipboard_content() {
                 paste_to_terminal(state, &content);
@@ -465,7 +497,7 @@ pub(super) fn handle_context_menu_action(state: &mut WindowState, item: ContextM
                 && let Some(shell) = state.shells.get_mut(&tab_id)
             {
                 use crt_core::{Column, Line, Point, SelectionType};
-                let terminal = shell.terminal_mut();
+                let mut terminal = shell.terminal_mut();
                 let screen_lines = terminal.screen_lines();
                 let columns = terminal.columns();
 
@@ -549,6 +581,17 @@ mod tests {
         assert_eq!(pos, Some((5, 23)));
     }
 
+    #[test]
+    fn gutter_line_beside_text_rows() {
+        let layout = test_layout();
+        // In the padding, level with the sixth row
+        assert_eq!(gutter_line_at(5.0, 120.0, &layout), Some(5));
+        // Over the text, above the rows, or below the last one
+        assert_eq!(gutter_line_at(12.0, 120.0, &layout), None);
+        assert_eq!(gutter_line_at(5.0, 35.0, &layout), None);
+        assert_eq!(gutter_line_at(5.0, 40.0 + 24.0 * 16.0, &layout), None);
+    }
+
     // ── compute_click_count tests ──────────────────────────────────────
 
     #[test]
@@ -653,6 +696,7 @@ mod tests {
             end_col: end,
             line: 0,
             end_line: 0,
+            explicit: false,
         }
     }
 
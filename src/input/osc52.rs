@@ -0,0 +1,181 @@
+//! OSC 52 clipboard access
+//!
+//! Programs (tmux, neovim over ssh) read and write the system clipboard with
+//! OSC 52. crt-core hands every request to us; this module applies the
+//! configured `[clipboard]` policy before touching the clipboard.
+
+use std::sync::Arc;
+
+use crt_core::ClipboardRequest;
+
+use crate::config::{ClipboardConfig, Osc52Policy};
+use crate::window::{ConfirmAction, TabId, ToastType, WindowState};
+
+use super::{get_clipboard_content, set_clipboard_content};
+
+/// What to do with a single OSC 52 request under the active policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Osc52Decision {
+    /// Write the payload to the clipboard
+    Store,
+    /// Answer the read with the clipboard contents
+    Answer,
+    /// Ask the user before answering the read
+    Prompt,
+    /// Payload exceeds `max_osc52_bytes`
+    TooLarge,
+    /// Policy forbids this direction
+    Deny,
+}
+
+/// Decide how to handle a clipboard request.
+///
+/// Pure function — the caller performs the clipboard access.
+pub fn decide_osc52(config: &ClipboardConfig, request: &ClipboardRequest) -> Osc52Decision {
+    match request {
+        ClipboardRequest::Store { text, .. } => {
+            if !config.osc52.allows_write() {
+                Osc52Decision::Deny
+            } else if text.len() > config.max_osc52_bytes {
+                Osc52Decision::TooLarge
+            } else {
+                Osc52Decision::Store
+            }
+        }
+        ClipboardRequest::Load { .. } => match config.osc52 {
+            Osc52Policy::ReadWrite => Osc52Decision::Answer,
+            Osc52Policy::Prompt => Osc52Decision::Prompt,
+            Osc52Policy::WriteOnly | Osc52Policy::Deny => Osc52Decision::Deny,
+        },
+    }
+}
+
+/// Apply the clipboard policy to requests received from the shell in `tab_id`.
+pub fn handle_clipboard_requests(
+    state: &mut WindowState,
+    tab_id: TabId,
+    requests: Vec<ClipboardRequest>,
+) {
+    for request in requests {
+        let decision = decide_osc52(&state.clipboard, &request);
+        log::debug!("OSC 52 {:?} -> {:?}", request, decision);
+
+        match (request, decision) {
+            (ClipboardRequest::Store { text, .. }, Osc52Decision::Store) => {
+                set_clipboard_content(&text);
+                state.ui.toast.show(
+                    format!("Program copied {} bytes to the clipboard", text.len()),
+                    ToastType::Info,
+                );
+            }
+            (ClipboardRequest::Load { formatter, .. }, Osc52Decision::Answer) => {
+                answer_clipboard_read(state, tab_id, &formatter);
+            }
+            (ClipboardRequest::Load { formatter, .. }, Osc52Decision::Prompt) => {
+                if state.ui.confirm.is_active() {
+                    log::debug!("OSC 52 read ignored: another prompt is pending");
+                    continue;
+                }
+                state.ui.confirm.show(
+                    "Allow program to read the clipboard?",
+                    ConfirmAction::ClipboardRead { tab_id, formatter },
+                );
+            }
+            (_, Osc52Decision::TooLarge) => {
+                state.ui.toast.show(
+                    format!(
+                        "Blocked clipboard write larger than {} bytes",
+                        state.clipboard.max_osc52_bytes
+                    ),
+                    ToastType::Warning,
+                );
+            }
+            _ => {}
+        }
+        state.render.dirty = true;
+    }
+}
+
+/// Send the clipboard contents to the program in `tab_id` as an OSC 52 reply.
+pub fn answer_clipboard_read(
+    state: &mut WindowState,
+    tab_id: TabId,
+    formatter: &Arc<dyn Fn(&str) -> String + Sync + Send>,
+) {
+    let text = get_clipboard_content().unwrap_or_default();
+    if text.len() > state.clipboard.max_osc52_bytes {
+        state.ui.toast.show(
+            format!(
+                "Blocked clipboard read larger than {} bytes",
+                state.clipboard.max_osc52_bytes
+            ),
+            ToastType::Warning,
+        );
+        return;
+    }
+    if let Some(shell) = state.shells.get(&tab_id) {
+        shell.send_input(formatter(&text).as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crt_core::ClipboardType;
+
+    fn store(len: usize) -> ClipboardRequest {
+        ClipboardRequest::Store {
+            clipboard: ClipboardType::Clipboard,
+            text: "x".repeat(len),
+        }
+    }
+
+    fn load() -> ClipboardRequest {
+        ClipboardRequest::Load {
+            clipboard: ClipboardType::Clipboard,
+            formatter: Arc::new(|text| text.to_string()),
+        }
+    }
+
+    fn config(osc52: Osc52Policy) -> ClipboardConfig {
+        ClipboardConfig {
+            osc52,
+            max_osc52_bytes: 16,
+        }
+    }
+
+    #[test]
+    fn write_only_allows_store_denies_load() {
+        let config = config(Osc52Policy::WriteOnly);
+        assert_eq!(decide_osc52(&config, &store(4)), Osc52Decision::Store);
+        assert_eq!(decide_osc52(&config, &load()), Osc52Decision::Deny);
+    }
+
+    #[test]
+    fn read_write_answers_load() {
+        let config = config(Osc52Policy::ReadWrite);
+        assert_eq!(decide_osc52(&config, &store(4)), Osc52Decision::Store);
+        assert_eq!(decide_osc52(&config, &load()), Osc52Decision::Answer);
+    }
+
+    #[test]
+    fn prompt_asks_before_load() {
+        let config = config(Osc52Policy::Prompt);
+        assert_eq!(decide_osc52(&config, &store(4)), Osc52Decision::Store);
+        assert_eq!(decide_osc52(&config, &load()), Osc52Decision::Prompt);
+    }
+
+    #[test]
+    fn deny_blocks_both_directions() {
+        let config = config(Osc52Policy::Deny);
+        assert_eq!(decide_osc52(&config, &store(4)), Osc52Decision::Deny);
+        assert_eq!(decide_osc52(&config, &load()), Osc52Decision::Deny);
+    }
+
+    #[test]
+    fn oversized_store_rejected() {
+        let config = config(Osc52Policy::ReadWrite);
+        assert_eq!(decide_osc52(&config, &store(16)), Osc52Decision::Store);
+        assert_eq!(decide_osc52(&config, &store(17)), Osc52Decision::TooLarge);
+    }
+}
@@ -39,9 +39,11 @@ fn main() {
     // Initialize profiling (enabled via CRT_PROFILE=1)
     profiling::init();
 
+    // Sleep until there's input, shell output, or a frame due (see app::scheduler)
     let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(ControlFlow::Poll);
-    event_loop.run_app(&mut app::App::new()).unwrap();
+    event_loop.set_control_flow(ControlFlow::Wait);
+    let proxy = event_loop.create_proxy();
+    event_loop.run_app(&mut app::App::new(proxy)).unwrap();
 
     // Flush profiling data on exit
     profiling::shutdown();
@@ -247,6 +247,8 @@ pub struct FrameTiming {
     pub render_us: u64,
     pub present_us: u64,
     pub effects_us: u64,
+    /// Terminal lines re-shaped by the text update (0 when nothing changed)
+    pub lines_rebuilt: u64,
 }
 
 /// Grid snapshot for debugging terminal state
@@ -307,10 +309,15 @@ impl TimingStats {
     }
 
     fn avg_ms(&self) -> f64 {
+        self.avg() / 1000.0
+    }
+
+    /// Average of the raw samples (for counts rather than durations)
+    fn avg(&self) -> f64 {
         if self.count == 0 {
             return 0.0;
         }
-        (self.total_us as f64 / self.count as f64) / 1000.0
+        self.total_us as f64 / self.count as f64
     }
 
     fn percentile(&self, p: f64) -> f64 {
@@ -344,6 +351,8 @@ struct Profiler {
     render_stats: TimingStats,
     present_stats: TimingStats,
     effects_stats: TimingStats,
+    /// Lines rebuilt per frame (a count, stored in the `_us` fields)
+    lines_rebuilt_stats: TimingStats,
 
     // Subsystem timing
     subsystem_stats: std::collections::HashMap<&'static str, TimingStats>,
@@ -392,6 +401,7 @@ impl Profiler {
             render_stats: TimingStats::new(),
             present_stats: TimingStats::new(),
             effects_stats: TimingStats::new(),
+            lines_rebuilt_stats: TimingStats::new(),
             subsystem_stats: std::collections::HashMap::new(),
             memory_samples: Vec::new(),
             last_memory_sample: Instant::now(),
@@ -465,16 +475,18 @@ impl Profiler {
         self.render_stats.record(timing.render_us);
         self.present_stats.record(timing.present_us);
         self.effects_stats.record(timing.effects_us);
+        self.lines_rebuilt_stats.record(timing.lines_rebuilt);
 
         // Log slow frames (>16ms = below 60fps)
         if timing.total_us > 16000 {
             self.write(&format!(
-                "SLOW FRAME: total={:.2}ms update={:.2}ms render={:.2}ms present={:.2}ms effects={:.2}ms",
+                "SLOW FRAME: total={:.2}ms update={:.2}ms render={:.2}ms present={:.2}ms effects={:.2}ms lines_rebuilt={}",
                 timing.total_us as f64 / 1000.0,
                 timing.update_us as f64 / 1000.0,
                 timing.render_us as f64 / 1000.0,
                 timing.present_us as f64 / 1000.0,
                 timing.effects_us as f64 / 1000.0,
+                timing.lines_rebuilt,
             ));
         }
 
@@ -565,11 +577,12 @@ impl Profiler {
         };
 
         self.write(&format!(
-            "STATS: frames={} avg={:.2}ms p99={:.2}ms fps={:.1}",
+            "STATS: frames={} avg={:.2}ms p99={:.2}ms fps={:.1} lines_rebuilt={:.1}/frame",
             self.frame_stats.count,
             self.frame_stats.avg_ms(),
             self.frame_stats.percentile(0.99),
             fps,
+            self.lines_rebuilt_stats.avg(),
         ));
 
         if let Some(sample) = self.memory_samples.last() {
@@ -621,6 +634,12 @@ impl Profiler {
                 self.effects_stats.avg_ms(),
                 self.effects_stats.percentile(0.99)
             ));
+            self.write(&format!(
+                "Lines rebuilt per frame: avg={:.1} max={} total={}",
+                self.lines_rebuilt_stats.avg(),
+                self.lines_rebuilt_stats.max_us,
+                self.lines_rebuilt_stats.total_us
+            ));
         }
 
         if !self.subsystem_stats.is_empty() {
@@ -722,6 +741,18 @@ mod tests {
         assert!((stats.avg_ms() - 2.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_timing_stats_counts() {
+        // Lines-rebuilt counts reuse the same stats, averaged without scaling
+        let mut stats = TimingStats::new();
+        stats.record(0);
+        stats.record(1);
+        stats.record(50);
+
+        assert_eq!(stats.max_us, 50);
+        assert!((stats.avg() - 17.0).abs() < 0.01);
+    }
+
     #[test]
     fn test_scoped_timer_compiles() {
         // Just verify the macro compiles
@@ -21,7 +21,7 @@ pub fn render(
     let padding_x = 12.0 * scale;
     let padding_y = 6.0 * scale;
     let item_height = 24.0 * scale;
-    let menu_width = 160.0 * scale; // Main menu is narrower now
+    let menu_width = 180.0 * scale; // Fits "Copy Command Output"; submenus are wider
 
     // Calculate total height accounting for separators
     let mut menu_height = padding_y * 2.0;
@@ -1,6 +1,6 @@
 //! Dialog rendering
 //!
-//! Renders input dialog overlays: search bar and window rename.
+//! Renders input dialog overlays: search bar, window rename, and confirmation prompts.
 
 use crate::gpu::SharedGpuState;
 use crate::window::WindowState;
@@ -98,17 +98,15 @@ pub fn render_search_bar(
     // Render search text using tab glyph cache
     state.gpu.tab_title_renderer.clear();
 
-    // Build display text: query with cursor + match count
-    let query = &state.ui.search.query;
-    let match_count = state.ui.search.matches.len();
-    let current_match = state.ui.search.current_match + 1; // 1-indexed for display
+    // Build display text: query with cursor + match counter ("3 of 27")
+    let search = &state.ui.search;
+    let query = &search.query;
+    let match_count = search.matches.len();
 
     let display_text = if query.is_empty() {
         "Find...".to_string()
-    } else if match_count > 0 {
-        format!("{}| ({}/{})", query, current_match, match_count)
     } else {
-        format!("{}| (no matches)", query)
+        format!("{}| ({})", query, search.status())
     };
 
     // Render text - get fresh reference to ui_style for text colors
@@ -121,26 +119,48 @@ pub fn render_search_bar(
         ui_style.search_bar.no_match_color.to_array()
     };
 
-    let mut glyphs = Vec::new();
-    let mut char_x = text_x;
+    // Option toggles at the right end, lit when on: case, whole word, regex
+    let on_color = ui_style.search_bar.text_color.to_array();
+    let off_color = ui_style.search_bar.placeholder_color.to_array();
+    let options = search.options;
+    let toggles = [
+        ("Aa", options.case_sensitive),
+        (r"\b", options.whole_word),
+        (".*", options.regex),
+    ];
+
     let font_height = 14.0 * state.scale_factor;
     let text_baseline_y = text_y + (text_height - font_height) / 2.0;
+    let cell_width = state.gpu.tab_glyph_cache.cell_width();
+    let toggles_width = 9.0 * cell_width;
+    let text_right = bar_x + bar_width - border_width - padding;
+
+    // Query and counter, cut short of the toggles
+    let max_chars = ((text_right - toggles_width - text_x) / cell_width).max(0.0) as usize;
+    let shown: String = display_text.chars().take(max_chars).collect();
+    let mut runs = vec![(shown, text_x, text_color)];
+    let mut toggle_x = text_right - toggles_width;
+    for (label, on) in toggles {
+        let color = if on { on_color } else { off_color };
+        runs.push((label.to_string(), toggle_x, color));
+        toggle_x += 3.0 * cell_width;
+    }
 
-    for c in display_text.chars() {
-        if let Some(glyph) = state
-            .gpu
-            .tab_glyph_cache
-            .position_char(c, char_x, text_baseline_y)
-        {
-            glyphs.push(glyph);
+    for (text, x, color) in runs {
+        let mut glyphs = Vec::new();
+        let mut char_x = x;
+        for c in text.chars() {
+            if let Some(glyph) = state
+                .gpu
+                .tab_glyph_cache
+                .position_char(c, char_x, text_baseline_y)
+            {
+                glyphs.push(glyph);
+            }
+            char_x += cell_width;
         }
-        char_x += state.gpu.tab_glyph_cache.cell_width();
+        state.gpu.tab_title_renderer.push_glyphs(&glyphs, color);
     }
-
-    state
-        .gpu
-        .tab_title_renderer
-        .push_glyphs(&glyphs, text_color);
     state.gpu.tab_glyph_cache.flush(&shared.queue);
 
     // Render text pass
@@ -173,6 +193,33 @@ pub fn render_window_rename(
     shared: &mut SharedGpuState,
     encoder: &mut wgpu::CommandEncoder,
     frame_view: &wgpu::TextureView,
+) {
+    let text = format!("{}|", state.ui.window_rename.input);
+    render_labeled_bar(state, shared, encoder, frame_view, "Rename: ", &text);
+}
+
+/// Render confirmation prompt overlay (e.g. OSC 52 clipboard read, closing a busy tab)
+pub fn render_confirm_prompt(
+    state: &mut WindowState,
+    shared: &mut SharedGpuState,
+    encoder: &mut wgpu::CommandEncoder,
+    frame_view: &wgpu::TextureView,
+) {
+    let label = format!("{} ", state.ui.confirm.message);
+    let hint = state.ui.confirm.hint();
+    render_labeled_bar(state, shared, encoder, frame_view, &label, hint);
+}
+
+/// Render a centered bar below the tab bar showing `label` followed by `text`
+///
+/// Shares the rename bar theme colors; the bar widens to fit long text.
+fn render_labeled_bar(
+    state: &mut WindowState,
+    shared: &mut SharedGpuState,
+    encoder: &mut wgpu::CommandEncoder,
+    frame_view: &wgpu::TextureView,
+    label: &str,
+    text: &str,
 ) {
     let (_, content_offset_y) = state.gpu.tab_bar.content_offset();
     let s = state.scale_factor;
@@ -185,13 +232,17 @@ pub fn render_window_rename(
     let focus_glow_color = ui_style.focus.glow_color.to_array();
     let focus_border_color = ui_style.focus.ring_color.to_array();
 
-    // Calculate rename bar dimensions (wider than search bar, centered)
-    let bar_width = 400.0 * s;
+    // Calculate bar dimensions (wider than search bar, centered)
     let bar_height = 36.0 * s;
     let margin = 40.0 * s;
     let padding = 10.0 * s;
     let border_width = ui_style.focus.ring_thickness * s;
     let glow_size = ui_style.focus.glow_size * s;
+    let char_count = label.chars().count() + text.chars().count();
+    let text_width = char_count as f32 * state.gpu.tab_glyph_cache.cell_width();
+    let bar_width = (text_width + (padding + border_width) * 2.0)
+        .max(400.0 * s)
+        .min(state.gpu.config.width as f32);
 
     // Center horizontally
     let bar_x = (state.gpu.config.width as f32 - bar_width) / 2.0;
@@ -232,7 +283,7 @@ pub fn render_window_rename(
     // Render rename bar background directly to frame
     {
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Window Rename Bar Background Pass"),
+            label: Some("Labeled Bar Background Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view: frame_view,
                 resolve_target: None,
@@ -261,9 +312,7 @@ pub fn render_window_rename(
     // Render rename text using tab glyph cache
     state.gpu.tab_title_renderer.clear();
 
-    // Build display text: "Rename: " + input + cursor
-    let input = &state.ui.window_rename.input;
-    let display_text = format!("Rename: {}|", input);
+    let display_text = format!("{}{}", label, text);
 
     // Render text - get fresh reference to ui_style for text colors
     let ui_style = &state.gpu.effect_pipeline.theme().ui;
@@ -274,7 +323,7 @@ pub fn render_window_rename(
     let mut char_x = text_x;
     let font_height = 14.0 * state.scale_factor;
     let text_baseline_y = text_y + (text_height - font_height) / 2.0;
-    let label_len = "Rename: ".len();
+    let label_len = label.chars().count();
 
     for (idx, c) in display_text.chars().enumerate() {
         if let Some(glyph) = state
@@ -286,7 +335,7 @@ pub fn render_window_rename(
         }
 
         // Render label part first, then input part
-        if idx == label_len - 1 {
+        if idx + 1 == label_len {
             // Push label glyphs
             state
                 .gpu
@@ -310,7 +359,7 @@ pub fn render_window_rename(
 
     // Render text pass
     let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-        label: Some("Window Rename Bar Text Render Pass"),
+        label: Some("Labeled Bar Text Render Pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
             view: frame_view,
             resolve_target: None,
@@ -11,8 +11,8 @@ use std::time::Instant;
 
 use crate::gpu::SharedGpuState;
 use crate::profiling::{self, FrameTiming, GridSnapshot};
-use crate::window::{DecorationKind, EffectId, OverrideEventType, WindowState};
-use crt_core::{ShellEvent, ShellTerminal};
+use crate::window::{DecorationKind, EffectId, OverrideEventType, TabId, WindowState, query_color};
+use crt_core::{ClipboardRequest, Colors, Rgb, ShellEvent, ShellTerminal};
 use crt_renderer::EffectConfig;
 use crt_theme::{EventOverride, Theme, ToEffectConfig};
 
@@ -24,19 +24,99 @@ pub struct PtyUpdateResult {
     pub shell_events: Vec<ShellEvent>,
     /// Title change from the shell, if any
     pub title_change: Option<String>,
+    /// OSC 52 clipboard requests awaiting the clipboard policy
+    pub clipboard_requests: Vec<ClipboardRequest>,
+    /// Exit code if the shell exited since the last update
+    pub child_exit: Option<i32>,
 }
 
 /// Process PTY output and collect shell events from a shell terminal.
 ///
 /// This is a pure extraction — no state mutation beyond the shell itself.
 /// The caller is responsible for applying the result to window state.
-pub fn process_pty_updates(shell: &mut ShellTerminal) -> PtyUpdateResult {
+/// Color queries are answered with `color` as they're found, in order with
+/// the terminal's other replies.
+pub fn process_pty_updates(
+    shell: &mut ShellTerminal,
+    color: impl Fn(usize, &Colors) -> Option<Rgb>,
+) -> PtyUpdateResult {
     let content_changed = shell.process_pty_output();
-    let (shell_events, title_change) = shell.take_shell_events();
+    let (shell_events, title_change) = shell.take_shell_events(color);
     PtyUpdateResult {
         content_changed,
         shell_events,
         title_change,
+        clipboard_requests: shell.take_clipboard_requests(),
+        child_exit: shell.take_child_exit(),
+    }
+}
+
+/// Process one shell's PTY output and apply the result to the window.
+///
+/// Replies, titles, OSC 52 requests and exits are handled for every tab;
+/// bell and theme overrides only for the `active` one, since they change
+/// what the whole window shows. Returns whether the shell's content changed.
+fn apply_pty_updates(state: &mut WindowState, tab_id: TabId, active: bool) -> bool {
+    let Some(shell) = state.shells.get_mut(&tab_id) else {
+        return false;
+    };
+    // Color queries are answered from the theme (with any active event overrides)
+    let theme = state.gpu.effect_pipeline.theme();
+    let overrides = &state.ui.overrides;
+    let pty_result = process_pty_updates(shell, |index, runtime| {
+        query_color(index, theme, overrides, runtime)
+    });
+    if pty_result.content_changed && active {
+        // Damage tracking picks out the lines that changed
+        state.render.dirty = true;
+    }
+    if let Some(title) = pty_result.title_change {
+        state.gpu.tab_bar.set_tab_title(tab_id, title);
+        state.render.dirty = true;
+    }
+    if active {
+        // Compute and apply shell event overrides
+        let overrides = compute_shell_event_overrides(&pty_result.shell_events, theme);
+        if overrides.bell_triggered {
+            state.ui.bell.trigger();
+        }
+        if overrides.clear_command_fail {
+            state
+                .ui
+                .overrides
+                .clear_event(OverrideEventType::CommandFail);
+        }
+        for (event_type, properties) in overrides.activations {
+            state.ui.overrides.add(event_type, properties);
+        }
+    }
+    if !pty_result.clipboard_requests.is_empty() {
+        crate::input::handle_clipboard_requests(state, tab_id, pty_result.clipboard_requests);
+    }
+    if let Some(code) = pty_result.child_exit {
+        state.handle_child_exit(tab_id, code);
+    }
+    pty_result.content_changed
+}
+
+/// Process PTY output from the shells `render_frame` won't get to: every
+/// background tab, and the active one too while the window is occluded.
+/// The active tab is also polled for its exit once its PTY has closed.
+///
+/// Called once per event loop iteration so programs in those tabs get their
+/// replies, and their exits are seen, without waiting for the tab to be shown.
+pub fn process_background_shells(state: &mut WindowState) {
+    let active_tab_id = state.gpu.tab_bar.active_tab_id();
+    let tab_ids: Vec<TabId> = state.shells.keys().copied().collect();
+    for tab_id in tab_ids {
+        let active = Some(tab_id) == active_tab_id && !state.render.occluded;
+        if active && !state.shells[&tab_id].awaiting_exit() {
+            continue;
+        }
+        if apply_pty_updates(state, tab_id, active) && !active {
+            // Redraw in full when the tab is next shown
+            state.content_hashes.insert(tab_id, 0);
+        }
     }
 }
 
@@ -183,6 +263,12 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
     let frame_start = Instant::now();
     let mut timing = FrameTiming::default();
     state.render.frame_count = state.render.frame_count.saturating_add(1);
+    state.render.last_frame = Some(frame_start);
+    // Rebuilt below from whatever still needs frames after this one
+    state.render.animating = false;
+    state.render.wake_at = None;
+    // Backdrop animation stops once the window has been idle for a while
+    let backdrop_animating = !state.render.animation_paused;
 
     // Log every 300 frames (~5 seconds at 60fps) or on frame 1
     if state.render.frame_count == 1 || state.render.frame_count.is_multiple_of(300) {
@@ -196,7 +282,7 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
 
     // Periodically reset vello renderer to clean up accumulated texture atlas resources
     // This prevents unbounded GPU memory growth from vello's internal caches
-    // NOTE: The primary memory fix is frame throttling in main.rs (see about_to_wait).
+    // NOTE: The primary memory fix is frame rate capping (see app::scheduler).
     // This reset is a secondary defense against vello atlas accumulation.
     if state
         .render
@@ -211,59 +297,62 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
     const ASSUMED_DT: f32 = 1.0 / 60.0; // ~60fps assumption for animation timestep
     let update_start = Instant::now();
     let dt = ASSUMED_DT;
-    state.gpu.effects_renderer.update(dt);
+    if backdrop_animating {
+        state.gpu.effects_renderer.update(dt);
+    }
     timing.effects_us = update_start.elapsed().as_micros() as u64;
 
     // Keep redrawing if effects are animating
-    if state.gpu.effects_renderer.has_enabled_effects() {
-        state.render.dirty = true;
+    if backdrop_animating && state.gpu.effects_renderer.has_enabled_effects() {
+        state.render.animating = true;
     }
 
     // Keep redrawing if sprite animation is active (uses raw wgpu, no memory growth)
-    if state.gpu.sprite_state.is_some() {
-        state.render.dirty = true;
+    if backdrop_animating && state.gpu.sprite_state.is_some() {
+        state.render.animating = true;
+    }
+
+    // Keep redrawing while CRT flicker is on
+    if backdrop_animating && state.gpu.crt_pipeline.is_animated() {
+        state.render.animating = true;
     }
 
     // Process PTY output from active shell
     let active_tab_id = state.gpu.tab_bar.active_tab_id();
-    if let Some(tab_id) = active_tab_id
-        && let Some(shell) = state.shells.get_mut(&tab_id)
+    let mut in_sync_update = false;
+    let mut content_changed = false;
+    if let Some(tab_id) = active_tab_id {
+        content_changed = apply_pty_updates(state, tab_id, true);
+        in_sync_update = state
+            .shells
+            .get(&tab_id)
+            .is_some_and(|shell| shell.sync_update_deadline().is_some());
+    }
+
+    // Keep search results up to date with new output and the active tab
+    if state.ui.search.active
+        && (content_changed || state.ui.search.tab_id != active_tab_id)
+        && crate::input::refresh_search_matches(state)
     {
-        let pty_result = process_pty_updates(shell);
-        if pty_result.content_changed {
-            state.render.dirty = true;
-            // Invalidate content hash to ensure re-render captures all changes
-            state.content_hashes.insert(tab_id, 0);
-        }
-        if let Some(title) = pty_result.title_change {
-            state.gpu.tab_bar.set_tab_title(tab_id, title);
-        }
-        // Compute and apply shell event overrides
-        let theme = state.gpu.effect_pipeline.theme();
-        let overrides = compute_shell_event_overrides(&pty_result.shell_events, theme);
-        if overrides.bell_triggered {
-            state.ui.bell.trigger();
-        }
-        if overrides.clear_command_fail {
-            state
-                .ui
-                .overrides
-                .clear_event(OverrideEventType::CommandFail);
-        }
-        for (event_type, properties) in overrides.activations {
-            state.ui.overrides.add(event_type, properties);
-        }
+        state.force_active_tab_redraw();
     }
 
     // Keep redrawing while bell flash is active
     if state.ui.bell.is_active() {
-        state.render.dirty = true;
+        state.render.animating = true;
     }
 
-    // Update overrides and keep redrawing while any are active
+    // Drop expired overrides, redrawing without them; the rest need frames
+    // while their flash fades and again when they expire
     if state.ui.overrides.update() {
         state.render.dirty = true;
     }
+    if state.ui.overrides.get_effective_flash().is_some() {
+        state.render.animating = true;
+    }
+    if let Some(expiry) = state.ui.overrides.next_expiry() {
+        state.render.schedule_wake(expiry);
+    }
 
     // Compute and apply effect patches from override state
     let theme = state.gpu.effect_pipeline.theme();
@@ -290,16 +379,21 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
     }
 
     // Keep redrawing while overlay indicators are visible (for fade animation)
-    if state.ui.zoom_indicator.is_visible()
-        || state.ui.copy_indicator.is_visible()
-        || state.ui.toast.is_visible()
-    {
-        state.render.dirty = true;
+    if state.ui.zoom_indicator.is_visible() || state.ui.copy_indicator.is_visible() {
+        state.render.animating = true;
+    }
+    // A toast holds still until it starts fading
+    if state.ui.toast.is_visible() {
+        match state.ui.toast.fade_starts_at() {
+            Some(fade_start) => state.render.schedule_wake(fade_start),
+            None => state.render.animating = true,
+        }
     }
 
     // Force re-renders during first 60 frames
     if state.render.frame_count < 60 {
         state.render.dirty = true;
+        state.render.animating = true;
         if let Some(tab_id) = active_tab_id {
             state.content_hashes.insert(tab_id, 0);
         }
@@ -313,9 +407,13 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
 
     // Update text buffer and get cursor/decoration info
     let text_update_start = Instant::now();
-    let update_result = if state.render.dirty {
+    // Content stays as it was while the program is mid-way through a
+    // synchronized update; `dirty` carries over to the frame after it ends
+    let update_result = if state.render.dirty && !in_sync_update {
         state.render.dirty = false;
-        state.update_text_buffer(shared)
+        let result = state.update_text_buffer(shared);
+        timing.lines_rebuilt = state.render.cached.lines_rebuilt as u64;
+        result
     } else {
         None
     };
@@ -323,7 +421,7 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
 
     // Render backdrop effects to their intermediate texture (if any effects are enabled)
     // This must happen before we create our command encoder since Vello submits its own commands
-    // NOTE: Memory stability depends on frame throttling in main.rs (see about_to_wait)
+    // NOTE: Memory stability depends on frame rate capping (see app::scheduler)
     let effects_render_start = Instant::now();
     let effects_rendered = if state.gpu.effects_renderer.has_enabled_effects() {
         // Ensure Vello renderer is initialized
@@ -449,7 +547,9 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         }
 
         // Update animation state
-        sprite_state.update(dt, width, height);
+        if backdrop_animating {
+            sprite_state.update(dt, width, height);
+        }
 
         // Render sprite
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -471,7 +571,9 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         sprite_state.render(&mut pass, &shared.queue, width, height);
 
         // Keep redrawing while sprite is animated
-        state.render.dirty = true;
+        if backdrop_animating {
+            state.render.animating = true;
+        }
     }
 
     // Pass 1.5: Render background image (if configured)
@@ -486,8 +588,8 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         }
 
         // Keep redrawing for animations
-        if bg_state.image.is_animated() {
-            state.render.dirty = true;
+        if backdrop_animating && bg_state.image.is_animated() {
+            state.render.animating = true;
         }
 
         // Update uniforms with UV transform and opacity
@@ -539,6 +641,9 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
 
     // Update cursor blink state
     state.gpu.terminal_vello.update_blink();
+    if let Some(next_blink) = state.gpu.terminal_vello.next_blink_at() {
+        state.render.schedule_wake(next_blink);
+    }
 
     // Update cached decorations when content changes
     if let Some(mut result) = update_result {
@@ -710,8 +815,9 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         let (selection, display_offset) = active_tab_id
             .and_then(|id| state.shells.get(&id))
             .map(|shell| {
-                let content = shell.terminal().renderable_content();
-                (content.selection, shell.terminal().display_offset() as i32)
+                let terminal = shell.terminal();
+                let content = terminal.renderable_content();
+                (content.selection, terminal.display_offset() as i32)
             })
             .unwrap_or((None, 0));
 
@@ -779,6 +885,9 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
                             override_color.b,
                             override_color.a,
                         ]
+                    } else if let Some(app_color) = cursor.color {
+                        // Color set by the application via OSC 12
+                        app_color
                     } else {
                         state.gpu.terminal_vello.cursor_color()
                     };
@@ -1047,6 +1156,11 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         dialogs::render_window_rename(state, shared, &mut encoder, render_target);
     }
 
+    // Pass 8.6: Render confirmation prompt (e.g. OSC 52 clipboard read)
+    if state.ui.confirm.is_active() {
+        dialogs::render_confirm_prompt(state, shared, &mut encoder, render_target);
+    }
+
     // Pass 9: Render bell flash overlay (if active via CSS theme)
     if let Some((color, intensity)) = state.ui.overrides.get_effective_flash()
         && intensity > 0.0
@@ -1069,6 +1183,11 @@ pub fn render_frame(state: &mut WindowState, shared: &mut SharedGpuState) {
         overlays::render_copy_indicator(state, shared, &mut encoder, render_target);
     }
 
+    // Pass 12.5: Render hovered hyperlink target (OSC 8)
+    if state.interaction.hovered_hyperlink().is_some() {
+        overlays::render_link_preview(state, shared, &mut encoder, render_target);
+    }
+
     // Pass 13: Render toast notifications (errors, warnings)
     if state.ui.toast.is_visible() {
         overlays::render_toast(state, shared, &mut encoder, render_target);
@@ -1757,6 +1876,8 @@ mod tests {
             content_changed: true,
             shell_events: vec![ShellEvent::Bell, ShellEvent::CommandFail(1)],
             title_change: Some("test".to_string()),
+            clipboard_requests: vec![],
+            child_exit: None,
         };
         assert!(result.content_changed);
         assert_eq!(result.shell_events.len(), 2);
@@ -1769,6 +1890,8 @@ mod tests {
             content_changed: false,
             shell_events: vec![],
             title_change: None,
+            clipboard_requests: vec![],
+            child_exit: None,
         };
         assert!(!result.content_changed);
         assert!(result.shell_events.is_empty());
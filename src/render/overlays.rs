@@ -304,6 +304,135 @@ pub fn render_copy_indicator(
     }
 }
 
+/// Render the hovered hyperlink's target (bottom-left, like a browser status bar)
+pub fn render_link_preview(
+    state: &mut WindowState,
+    shared: &SharedGpuState,
+    encoder: &mut wgpu::CommandEncoder,
+    frame_view: &wgpu::TextureView,
+) {
+    let Some(uri) = state.interaction.hovered_hyperlink() else {
+        return;
+    };
+
+    // Calculate dimensions
+    let screen_width = state.gpu.config.width as f32;
+    let screen_height = state.gpu.config.height as f32;
+
+    // Use tab glyph cache metrics for consistent sizing
+    let char_width = state.gpu.tab_glyph_cache.cell_width();
+    let line_height = state.gpu.tab_glyph_cache.line_height();
+
+    let padding_x = char_width;
+    let padding_y = line_height * 0.3;
+    let margin = line_height * 0.5;
+
+    // Long targets are cut off with an ellipsis rather than running off-screen
+    let max_chars =
+        ((screen_width - margin * 2.0 - padding_x * 2.0) / char_width).max(1.0) as usize;
+    let text: String = if uri.chars().count() > max_chars {
+        uri.chars()
+            .take(max_chars.saturating_sub(1))
+            .chain(std::iter::once('…'))
+            .collect()
+    } else {
+        uri.to_string()
+    };
+
+    let text_width = char_width * text.chars().count() as f32;
+    let pill_width = text_width + padding_x * 2.0;
+    let pill_height = line_height + padding_y * 2.0;
+    let pill_x = margin;
+    let pill_y = screen_height - pill_height - margin;
+
+    // Colors from theme so the pill matches the active theme's UI.
+    let ui = &state.gpu.effect_pipeline.theme().ui;
+    let bg_color = ui.context_menu.background.to_array();
+    let text_color = ui.context_menu.text_color.to_array();
+
+    // Render background pill
+    state.gpu.rect_renderer.clear();
+    state
+        .gpu
+        .rect_renderer
+        .update_screen_size(&shared.queue, screen_width, screen_height);
+    state
+        .gpu
+        .rect_renderer
+        .push_rect(pill_x, pill_y, pill_width, pill_height, bg_color);
+
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Link Preview Background Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        state
+            .gpu
+            .rect_renderer
+            .render(&shared.queue, &mut pass, &state.gpu.rect_instance_buffer);
+    }
+
+    // Render text using tab title renderer
+    state.gpu.tab_title_renderer.clear();
+    state
+        .gpu
+        .tab_title_renderer
+        .update_screen_size(&shared.queue, screen_width, screen_height);
+
+    let text_x = pill_x + padding_x;
+    let text_y = pill_y + padding_y;
+
+    let mut glyphs = Vec::new();
+    let mut char_x = text_x;
+    for ch in text.chars() {
+        if let Some(glyph) = state.gpu.tab_glyph_cache.position_char(ch, char_x, text_y) {
+            glyphs.push(glyph);
+        }
+        char_x += char_width;
+    }
+    state
+        .gpu
+        .tab_title_renderer
+        .push_glyphs(&glyphs, text_color);
+    state.gpu.tab_glyph_cache.flush(&shared.queue);
+
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Link Preview Text Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        state.gpu.tab_title_renderer.render(
+            &shared.queue,
+            &mut pass,
+            &state.gpu.overlay_text_instance_buffer,
+        );
+    }
+}
+
 /// Render toast notification (bottom-centered)
 pub fn render_toast(
     state: &mut WindowState,
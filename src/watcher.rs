@@ -30,7 +30,10 @@ pub struct ConfigWatcher {
 
 impl ConfigWatcher {
     /// Create a new config watcher
-    pub fn new() -> Option<Self> {
+    ///
+    /// `wake` runs on the watcher's thread after each change is queued, so
+    /// a sleeping event loop gets around to `poll`.
+    pub fn new(wake: impl Fn() + Send + 'static) -> Option<Self> {
         let config_dir = Config::config_dir()?;
 
         // Canonicalize paths to handle symlinks (e.g., /tmp -> /private/tmp on macOS)
@@ -62,11 +65,13 @@ impl ConfigWatcher {
                             if path == &config_path_clone {
                                 log::info!("Config file changed!");
                                 let _ = tx.send(ConfigEvent::ConfigChanged);
+                                wake();
                             } else if path.starts_with(&themes_dir)
                                 && path.extension().map(|e| e == "css").unwrap_or(false)
                             {
                                 log::info!("Theme file changed: {:?}", path);
                                 let _ = tx.send(ConfigEvent::ThemeChanged);
+                                wake();
                             }
                         }
                     }
@@ -2,21 +2,35 @@
 //!
 //! Groups state related to user interaction, mouse handling, search, and context menus.
 
+use std::collections::HashSet;
 use std::time::Instant;
 
+use crt_core::{Search, SearchOptions};
+use winit::keyboard::PhysicalKey;
+
 use super::types::TabId;
 
-/// Search match position in terminal
-#[derive(Debug, Clone, Copy)]
+/// Search match position in terminal (may span wrapped lines)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SearchMatch {
-    /// Line number (grid-relative: negative = history, 0+ = visible)
+    /// Start line number (grid-relative: negative = history, 0+ = visible)
     pub line: i32,
-    /// Starting column
+    /// Starting column on the start line
     pub start_col: usize,
-    /// Ending column (exclusive)
+    /// End line number (same as line unless the match wraps)
+    pub end_line: i32,
+    /// Ending column on the end line (exclusive)
     pub end_col: usize,
 }
 
+impl SearchMatch {
+    /// Check if the match covers a cell
+    pub fn contains(&self, line: i32, col: usize) -> bool {
+        (line > self.line || (line == self.line && col >= self.start_col))
+            && (line < self.end_line || (line == self.end_line && col < self.end_col))
+    }
+}
+
 /// Search state for find-in-terminal functionality
 #[derive(Debug, Clone, Default)]
 pub struct SearchState {
@@ -24,16 +38,61 @@ pub struct SearchState {
     pub active: bool,
     /// Current search query
     pub query: String,
+    /// Regex, case and whole-word toggles (kept when search closes)
+    pub options: SearchOptions,
+    /// The compiled query and its matches so far; `None` while the query is
+    /// empty or not a valid regex
+    pub search: Option<Search>,
+    /// Tab the matches were found in
+    pub tab_id: Option<TabId>,
     /// All matches found
     pub matches: Vec<SearchMatch>,
     /// Index of current/focused match
     pub current_match: usize,
 }
 
+impl SearchState {
+    /// Close search, keeping the options for next time
+    pub fn close(&mut self) {
+        self.active = false;
+        self.query.clear();
+        self.search = None;
+        self.matches.clear();
+        self.current_match = 0;
+    }
+
+    /// Move to the next match, or the previous one, wrapping around
+    pub fn navigate(&mut self, reverse: bool) {
+        let count = self.matches.len();
+        if count > 0 {
+            self.current_match = if reverse {
+                (self.current_match + count - 1) % count
+            } else {
+                (self.current_match + 1) % count
+            };
+        }
+    }
+
+    /// Match counter for the search bar, e.g. `3 of 27`
+    pub fn status(&self) -> String {
+        if self.query.is_empty() {
+            String::new()
+        } else if self.search.is_none() {
+            "invalid regex".to_string()
+        } else if self.matches.is_empty() {
+            "no matches".to_string()
+        } else {
+            format!("{} of {}", self.current_match + 1, self.matches.len())
+        }
+    }
+}
+
 /// Context menu item
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ContextMenuItem {
     Copy,
+    /// Copy the output of the command that was right-clicked
+    CopyCommandOutput,
     Paste,
     SelectAll,
     Separator,
@@ -48,6 +107,7 @@ impl ContextMenuItem {
     pub fn label(&self) -> String {
         match self {
             ContextMenuItem::Copy => "Copy".to_string(),
+            ContextMenuItem::CopyCommandOutput => "Copy Command Output".to_string(),
             ContextMenuItem::Paste => "Paste".to_string(),
             ContextMenuItem::SelectAll => "Select All".to_string(),
             ContextMenuItem::Separator => String::new(),
@@ -64,7 +124,9 @@ impl ContextMenuItem {
             ContextMenuItem::Paste => "Cmd+V",
             ContextMenuItem::SelectAll => "Cmd+A",
             ContextMenuItem::Themes => "\u{25B6}", // Right-pointing triangle for submenu
-            ContextMenuItem::Separator | ContextMenuItem::Theme(_) => "",
+            ContextMenuItem::CopyCommandOutput
+            | ContextMenuItem::Separator
+            | ContextMenuItem::Theme(_) => "",
         }
         #[cfg(not(target_os = "macos"))]
         match self {
@@ -72,7 +134,9 @@ impl ContextMenuItem {
             ContextMenuItem::Paste => "Ctrl+V",
             ContextMenuItem::SelectAll => "Ctrl+A",
             ContextMenuItem::Themes => "\u{25B6}", // Right-pointing triangle for submenu
-            ContextMenuItem::Separator | ContextMenuItem::Theme(_) => "",
+            ContextMenuItem::CopyCommandOutput
+            | ContextMenuItem::Separator
+            | ContextMenuItem::Theme(_) => "",
         }
     }
 
@@ -114,6 +178,8 @@ pub struct InteractionState {
     pub last_click_tab: Option<TabId>,
     /// Whether mouse button is currently pressed
     pub mouse_pressed: bool,
+    /// The pressed button was reported to the program rather than selecting
+    pub mouse_press_reported: bool,
     /// Click count for multi-click selection (1=single, 2=word, 3=line)
     pub selection_click_count: u8,
     /// Last selection click time for multi-click detection
@@ -130,6 +196,20 @@ pub struct InteractionState {
     pub hovered_path_index: Option<usize>,
     /// Caches path existence checks across frames (NFR-001)
     pub path_validator: crate::input::PathValidator,
+    /// Keys whose press went to the shell, so their release can be reported
+    pub keys_sent: HashSet<PhysicalKey>,
+    /// Tab whose program was last told it has focus (mode 1004)
+    pub focus_reported_tab: Option<TabId>,
+}
+
+impl InteractionState {
+    /// Target of the hovered OSC 8 hyperlink, shown while hovering
+    pub fn hovered_hyperlink(&self) -> Option<&str> {
+        self.hovered_url_index
+            .and_then(|index| self.detected_urls.get(index))
+            .filter(|url| url.explicit)
+            .map(|url| url.url.as_str())
+    }
 }
 
 /// Context menu state
@@ -162,12 +242,17 @@ pub struct ContextMenu {
     pub submenu_height: f32,
     /// Hovered item in submenu
     pub submenu_hovered_item: Option<usize>,
+    /// Id of the command whose output the menu was opened on, if any
+    pub command: Option<u64>,
 }
 
 impl ContextMenu {
     /// Build the main menu items (Themes shown as a parent item, not expanded)
     pub fn items(&self) -> Vec<ContextMenuItem> {
         let mut items = ContextMenuItem::edit_items();
+        if self.command.is_some() {
+            items.insert(1, ContextMenuItem::CopyCommandOutput);
+        }
         if !self.themes.is_empty() {
             items.push(ContextMenuItem::Separator);
             items.push(ContextMenuItem::Themes);
@@ -339,3 +424,56 @@ impl ContextMenu {
             .position(|item| matches!(item, ContextMenuItem::Themes))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_match_covers_wrapped_rows() {
+        let found = SearchMatch {
+            line: -1,
+            start_col: 7,
+            end_line: 0,
+            end_col: 2,
+        };
+        assert!(!found.contains(-1, 6));
+        assert!(found.contains(-1, 7) && found.contains(-1, 79));
+        assert!(found.contains(0, 0) && found.contains(0, 1));
+        assert!(!found.contains(0, 2) && !found.contains(1, 0));
+    }
+
+    #[test]
+    fn search_counter_and_navigation() {
+        let found = SearchMatch {
+            line: 0,
+            start_col: 0,
+            end_line: 0,
+            end_col: 1,
+        };
+        let mut search = SearchState {
+            active: true,
+            query: "(".to_string(),
+            ..SearchState::default()
+        };
+        assert_eq!(search.status(), "invalid regex");
+
+        search.search = Search::new("x", SearchOptions::default());
+        assert_eq!(search.status(), "no matches");
+        search.matches = vec![found; 27];
+        search.current_match = 2;
+        assert_eq!(search.status(), "3 of 27");
+
+        // Backwards from the first match wraps to the last
+        search.current_match = 0;
+        search.navigate(true);
+        assert_eq!(search.current_match, 26);
+        search.navigate(false);
+        assert_eq!(search.current_match, 0);
+
+        search.options.regex = true;
+        search.close();
+        assert!(!search.active && search.query.is_empty() && search.matches.is_empty());
+        assert!(search.options.regex);
+    }
+}
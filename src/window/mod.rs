@@ -15,9 +15,11 @@ pub use render::{
     CachedRenderState, CursorInfo, DecorationKind, PreparedCell, RenderContext, RenderLayout,
     RenderState, TerminalRenderData, TextBufferUpdateResult, TextDecoration, prepare_render_cells,
 };
+pub(crate) use types::query_color;
 pub use types::{EffectId, TabId};
 pub use ui::{
-    BellState, CopyIndicator, Toast, ToastType, UiState, WindowRenameState, ZoomIndicator,
+    BellState, ConfirmAction, CopyIndicator, Toast, ToastType, UiState, WindowRenameState,
+    ZoomIndicator,
 };
 
 use std::collections::hash_map::DefaultHasher;
@@ -25,13 +27,17 @@ use std::collections::{HashMap, HashSet};
 use std::hash::Hasher;
 use std::sync::Arc;
 
-use crt_core::{CellFlags, ShellTerminal, Size, SpawnOptions};
-use crt_renderer::GlyphStyle;
+use crt_core::{CellFlags, Line, NamedColor, SemanticZone, ShellTerminal, Size, SpawnOptions};
+use crt_renderer::{GlyphInstance, GlyphStyle};
 use crt_theme::Theme;
 use winit::window::Window;
 
+use crate::config::{ClipboardConfig, ExitBehavior};
 use crate::gpu::{SharedGpuState, WindowGpuState};
-use crate::input::{detect_paths_in_line, detect_urls_in_line, merge_wrapped_urls};
+use crate::input::{
+    detect_paths_in_line, detect_urls_in_line, extend_hyperlink_spans, merge_hyperlinks,
+    merge_wrapped_urls,
+};
 
 /// Per-window state containing window handle, GPU state, shells, and interaction state
 pub struct WindowState {
@@ -39,7 +45,7 @@ pub struct WindowState {
     pub gpu: WindowGpuState,
     // Map of tab_id -> shell (each window has its own tabs)
     pub shells: HashMap<TabId, ShellTerminal>,
-    // Content hash to skip reshaping when unchanged (per tab)
+    // Cursor/scroll hash to skip text updates when unchanged (per tab); 0 forces a full rebuild
     pub content_hashes: HashMap<TabId, u64>,
     // Window-specific sizing
     pub cols: usize,
@@ -53,6 +59,14 @@ pub struct WindowState {
     pub interaction: InteractionState,
     // UI overlay state (search, bell, context menu)
     pub ui: UiState,
+    // Clipboard policy for OSC 52 requests (from config)
+    pub clipboard: ClipboardConfig,
+    // What to do with a tab whose shell exits (from config)
+    pub exit_behavior: ExitBehavior,
+    // Programs that never trigger the close confirmation (from config)
+    pub confirm_close_allowlist: Vec<String>,
+    // Last tab's shell exited and the window should close (App handles it)
+    pub close_requested: bool,
     // Custom window title (None = use default "CRT Terminal")
     pub custom_title: Option<String>,
     // Per-window theme
@@ -63,6 +77,13 @@ pub struct WindowState {
 impl WindowState {
     /// Set the theme for this window, updating all GPU resources
     pub fn set_theme(&mut self, name: &str, theme: Theme) {
+        // Colors programs set at runtime (OSC 4/10/11/12) belong to the old theme
+        if name != self.theme_name {
+            for (tab_id, shell) in &mut self.shells {
+                shell.terminal_mut().reset_colors();
+                self.content_hashes.insert(*tab_id, 0);
+            }
+        }
         self.theme_name = name.to_string();
         self.theme = theme.clone();
 
@@ -95,33 +116,41 @@ impl WindowState {
 
     /// Update text buffer for this window's active shell
     ///
-    /// Returns cursor position and decorations if content changed, None otherwise
+    /// Only lines the terminal reports as damaged are re-collected; of those,
+    /// only lines whose cells or glow routing actually changed are re-shaped
+    /// and re-scanned for links. Every other line replays its cached glyphs
+    /// and decorations. Returns cursor position and decorations if anything
+    /// changed, None otherwise.
     pub fn update_text_buffer(
         &mut self,
         shared_gpu: &SharedGpuState,
     ) -> Option<TextBufferUpdateResult> {
-        let active_tab_id = self.gpu.tab_bar.active_tab_id();
+        let tab_id = self.gpu.tab_bar.active_tab_id()?;
 
-        // Get damage info via mutable reference before taking immutable terminal ref
-        let damaged_lines = active_tab_id.and_then(|id| {
-            self.shells
-                .get_mut(&id)
-                .map(|shell| shell.terminal_mut().damaged_line_set())
-        });
+        // Resolved before locking the terminal: it reads the OSC 7 report
+        let path_cwd = self.active_shell_cwd();
 
-        let shell = active_tab_id.and_then(|id| self.shells.get(&id));
+        let shell = self.shells.get(&tab_id)?;
+        // Hold the lock for the whole pass so damage and content come from the
+        // same snapshot while the parser thread keeps running
+        let mut terminal = shell.terminal();
+        let damaged_lines = terminal.damaged_line_set();
+        terminal.reset_damage();
 
-        shell?;
-        let shell = shell.unwrap();
-        let terminal = shell.terminal();
+        let screen_lines = terminal.screen_lines();
+        let display_offset = terminal.display_offset() as i32;
+        let cursor = terminal.cursor();
+        let cursor_visible = terminal.cursor_mode_visible();
+        let cursor_viewport_line = cursor.point.line.0 + display_offset;
+        let has_semantic_zones = terminal.has_semantic_zones();
 
-        // Compute content hash to avoid re-rendering unchanged content
+        // Cell changes are tracked through damage; the hash only covers the
+        // cursor and scroll state. 0 is reserved for "rebuild everything".
         let mut hasher = DefaultHasher::new();
-        let content = terminal.renderable_content();
-        hasher.write_i32(content.cursor.point.line.0);
-        hasher.write_usize(content.cursor.point.column.0);
+        hasher.write_i32(cursor.point.line.0);
+        hasher.write_usize(cursor.point.column.0);
         // Include cursor shape in hash - programs like Claude Code change cursor style
-        let cursor_shape_discriminant = match content.cursor.shape {
+        let cursor_shape_discriminant = match cursor.shape {
             crt_core::CursorShape::Block => 0u8,
             crt_core::CursorShape::Underline => 1u8,
             crt_core::CursorShape::Beam => 2u8,
@@ -129,125 +158,105 @@ impl WindowState {
             crt_core::CursorShape::Hidden => 4u8,
         };
         hasher.write_u8(cursor_shape_discriminant);
-        // Include cursor visibility mode
-        hasher.write_u8(if terminal.cursor_mode_visible() { 1 } else { 0 });
-        for cell in content.display_iter {
-            hasher.write_u32(cell.c as u32);
-        }
-        let content_hash = hasher.finish();
-
-        // Check if content changed
-        let tab_id = active_tab_id.unwrap();
+        hasher.write_u8(if cursor_visible { 1 } else { 0 });
+        hasher.write_i32(display_offset);
+        let view_hash = hasher.finish().max(1);
         let prev_hash = self.content_hashes.get(&tab_id).copied().unwrap_or(0);
-        log::debug!(
-            "update_text_buffer: prev_hash={}, content_hash={}, will_render={}",
-            prev_hash,
-            content_hash,
-            prev_hash == 0 || content_hash != prev_hash
-        );
-        if content_hash == prev_hash && prev_hash != 0 {
-            return None; // No changes
-        }
-        self.content_hashes.insert(tab_id, content_hash);
 
         // Get content offset (excluding tab bar)
         let (offset_x, offset_y) = self.gpu.tab_bar.content_offset();
-
-        // Re-read content since we consumed it above for hashing
-        let content = terminal.renderable_content();
-        self.gpu.grid_renderer.clear();
-        self.gpu.output_grid_renderer.clear();
-
         let cell_width = self.gpu.glyph_cache.cell_width();
         let line_height = self.gpu.glyph_cache.line_height();
         let padding = 10.0 * self.scale_factor;
 
-        // Get display offset to convert grid lines to viewport lines
-        let display_offset = terminal.display_offset() as i32;
-
-        // Cursor info
-        let cursor = content.cursor;
-        let cursor_point = cursor.point;
-        // Check cursor visibility via TermMode::SHOW_CURSOR (CSI ?25h/l)
-        let cursor_visible = terminal.cursor_mode_visible();
-
-        // Compute cursor position (adjust for scroll offset)
-        let cursor_viewport_line = cursor_point.line.0 + display_offset;
-        let cursor_x = offset_x + padding + (cursor_point.column.0 as f32 * cell_width);
-        let cursor_y = offset_y + padding + (cursor_viewport_line as f32 * line_height);
-
-        // Single pass: collect cells AND build line text for URL detection
-        // This avoids a second terminal.renderable_content() call
-        // Reuse cached collections to avoid per-update allocations
-        // Clear line_texts values (keeping keys to reuse String allocations)
-        for s in self.render.cached.line_texts.values_mut() {
-            s.clear();
-        }
-        self.render.cached.collected_cells.clear();
-
-        let mut inverse_count = 0;
-        let mut total_cells = 0;
-        let mut line1_cells = 0;
-        for cell in content.display_iter {
-            let viewport_line = cell.point.line.0 + display_offset;
+        let key = render::LineCacheKey {
+            tab_id,
+            screen_lines,
+            display_offset,
+            has_semantic_zones,
+            layout: [
+                offset_x + padding,
+                offset_y + padding,
+                cell_width,
+                line_height,
+            ],
+            atlas_generation: self.gpu.glyph_cache.generation(),
+        };
+        // Paste normalization looks at every line at once
+        let full = prev_hash == 0
+            || damaged_lines.is_none()
+            || self.render.cached.key != Some(key)
+            || self.render.paste_pending;
+        if full {
+            self.render.cached.lines.clear();
             self.render
                 .cached
-                .line_texts
-                .entry(viewport_line)
-                .or_default()
-                .push(cell.c);
-
-            // Track cells on line 1 for debugging paste issue
-            if cell.point.line.0 == 1 {
-                line1_cells += 1;
-                if line1_cells <= 20 {
-                    log::debug!(
-                        "Line1 cell: col={}, char='{}', inverse={}, fg={:?}, bg={:?}",
-                        cell.point.column.0,
-                        cell.c,
-                        cell.flags.contains(CellFlags::INVERSE),
-                        cell.fg,
-                        cell.bg
-                    );
-                }
+                .lines
+                .resize_with(screen_lines, Default::default);
+            self.render.cached.line_texts.clear();
+        }
+        let damaged: HashSet<usize> = damaged_lines.unwrap_or_default().into_iter().collect();
+
+        // Pass 1: re-collect damaged lines (and lines whose glow routing
+        // changed) and rebuild those whose cells differ from the cache
+        let mut rebuilt = Vec::new();
+        let mut cells = Vec::new();
+        let mut hyperlinks = Vec::new();
+        for viewport_line in 0..screen_lines {
+            let vp_line = viewport_line as i32;
+            let grid_line = vp_line - display_offset;
+            let glow = if has_semantic_zones {
+                matches!(
+                    terminal.get_line_zone(grid_line),
+                    SemanticZone::Prompt | SemanticZone::Input
+                )
+            } else {
+                vp_line >= cursor_viewport_line - 1 && vp_line <= cursor_viewport_line
+            };
+            let cache = &mut self.render.cached.lines[viewport_line];
+            if !full && !damaged.contains(&viewport_line) && cache.glow == glow {
+                continue;
             }
 
-            // Track INVERSE cells for debugging
-            if cell.flags.contains(CellFlags::INVERSE) {
-                inverse_count += 1;
-                if inverse_count <= 5 {
-                    log::debug!(
-                        "INVERSE cell: line={}, col={}, char='{}', fg={:?}, bg={:?}",
-                        cell.point.line.0,
-                        cell.point.column.0,
-                        cell.c,
-                        cell.fg,
-                        cell.bg
+            cells.clear();
+            hyperlinks.clear();
+            let row = &terminal.inner().grid()[Line(grid_line)];
+            for (col, cell) in row.into_iter().enumerate() {
+                // OSC 8 hyperlinks are attached to the cells they cover
+                if let Some(link) = cell.hyperlink() {
+                    extend_hyperlink_spans(
+                        &mut hyperlinks,
+                        link.uri(),
+                        col,
+                        viewport_line,
+                        self.cols,
                     );
                 }
-            }
-            total_cells += 1;
-
-            // Collect cell data for rendering pass
-            self.render
-                .cached
-                .collected_cells
-                .push(render::CollectedCell {
-                    col: cell.point.column.0,
-                    grid_line: cell.point.line.0,
+                cells.push(render::CollectedCell {
+                    col,
+                    grid_line,
                     c: cell.c,
                     flags: cell.flags,
                     fg: cell.fg,
                     bg: cell.bg,
                 });
+            }
+            if !full && cache.matches(&cells, &hyperlinks, glow) {
+                continue;
+            }
+
+            std::mem::swap(&mut cache.cells, &mut cells);
+            std::mem::swap(&mut cache.hyperlinks, &mut hyperlinks);
+            cache.glow = glow;
+            rebuilt.push(viewport_line);
         }
-        if inverse_count > 0 {
-            log::info!(
-                "Collected {} cells, {} with INVERSE flag",
-                total_cells,
-                inverse_count
-            );
+
+        self.render.cached.lines_rebuilt = rebuilt.len();
+        if rebuilt.is_empty() && view_hash == prev_hash {
+            return None; // No changes
         }
+        self.content_hashes.insert(tab_id, view_hash);
+        self.render.cached.key = Some(key);
 
         // Normalize INVERSE flag on paste to fix visual boundary issue
         // zsh enables INVERSE mid-line for paste highlighting, creating an ugly
@@ -257,7 +266,7 @@ impl WindowState {
             // Count INVERSE vs non-INVERSE cells per line (skip whitespace)
             let mut line_stats: HashMap<i32, (usize, usize)> = HashMap::new();
             let mut total_inverse = 0usize;
-            for cell in &self.render.cached.collected_cells {
+            for cell in self.render.cached.lines.iter().flat_map(|line| &line.cells) {
                 if cell.c == ' ' || cell.c == '\0' {
                     continue;
                 }
@@ -284,9 +293,11 @@ impl WindowState {
                     "Paste: adding INVERSE to all cells on {} lines with mixed states",
                     mixed_lines.len()
                 );
-                for cell in &mut self.render.cached.collected_cells {
-                    if mixed_lines.contains(&cell.grid_line) {
-                        cell.flags.insert(CellFlags::INVERSE);
+                for line in &mut self.render.cached.lines {
+                    for cell in &mut line.cells {
+                        if mixed_lines.contains(&cell.grid_line) {
+                            cell.flags.insert(CellFlags::INVERSE);
+                        }
                     }
                 }
                 self.render.paste_pending = false;
@@ -299,11 +310,43 @@ impl WindowState {
             }
         }
 
-        // Detect URLs before rendering so we can underline them with text color
+        // Detect URLs and file paths on rebuilt lines only; other lines keep
+        // what was found when they last changed
+        for &viewport_line in &rebuilt {
+            let line = &mut self.render.cached.lines[viewport_line];
+            let text: String = line.cells.iter().map(|cell| cell.c).collect();
+            line.urls = detect_urls_in_line(&text, viewport_line);
+            line.paths = detect_paths_in_line(&text, viewport_line);
+            self.render
+                .cached
+                .line_texts
+                .insert(viewport_line as i32, text);
+        }
+
+        // Reassemble the viewport's links so they are underlined with text color
+        let mut hyperlinks = Vec::new();
         self.interaction.detected_urls.clear();
-        for (viewport_line, line_text) in &self.render.cached.line_texts {
-            let urls = detect_urls_in_line(line_text, *viewport_line as usize);
-            self.interaction.detected_urls.extend(urls);
+        self.interaction.detected_paths.clear();
+        for line in &self.render.cached.lines {
+            self.interaction
+                .detected_urls
+                .extend(line.urls.iter().cloned());
+            self.interaction
+                .detected_paths
+                .extend(line.paths.iter().cloned());
+            // Rejoin OSC 8 spans that wrap onto the next line
+            for span in &line.hyperlinks {
+                extend_hyperlink_spans(
+                    &mut hyperlinks,
+                    &span.url,
+                    span.start_col,
+                    span.line,
+                    self.cols,
+                );
+                if let Some(last) = hyperlinks.last_mut() {
+                    last.end_col = span.end_col;
+                }
+            }
         }
         // Merge URLs that wrap across multiple lines
         merge_wrapped_urls(
@@ -311,18 +354,14 @@ impl WindowState {
             &self.render.cached.line_texts,
             self.cols,
         );
+        // OSC 8 hyperlinks take precedence over URLs found in the text
+        merge_hyperlinks(&mut self.interaction.detected_urls, hyperlinks);
 
-        // Detect file paths the same way, then validate them against the
-        // filesystem so only existing paths become clickable. Resolution uses
-        // the shell's current working directory and $HOME; existence checks are
-        // cached across frames (see PathValidator / NFR-001).
-        let path_cwd = self.active_shell_cwd();
+        // Validate file paths against the filesystem so only existing paths
+        // become clickable. Resolution uses the shell's current working
+        // directory and $HOME; existence checks are cached across frames (see
+        // PathValidator / NFR-001).
         let path_home = std::env::var_os("HOME").map(std::path::PathBuf::from);
-        self.interaction.detected_paths.clear();
-        for (viewport_line, line_text) in &self.render.cached.line_texts {
-            let paths = detect_paths_in_line(line_text, *viewport_line as usize);
-            self.interaction.detected_paths.extend(paths);
-        }
         let interaction = &mut self.interaction;
         interaction.path_validator.begin_pass(path_cwd, path_home);
         interaction
@@ -330,11 +369,7 @@ impl WindowState {
             .validate_all(&mut interaction.detected_paths);
         interaction.detected_paths.retain(|p| p.exists);
 
-        // Flatten the Option<Option<Vec>> from the early damage query
-        let damaged_lines = damaged_lines.flatten();
-
         // Prepare render data using pure function (no GPU calls)
-        let has_semantic_zones = terminal.has_semantic_zones();
         let theme = self.gpu.effect_pipeline.theme();
         let ctx = RenderContext {
             layout: RenderLayout {
@@ -347,6 +382,7 @@ impl WindowState {
             display_offset,
             cursor_viewport_line,
             palette: &theme.palette,
+            runtime_colors: terminal.colors(),
             default_fg: theme.foreground.to_array(),
             default_bg: theme.background.bottom.to_array(),
             hovered_url_index: self.interaction.hovered_url_index,
@@ -363,153 +399,65 @@ impl WindowState {
             },
             has_semantic_zones,
             get_line_zone: Box::new(|grid_line| terminal.get_line_zone(grid_line)),
+            command_fold_style: Some(&theme.command_fold),
+            is_folded_line: Box::new(|grid_line| terminal.is_folded_line(grid_line)),
         };
 
-        // Damage-aware rendering: only prepare cells for changed lines,
-        // reuse cached data for undamaged lines.
-        let mut all_decorations = Vec::new();
-        let is_partial = damaged_lines.is_some();
-
-        if let Some(ref damaged) = damaged_lines
-            && !damaged.is_empty()
-            && !self.render.cached.line_cells.is_empty()
-        {
-            // Partial damage — only re-prepare damaged lines
-            let damaged_set: HashSet<i32> = damaged
-                .iter()
-                .map(|&line| line as i32 + display_offset)
-                .collect();
-
-            // Group collected cells by viewport line
-            let mut cells_by_line: HashMap<i32, Vec<render::CollectedCell>> = HashMap::new();
-            for cell in &self.render.cached.collected_cells {
-                let viewport_line = cell.grid_line + display_offset;
-                cells_by_line
-                    .entry(viewport_line)
-                    .or_default()
-                    .push(cell.clone());
-            }
-
-            // Collect all viewport lines that have content
-            let all_lines: HashSet<i32> = cells_by_line.keys().copied().collect();
-
-            for &vp_line in &all_lines {
-                if damaged_set.contains(&vp_line) {
-                    // Damaged line — re-prepare
-                    if let Some(line_cells) = cells_by_line.get(&vp_line) {
-                        let (prepared, decos) = prepare_render_cells(line_cells, &ctx);
-                        self.render
-                            .cached
-                            .line_cells
-                            .insert(vp_line, prepared.clone());
-                        self.render
-                            .cached
-                            .line_decorations
-                            .insert(vp_line, decos.clone());
-                        all_decorations.extend(decos);
-                        for cell in &prepared {
-                            let style = GlyphStyle::new(cell.bold, cell.italic);
-                            if let Some(glyph) = self
-                                .gpu
-                                .glyph_cache
-                                .position_char_styled(cell.character, cell.x, cell.y, style)
-                            {
-                                if cell.use_glow {
-                                    self.gpu
-                                        .grid_renderer
-                                        .push_glyphs(&[glyph], cell.fg_color);
-                                } else {
-                                    self.gpu
-                                        .output_grid_renderer
-                                        .push_glyphs(&[glyph], cell.fg_color);
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    // Undamaged line — replay cached data
-                    if let Some(cached_cells) = self.render.cached.line_cells.get(&vp_line) {
-                        for cell in cached_cells {
-                            let style = GlyphStyle::new(cell.bold, cell.italic);
-                            if let Some(glyph) = self
-                                .gpu
-                                .glyph_cache
-                                .position_char_styled(cell.character, cell.x, cell.y, style)
-                            {
-                                if cell.use_glow {
-                                    self.gpu
-                                        .grid_renderer
-                                        .push_glyphs(&[glyph], cell.fg_color);
-                                } else {
-                                    self.gpu
-                                        .output_grid_renderer
-                                        .push_glyphs(&[glyph], cell.fg_color);
-                                }
-                            }
-                        }
-                    }
-                    if let Some(cached_decos) = self.render.cached.line_decorations.get(&vp_line) {
-                        all_decorations.extend(cached_decos.iter().cloned());
-                    }
-                }
-            }
-        } else {
-            // Full damage or first render — prepare everything
-            let (prepared_cells, decorations) =
-                prepare_render_cells(&self.render.cached.collected_cells, &ctx);
-
-            // Cache per-line results for future partial damage reuse
-            self.render.cached.line_cells.clear();
-            self.render.cached.line_decorations.clear();
-            for cell in &prepared_cells {
-                let vp_line = ((cell.y - offset_y - padding) / line_height).round() as i32;
-                self.render
-                    .cached
-                    .line_cells
-                    .entry(vp_line)
-                    .or_default()
-                    .push(cell.clone());
-            }
-            for deco in &decorations {
-                let vp_line = ((deco.y - offset_y - padding) / line_height).round() as i32;
-                self.render
-                    .cached
-                    .line_decorations
-                    .entry(vp_line)
-                    .or_default()
-                    .push(deco.clone());
-            }
-
-            // Push to GPU
-            for cell in &prepared_cells {
+        // Pass 2: re-shape rebuilt lines into cached glyph instances
+        let glyph_cache = &mut self.gpu.glyph_cache;
+        for &viewport_line in &rebuilt {
+            let line = &mut self.render.cached.lines[viewport_line];
+            let (prepared, decorations) = prepare_render_cells(&line.cells, &ctx);
+            line.decorations = decorations;
+            line.glow_glyphs.clear();
+            line.output_glyphs.clear();
+            for cell in &prepared {
                 let style = GlyphStyle::new(cell.bold, cell.italic);
-                if let Some(glyph) = self
-                    .gpu
-                    .glyph_cache
-                    .position_char_styled(cell.character, cell.x, cell.y, style)
+                if let Some(glyph) =
+                    glyph_cache.position_char_styled(cell.character, cell.x, cell.y, style)
                 {
+                    let instance = GlyphInstance::from_positioned(&glyph, cell.fg_color);
                     if cell.use_glow {
-                        self.gpu.grid_renderer.push_glyphs(&[glyph], cell.fg_color);
+                        line.glow_glyphs.push(instance);
                     } else {
-                        self.gpu
-                            .output_grid_renderer
-                            .push_glyphs(&[glyph], cell.fg_color);
+                        line.output_glyphs.push(instance);
                     }
                 }
             }
+        }
+        drop(ctx);
 
-            all_decorations = decorations;
+        // A full atlas is cleared mid-pass, leaving earlier glyphs with stale
+        // UVs; rebuild everything next frame
+        if self.gpu.glyph_cache.generation() != key.atlas_generation {
+            self.render.cached.key = None;
+            self.render.dirty = true;
         }
 
-        if is_partial {
-            log::debug!(
-                "Partial damage render: {} damaged lines",
-                damaged_lines.as_ref().map_or(0, |v| v.len())
-            );
+        // Pass 3: push every line, cached or rebuilt, in viewport order
+        self.gpu.grid_renderer.clear();
+        self.gpu.output_grid_renderer.clear();
+        let mut all_decorations = Vec::new();
+        for line in &self.render.cached.lines {
+            self.gpu.grid_renderer.push_instances(&line.glow_glyphs);
+            self.gpu
+                .output_grid_renderer
+                .push_instances(&line.output_glyphs);
+            all_decorations.extend_from_slice(&line.decorations);
         }
+        log::debug!(
+            "update_text_buffer: rebuilt {} of {} lines{}",
+            rebuilt.len(),
+            screen_lines,
+            if full { " (full)" } else { "" }
+        );
 
         self.gpu.glyph_cache.flush(&shared_gpu.queue);
 
+        // Compute cursor position (adjust for scroll offset)
+        let cursor_x = offset_x + padding + (cursor.point.column.0 as f32 * cell_width);
+        let cursor_y = offset_y + padding + (cursor_viewport_line as f32 * line_height);
+
         Some(TextBufferUpdateResult {
             cursor: CursorInfo {
                 x: cursor_x,
@@ -518,14 +466,22 @@ impl WindowState {
                 cell_height: line_height,
                 visible: cursor_visible,
                 shape: cursor.shape,
+                color: terminal.colors()[NamedColor::Cursor].map(types::rgb_to_rgba),
             },
             decorations: all_decorations,
         })
     }
 
+    /// Size of the terminal grid, with the cell size in pixels
+    pub fn terminal_size(&self) -> Size {
+        let glyph_cache = &self.gpu.glyph_cache;
+        Size::new(self.cols, self.rows)
+            .with_cell_size(glyph_cache.cell_width(), glyph_cache.line_height())
+    }
+
     /// Create a shell for a new tab with spawn options
     pub fn create_shell_for_tab(&mut self, tab_id: u64, options: SpawnOptions) {
-        let size = Size::new(self.cols, self.rows);
+        let size = self.terminal_size();
         log::info!(
             "Spawning shell for tab {} with semantic_prompts={}",
             tab_id,
@@ -547,11 +503,124 @@ impl WindowState {
 
     /// Get the current working directory of the active tab's shell
     pub fn active_shell_cwd(&self) -> Option<std::path::PathBuf> {
-        let tab_id = self.gpu.tab_bar.active_tab_id()?;
+        self.shell_cwd(self.gpu.tab_bar.active_tab_id()?)
+    }
+
+    /// Get the current working directory of a tab's shell
+    ///
+    /// Prefers the directory the shell reported via OSC 7, which stays correct
+    /// inside tmux and subshells and outlives the shell process. A report from
+    /// another host (ssh) names a path we can't use here, so we fall back to
+    /// the PTY process's cwd.
+    pub fn shell_cwd(&self, tab_id: TabId) -> Option<std::path::PathBuf> {
         let shell = self.shells.get(&tab_id)?;
+        if let Some(reported) = shell.terminal().reported_cwd()
+            && reported.is_local(crate::input::local_hostname())
+        {
+            return Some(reported.path.clone());
+        }
         shell.working_directory()
     }
 
+    /// React to the shell in `tab_id` exiting, per `[shell] on_exit`
+    ///
+    /// A held tab gets an exit banner and stays open until Enter restarts
+    /// the shell. Otherwise the tab closes; closing the last tab is left to
+    /// the App via `close_requested`.
+    pub fn handle_child_exit(&mut self, tab_id: TabId, code: i32) {
+        if self.exit_behavior.holds(code) {
+            if let Some(shell) = self.shells.get_mut(&tab_id) {
+                let banner = format!("\r\n[process exited with code {code}]\r\n");
+                shell.terminal_mut().process_input(banner.as_bytes());
+            }
+            self.content_hashes.insert(tab_id, 0);
+            self.render.dirty = true;
+        } else if self.gpu.tab_bar.tab_count() > 1 {
+            self.close_tab(tab_id);
+        } else {
+            self.close_requested = true;
+        }
+    }
+
+    /// Close a tab and its shell, redrawing whichever tab becomes active
+    pub fn close_tab(&mut self, tab_id: TabId) {
+        self.gpu.tab_bar.close_tab(tab_id);
+        self.remove_shell_for_tab(tab_id);
+        self.force_active_tab_redraw();
+        self.window.request_redraw();
+    }
+
+    /// Report focus changes to the programs that asked for them (mode 1004)
+    ///
+    /// A tab's program has focus while its tab is active in a focused window,
+    /// so switching tabs moves focus just like switching windows.
+    pub fn sync_focus_reports(&mut self) {
+        let focused_tab = self
+            .render
+            .focused
+            .then(|| self.gpu.tab_bar.active_tab_id())
+            .flatten();
+        let reported_tab = self.interaction.focus_reported_tab;
+        if focused_tab == reported_tab {
+            return;
+        }
+
+        if let Some(shell) = reported_tab.and_then(|tab_id| self.shells.get(&tab_id)) {
+            shell.report_focus(false);
+        }
+        if let Some(shell) = focused_tab.and_then(|tab_id| self.shells.get(&tab_id)) {
+            shell.report_focus(true);
+        }
+        self.interaction.focus_reported_tab = focused_tab;
+    }
+
+    /// Name of the program closing `tab_id` would kill, if it warrants a
+    /// confirmation: anything but an idle shell or an allowlisted program
+    pub fn busy_process(&self, tab_id: TabId) -> Option<String> {
+        let job = self.shells.get(&tab_id)?.foreground_job()?;
+        (!self.confirm_close_allowlist.contains(&job.name)).then_some(job.name)
+    }
+
+    /// First program in any tab of this window that warrants a confirmation
+    pub fn any_busy_process(&self) -> Option<String> {
+        self.shells
+            .keys()
+            .find_map(|&tab_id| self.busy_process(tab_id))
+    }
+
+    /// Ask before closing the window if a program is running in it
+    ///
+    /// Returns true if the window may close now. Otherwise the confirmation
+    /// is shown, and accepting it closes the window.
+    pub fn confirm_close_window(&mut self) -> bool {
+        let Some(name) = self.any_busy_process() else {
+            return true;
+        };
+        self.ui.confirm.show(
+            format!("{name} is running. Close this window?"),
+            ConfirmAction::CloseWindow,
+        );
+        self.render.dirty = true;
+        self.window.request_redraw();
+        false
+    }
+
+    /// Title tabs after their foreground program unless the program set a
+    /// title itself (OSC 0/2)
+    pub fn refresh_process_titles(&mut self) {
+        for (tab_id, shell) in &self.shells {
+            if shell.title().is_some() {
+                continue;
+            }
+            if let Some(process) = shell.foreground_process()
+                && self.gpu.tab_bar.get_tab_title(*tab_id) != Some(process.name.as_str())
+            {
+                self.gpu.tab_bar.set_tab_title(*tab_id, process.name);
+                self.render.dirty = true;
+            }
+        }
+    }
+
     /// Remove shell for a closed tab
     pub fn remove_shell_for_tab(&mut self, tab_id: u64) {
         self.shells.remove(&tab_id);
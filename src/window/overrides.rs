@@ -157,6 +157,14 @@ impl OverrideState {
         removed
     }
 
+    /// When the first active override expires
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.active
+            .iter()
+            .map(|o| o.triggered_at + Duration::from_millis(o.properties.duration_ms as u64))
+            .min()
+    }
+
     /// Check if an effect is currently patched
     pub fn is_patched(&self, effect: EffectId) -> bool {
         self.patched_effects.contains(&effect)
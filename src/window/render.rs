@@ -3,11 +3,15 @@
 //! Contains render-related state, cell collection, and the pure
 //! `prepare_render_cells()` function for renderer-agnostic terminal data.
 
-use crt_core::{AnsiColor, CellFlags, SemanticZone};
+use std::time::Instant;
+
+use crt_core::{AnsiColor, CellFlags, Colors, SemanticZone};
+use crt_renderer::GlyphInstance;
 use crt_theme::AnsiPalette;
 
 use super::interaction::SearchMatch;
-use super::types::ansi_color_to_rgba;
+use super::types::{TabId, ansi_color_to_rgba};
+use crate::input::{DetectedPath, DetectedUrl};
 
 /// Cursor position info returned from text buffer update
 #[derive(Debug, Clone, Copy)]
@@ -24,6 +28,8 @@ pub struct CursorInfo {
     pub visible: bool,
     /// Cursor shape requested by the terminal/application
     pub shape: crt_core::CursorShape,
+    /// Cursor color set by the application (OSC 12), replacing the theme color
+    pub color: Option<[f32; 4]>,
 }
 
 /// Text decoration (underline, strikethrough, or background)
@@ -62,7 +68,7 @@ pub struct TextBufferUpdateResult {
 
 /// Collected cell data for single-pass processing
 /// Avoids multiple terminal.renderable_content() calls
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub(crate) struct CollectedCell {
     pub(crate) col: usize,
     pub(crate) grid_line: i32,
@@ -119,6 +125,8 @@ pub struct RenderContext<'a> {
     pub display_offset: i32,
     pub cursor_viewport_line: i32,
     pub palette: &'a AnsiPalette,
+    /// Colors set by the application at runtime (OSC 4/10/11), layered over `palette`
+    pub runtime_colors: &'a Colors,
     pub default_fg: [f32; 4],
     pub default_bg: [f32; 4],
     pub hovered_url_index: Option<usize>,
@@ -132,6 +140,9 @@ pub struct RenderContext<'a> {
     pub has_semantic_zones: bool,
     /// Function to determine semantic zone for a grid line
     pub get_line_zone: Box<dyn Fn(i32) -> SemanticZone + 'a>,
+    pub command_fold_style: Option<&'a crt_theme::CommandFoldStyle>,
+    /// Function to check if a grid line is the summary row of folded output
+    pub is_folded_line: Box<dyn Fn(i32) -> bool + 'a>,
 }
 
 /// Prepare terminal render data from collected cells.
@@ -156,6 +167,10 @@ pub fn prepare_render_cells(
             + (viewport_line as f32 * ctx.layout.line_height);
 
         let flags = cell.flags;
+        // Summary rows of folded output are drawn as a band in the fold style
+        let fold_style = ctx
+            .command_fold_style
+            .filter(|_| (ctx.is_folded_line)(grid_line));
 
         // Handle INVERSE flag - swap foreground and background colors
         let (fg_ansi, bg_ansi) = if flags.contains(CellFlags::INVERSE) {
@@ -165,8 +180,17 @@ pub fn prepare_render_cells(
         };
 
         // Get foreground color
-        let mut fg_color =
-            ansi_color_to_rgba(fg_ansi, ctx.palette, ctx.default_fg, ctx.default_bg);
+        let mut fg_color = ansi_color_to_rgba(
+            fg_ansi,
+            ctx.palette,
+            ctx.runtime_colors,
+            ctx.default_fg,
+            ctx.default_bg,
+        );
+
+        if let Some(style) = fold_style {
+            fg_color = style.foreground.to_array();
+        }
 
         // Apply DIM flag by reducing alpha
         if flags.contains(CellFlags::DIM) {
@@ -174,13 +198,27 @@ pub fn prepare_render_cells(
         }
 
         // Get background color and add decoration if non-default
-        let is_spacer = flags
-            .intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER);
+        let is_spacer =
+            flags.intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER);
         let is_hidden = flags.contains(CellFlags::HIDDEN);
 
-        if !is_spacer && !is_hidden {
-            let bg_color =
-                ansi_color_to_rgba(bg_ansi, ctx.palette, ctx.default_fg, ctx.default_bg);
+        if let Some(style) = fold_style {
+            decorations.push(TextDecoration {
+                x,
+                y,
+                cell_width: ctx.layout.cell_width,
+                cell_height: ctx.layout.line_height,
+                color: style.background.to_array(),
+                kind: DecorationKind::Background,
+            });
+        } else if !is_spacer && !is_hidden {
+            let bg_color = ansi_color_to_rgba(
+                bg_ansi,
+                ctx.palette,
+                ctx.runtime_colors,
+                ctx.default_fg,
+                ctx.default_bg,
+            );
             if bg_color != ctx.default_bg {
                 decorations.push(TextDecoration {
                     x,
@@ -262,10 +300,7 @@ pub fn prepare_render_cells(
         if ctx.search_active && !ctx.search_matches.is_empty() {
             if let Some(highlight_style) = ctx.highlight_style {
                 for (match_idx, search_match) in ctx.search_matches.iter().enumerate() {
-                    if search_match.line == grid_line
-                        && col >= search_match.start_col
-                        && col < search_match.end_col
-                    {
+                    if search_match.contains(grid_line, col) {
                         let highlight_color = if match_idx == ctx.current_match {
                             highlight_style.current_background.to_array()
                         } else {
@@ -313,6 +348,52 @@ pub fn prepare_render_cells(
     (prepared, decorations)
 }
 
+/// Everything derived from one viewport line, reused until the line changes
+#[derive(Default)]
+pub(crate) struct LineCache {
+    /// Cells the line was built from
+    pub(crate) cells: Vec<CollectedCell>,
+    /// OSC 8 hyperlink spans on the line
+    pub(crate) hyperlinks: Vec<DetectedUrl>,
+    /// Whether the line is routed to the glow renderer
+    pub(crate) glow: bool,
+    /// URLs detected in the line text
+    pub(crate) urls: Vec<DetectedUrl>,
+    /// File paths detected in the line text, before validation
+    pub(crate) paths: Vec<DetectedPath>,
+    /// Backgrounds, underlines and strikethroughs
+    pub(crate) decorations: Vec<TextDecoration>,
+    /// Positioned glyphs for the glow (prompt/input) renderer
+    pub(crate) glow_glyphs: Vec<GlyphInstance>,
+    /// Positioned glyphs for the output renderer
+    pub(crate) output_glyphs: Vec<GlyphInstance>,
+}
+
+impl LineCache {
+    /// Whether this cache still matches the line's current cells and routing
+    pub(crate) fn matches(
+        &self,
+        cells: &[CollectedCell],
+        hyperlinks: &[DetectedUrl],
+        glow: bool,
+    ) -> bool {
+        self.glow == glow && self.cells == cells && self.hyperlinks == hyperlinks
+    }
+}
+
+/// Inputs shared by every cached line; any change invalidates them all
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct LineCacheKey {
+    pub(crate) tab_id: TabId,
+    pub(crate) screen_lines: usize,
+    pub(crate) display_offset: i32,
+    pub(crate) has_semantic_zones: bool,
+    /// Origin, cell width and line height the glyphs were positioned with
+    pub(crate) layout: [f32; 4],
+    /// Glyph atlas generation the glyphs were positioned with
+    pub(crate) atlas_generation: u64,
+}
+
 /// Cached rendering state that persists across frames
 #[derive(Default)]
 pub struct CachedRenderState {
@@ -320,23 +401,39 @@ pub struct CachedRenderState {
     pub decorations: Vec<TextDecoration>,
     /// Cached cursor info
     pub cursor: Option<CursorInfo>,
-    /// Reusable line text buffer for URL detection (cleared and reused each update)
+    /// Text of each viewport line, for URL detection across wrapped lines
     pub(crate) line_texts: std::collections::BTreeMap<i32, String>,
-    /// Reusable cell collection buffer (cleared and reused each update)
-    pub(crate) collected_cells: Vec<CollectedCell>,
-    /// Per-line cached prepared cells from previous frame (for partial damage reuse)
-    pub(crate) line_cells: std::collections::HashMap<i32, Vec<PreparedCell>>,
-    /// Per-line cached decorations from previous frame
-    pub(crate) line_decorations: std::collections::HashMap<i32, Vec<TextDecoration>>,
+    /// Per-viewport-line caches, indexed by viewport line
+    pub(crate) lines: Vec<LineCache>,
+    /// What `lines` was built for; `None` forces a full rebuild
+    pub(crate) key: Option<LineCacheKey>,
+    /// Lines rebuilt by the last text update
+    pub lines_rebuilt: usize,
 }
 
 /// Render state (dirty tracking, frame count, visibility)
 ///
 /// Groups state related to rendering decisions and caching.
-#[derive(Default)]
 pub struct RenderState {
     /// Whether the window needs redrawing
     pub dirty: bool,
+    /// Something on screen is animating, so the next frame is due as soon
+    /// as the frame rate allows (recomputed every frame)
+    pub animating: bool,
+    /// Earliest time a frame is due with nothing animating: cursor blink,
+    /// toast fade, override expiry (recomputed every frame)
+    pub wake_at: Option<Instant>,
+    /// When the last frame was rendered, for frame rate capping
+    pub last_frame: Option<Instant>,
+    /// Last input or shell output, for pausing backdrop animation when idle
+    pub last_activity: Instant,
+    /// Backdrop effects are paused after `pause_animation_after_secs` idle
+    pub animation_paused: bool,
+    /// The active tab's program is mid-way through a synchronized update
+    /// (DEC mode 2026); content redraws wait until it ends or this deadline
+    pub sync_update_until: Option<Instant>,
+    /// When tab titles are next refreshed from their foreground process
+    pub title_refresh_at: Instant,
     /// Frame counter for periodic operations
     pub frame_count: u32,
     /// Window is occluded (hidden, minimized, or fully covered)
@@ -349,6 +446,33 @@ pub struct RenderState {
     pub paste_pending: bool,
 }
 
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            dirty: true,
+            animating: false,
+            wake_at: None,
+            last_frame: None,
+            last_activity: Instant::now(),
+            animation_paused: false,
+            sync_update_until: None,
+            title_refresh_at: Instant::now(),
+            frame_count: 0,
+            occluded: false,
+            focused: true,
+            cached: CachedRenderState::default(),
+            paste_pending: false,
+        }
+    }
+}
+
+impl RenderState {
+    /// Ask for a frame at `at` unless one is already due sooner
+    pub fn schedule_wake(&mut self, at: Instant) {
+        self.wake_at = Some(self.wake_at.map_or(at, |wake_at| wake_at.min(at)));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -393,6 +517,7 @@ mod tests {
         ];
 
         let palette = AnsiPalette::default();
+        let runtime_colors = Colors::default();
         let default_fg = [1.0, 1.0, 1.0, 1.0];
         let default_bg = [0.0, 0.0, 0.0, 1.0];
 
@@ -407,6 +532,7 @@ mod tests {
             display_offset: 0,
             cursor_viewport_line: 0,
             palette: &palette,
+            runtime_colors: &runtime_colors,
             default_fg,
             default_bg,
             hovered_url_index: None,
@@ -419,6 +545,8 @@ mod tests {
             highlight_style: None,
             has_semantic_zones: false,
             get_line_zone: Box::new(|_| SemanticZone::Unknown),
+            command_fold_style: None,
+            is_folded_line: Box::new(|_| false),
         };
 
         let (prepared, decorations) = prepare_render_cells(&cells, &ctx);
@@ -475,6 +603,7 @@ mod tests {
             },
         ];
         let palette = AnsiPalette::default();
+        let runtime_colors = Colors::default();
         // A path spanning cols 0..2 on viewport line 0.
         let paths = vec![crate::input::DetectedPath {
             path: "src/a.rs".to_string(),
@@ -498,6 +627,7 @@ mod tests {
             display_offset: 0,
             cursor_viewport_line: 0,
             palette: &palette,
+            runtime_colors: &runtime_colors,
             default_fg: [1.0, 1.0, 1.0, 1.0],
             default_bg: [0.0, 0.0, 0.0, 1.0],
             hovered_url_index: None,
@@ -510,6 +640,8 @@ mod tests {
             highlight_style: None,
             has_semantic_zones: false,
             get_line_zone: Box::new(|_| SemanticZone::Unknown),
+            command_fold_style: None,
+            is_folded_line: Box::new(|_| false),
         };
 
         // Hovered → both cells underlined.
@@ -517,4 +649,105 @@ mod tests {
         // Not hovered → no path underline.
         assert_eq!(underline_cells(&cells, &mk_ctx(None)), 0);
     }
+
+    #[test]
+    fn folded_line_drawn_in_fold_style() {
+        let cells = text_cells("▸ ls");
+        let palette = AnsiPalette::default();
+        let runtime_colors = Colors::default();
+        let style = crt_theme::CommandFoldStyle::default();
+        let mk_ctx = |folded: bool| RenderContext {
+            layout: RenderLayout {
+                offset_x: 0.0,
+                offset_y: 0.0,
+                padding: 10.0,
+                cell_width: 8.0,
+                line_height: 16.0,
+            },
+            display_offset: 0,
+            cursor_viewport_line: 0,
+            palette: &palette,
+            runtime_colors: &runtime_colors,
+            default_fg: [1.0, 1.0, 1.0, 1.0],
+            default_bg: [0.0, 0.0, 0.0, 1.0],
+            hovered_url_index: None,
+            detected_urls: &[],
+            hovered_path_index: None,
+            detected_paths: &[],
+            search_active: false,
+            search_matches: &[],
+            current_match: 0,
+            highlight_style: None,
+            has_semantic_zones: false,
+            get_line_zone: Box::new(|_| SemanticZone::Unknown),
+            command_fold_style: Some(&style),
+            is_folded_line: Box::new(move |line| folded && line == 0),
+        };
+
+        // Every cell of the row gets the band, spaces included
+        let (prepared, decorations) = prepare_render_cells(&cells, &mk_ctx(true));
+        assert_eq!(decorations.len(), 4);
+        assert!(
+            decorations
+                .iter()
+                .all(|d| d.color == style.background.to_array())
+        );
+        assert_eq!(prepared[0].fg_color, style.foreground.to_array());
+
+        let (prepared, decorations) = prepare_render_cells(&cells, &mk_ctx(false));
+        assert!(decorations.is_empty());
+        assert_eq!(prepared[0].fg_color, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    fn text_cells(text: &str) -> Vec<CollectedCell> {
+        text.chars()
+            .enumerate()
+            .map(|(col, c)| CollectedCell {
+                col,
+                grid_line: 0,
+                c,
+                flags: CellFlags::empty(),
+                fg: AnsiColor::Named(NamedColor::Foreground),
+                bg: AnsiColor::Named(NamedColor::Background),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_line_cache_matches_unchanged_line() {
+        let cache = LineCache {
+            cells: text_cells("$ ls"),
+            ..Default::default()
+        };
+        assert!(cache.matches(&text_cells("$ ls"), &[], false));
+    }
+
+    #[test]
+    fn test_line_cache_detects_changes() {
+        let cache = LineCache {
+            cells: text_cells("$ ls"),
+            ..Default::default()
+        };
+        // Different text
+        assert!(!cache.matches(&text_cells("$ lx"), &[], false));
+
+        // Same text, different attributes
+        let mut bold = text_cells("$ ls");
+        bold[2].flags = CellFlags::BOLD;
+        assert!(!cache.matches(&bold, &[], false));
+
+        // Same cells, cursor moved so the line now glows
+        assert!(!cache.matches(&text_cells("$ ls"), &[], true));
+
+        // Same cells, now carrying an OSC 8 hyperlink
+        let link = DetectedUrl {
+            url: "https://example.com".to_string(),
+            start_col: 2,
+            end_col: 4,
+            line: 0,
+            end_line: 0,
+            explicit: true,
+        };
+        assert!(!cache.matches(&text_cells("$ ls"), &[link], false));
+    }
 }
@@ -1,7 +1,9 @@
 //! Basic types and utility functions for window state.
 
-use crt_core::AnsiColor;
-use crt_theme::AnsiPalette;
+use crt_core::{AnsiColor, Colors, NamedColor, Rgb};
+use crt_theme::{AnsiPalette, Color, Theme};
+
+use super::overrides::OverrideState;
 
 /// Unique identifier for a terminal tab
 pub type TabId = u64;
@@ -36,15 +38,99 @@ impl EffectId {
     }
 }
 
+/// Resolve the color a program asked for with OSC 4/10/11/12.
+///
+/// `index` follows alacritty's color indices: 0-255 for the palette, then
+/// `NamedColor::Foreground`/`Background`/`Cursor`. Colors the application set
+/// itself (`runtime`) win, then active event overrides (e.g.
+/// `::on-command-fail`), then the theme. Gradient backgrounds report their
+/// bottom stop, the same color used to paint cells with the default
+/// background. Returns `None` for indices we do not answer.
+pub(crate) fn query_color(
+    index: usize,
+    theme: &Theme,
+    overrides: &OverrideState,
+    runtime: &Colors,
+) -> Option<Rgb> {
+    if let Some(rgb) = runtime_color(runtime, index) {
+        return Some(rgb);
+    }
+    let color = match index {
+        0..=15 => theme.palette.get(index as u8),
+        16..=255 => {
+            let idx = index as u8;
+            theme
+                .palette
+                .get_extended(idx)
+                .unwrap_or_else(|| AnsiPalette::calculate_extended(idx))
+        }
+        i if i == NamedColor::Foreground as usize => {
+            overrides.get_foreground().unwrap_or(theme.foreground)
+        }
+        i if i == NamedColor::Background as usize => {
+            overrides
+                .get_background()
+                .unwrap_or(theme.background)
+                .bottom
+        }
+        i if i == NamedColor::Cursor as usize => {
+            overrides.get_cursor_color().unwrap_or(theme.cursor_color)
+        }
+        _ => return None,
+    };
+    Some(color_to_rgb(color))
+}
+
+/// Convert a theme color to 8-bit RGB (alpha is dropped)
+pub(crate) fn color_to_rgb(color: Color) -> Rgb {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb {
+        r: channel(color.r),
+        g: channel(color.g),
+        b: channel(color.b),
+    }
+}
+
+/// Look up a color set by the application, tolerating out-of-range indices
+fn runtime_color(runtime: &Colors, index: usize) -> Option<Rgb> {
+    if index < crt_core::color::COUNT {
+        runtime[index]
+    } else {
+        None
+    }
+}
+
+/// Convert 8-bit RGB to an opaque RGBA array
+pub(crate) fn rgb_to_rgba(rgb: Rgb) -> [f32; 4] {
+    [
+        rgb.r as f32 / 255.0,
+        rgb.g as f32 / 255.0,
+        rgb.b as f32 / 255.0,
+        1.0,
+    ]
+}
+
 /// Map alacritty_terminal AnsiColor to RGBA array using theme palette
+///
+/// Colors the application changed at runtime (OSC 4/10/11, kept in
+/// `Term::colors()`) are layered over the theme palette and default colors.
 pub(crate) fn ansi_color_to_rgba(
     color: AnsiColor,
     palette: &AnsiPalette,
+    runtime: &Colors,
     default_fg: [f32; 4],
     default_bg: [f32; 4],
 ) -> [f32; 4] {
     use crt_core::AnsiColor::*;
-    use crt_core::NamedColor;
+
+    let runtime_override = match color {
+        Named(named) => runtime_color(runtime, named as usize),
+        Indexed(idx) => runtime_color(runtime, idx as usize),
+        Spec(_) => None,
+    };
+    if let Some(rgb) = runtime_override {
+        return rgb_to_rgba(rgb);
+    }
 
     match color {
         // Named colors (0-7 normal, 8-15 bright)
@@ -102,11 +188,182 @@ pub(crate) fn ansi_color_to_rgba(
             }
         }
         // Direct RGB color
-        Spec(rgb) => [
-            rgb.r as f32 / 255.0,
-            rgb.g as f32 / 255.0,
-            rgb.b as f32 / 255.0,
-            1.0,
-        ],
+        Spec(rgb) => rgb_to_rgba(rgb),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::OverrideEventType;
+    use crt_theme::{EventOverride, LinearGradient};
+
+    fn rgb(hex: u32) -> Rgb {
+        color_to_rgb(Color::from_hex(hex))
+    }
+
+    #[test]
+    fn query_palette_colors() {
+        let theme = Theme::default();
+        let overrides = OverrideState::default();
+        let runtime = Colors::default();
+        assert_eq!(
+            query_color(1, &theme, &overrides, &runtime),
+            Some(color_to_rgb(theme.palette.red))
+        );
+        // Standard 256-color cube entry
+        assert_eq!(
+            query_color(196, &theme, &overrides, &runtime),
+            Some(rgb(0xff0000))
+        );
+        assert_eq!(
+            query_color(232, &theme, &overrides, &runtime),
+            Some(rgb(0x080808))
+        );
+    }
+
+    #[test]
+    fn query_dynamic_colors_from_theme() {
+        let theme = Theme {
+            foreground: Color::from_hex(0x112233),
+            background: LinearGradient {
+                top: Color::from_hex(0xffffff),
+                bottom: Color::from_hex(0x445566),
+            },
+            cursor_color: Color::from_hex(0x778899),
+            ..Default::default()
+        };
+        let overrides = OverrideState::default();
+        let runtime = Colors::default();
+
+        let fg = NamedColor::Foreground as usize;
+        let bg = NamedColor::Background as usize;
+        let cursor = NamedColor::Cursor as usize;
+        assert_eq!(
+            query_color(fg, &theme, &overrides, &runtime),
+            Some(rgb(0x112233))
+        );
+        // Gradient backgrounds report the bottom stop
+        assert_eq!(
+            query_color(bg, &theme, &overrides, &runtime),
+            Some(rgb(0x445566))
+        );
+        assert_eq!(
+            query_color(cursor, &theme, &overrides, &runtime),
+            Some(rgb(0x778899))
+        );
+    }
+
+    #[test]
+    fn query_prefers_active_override() {
+        let theme = Theme::default();
+        let mut overrides = OverrideState::default();
+        let runtime = Colors::default();
+        overrides.add(
+            OverrideEventType::CommandFail,
+            EventOverride {
+                foreground: Some(Color::from_hex(0xff0000)),
+                duration_ms: 60_000,
+                ..Default::default()
+            },
+        );
+        let fg = NamedColor::Foreground as usize;
+        assert_eq!(
+            query_color(fg, &theme, &overrides, &runtime),
+            Some(rgb(0xff0000))
+        );
+    }
+
+    #[test]
+    fn query_unknown_index_unanswered() {
+        let theme = Theme::default();
+        let overrides = OverrideState::default();
+        let runtime = Colors::default();
+        assert_eq!(
+            query_color(NamedColor::DimRed as usize, &theme, &overrides, &runtime),
+            None
+        );
+    }
+
+    #[test]
+    fn query_prefers_runtime_color() {
+        let theme = Theme::default();
+        let overrides = OverrideState::default();
+        let mut runtime = Colors::default();
+        runtime[NamedColor::Background] = Some(Rgb { r: 1, g: 2, b: 3 });
+        runtime[4] = Some(Rgb { r: 4, g: 5, b: 6 });
+        let bg = NamedColor::Background as usize;
+        assert_eq!(
+            query_color(bg, &theme, &overrides, &runtime),
+            Some(Rgb { r: 1, g: 2, b: 3 })
+        );
+        assert_eq!(
+            query_color(4, &theme, &overrides, &runtime),
+            Some(Rgb { r: 4, g: 5, b: 6 })
+        );
+    }
+
+    #[test]
+    fn runtime_palette_layered_over_theme() {
+        let palette = AnsiPalette::default();
+        let fg = [1.0, 1.0, 1.0, 1.0];
+        let bg = [0.0, 0.0, 0.0, 1.0];
+        let mut runtime = Colors::default();
+
+        let red = AnsiColor::Named(NamedColor::Red);
+        assert_eq!(
+            ansi_color_to_rgba(red, &palette, &runtime, fg, bg),
+            palette.red.to_array()
+        );
+
+        runtime[1] = Some(Rgb { r: 255, g: 0, b: 0 });
+        runtime[NamedColor::Foreground] = Some(Rgb { r: 0, g: 255, b: 0 });
+        runtime[200] = Some(Rgb { r: 0, g: 0, b: 255 });
+        assert_eq!(
+            ansi_color_to_rgba(red, &palette, &runtime, fg, bg),
+            [1.0, 0.0, 0.0, 1.0]
+        );
+        // Indexed 1 and Named Red share the same slot
+        assert_eq!(
+            ansi_color_to_rgba(AnsiColor::Indexed(1), &palette, &runtime, fg, bg),
+            [1.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            ansi_color_to_rgba(
+                AnsiColor::Named(NamedColor::Foreground),
+                &palette,
+                &runtime,
+                fg,
+                bg
+            ),
+            [0.0, 1.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            ansi_color_to_rgba(AnsiColor::Indexed(200), &palette, &runtime, fg, bg),
+            [0.0, 0.0, 1.0, 1.0]
+        );
+        // Background untouched falls back to the theme default
+        assert_eq!(
+            ansi_color_to_rgba(
+                AnsiColor::Named(NamedColor::Background),
+                &palette,
+                &runtime,
+                fg,
+                bg
+            ),
+            bg
+        );
+    }
+
+    #[test]
+    fn color_to_rgb_clamps_and_rounds() {
+        assert_eq!(
+            color_to_rgb(Color::rgba(1.5, -0.2, 0.5, 0.3)),
+            Rgb {
+                r: 255,
+                g: 0,
+                b: 128
+            }
+        );
     }
 }
@@ -1,12 +1,15 @@
 //! UI overlay state types.
 //!
 //! Groups transient UI state that overlays the terminal content:
-//! search, bell, context menu, zoom indicator, toast, window rename, and theme overrides.
+//! search, bell, context menu, zoom indicator, toast, window rename, confirmation
+//! prompts, and theme overrides.
 
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use super::interaction::{ContextMenu, SearchState};
 use super::overrides::OverrideState;
+use super::types::TabId;
 
 /// Window rename input state
 #[derive(Debug, Clone, Default)]
@@ -46,6 +49,60 @@ impl WindowRenameState {
     }
 }
 
+/// Action held until the user answers a confirmation prompt
+pub enum ConfirmAction {
+    /// Answer an OSC 52 clipboard read from the program running in `tab_id`
+    ClipboardRead {
+        tab_id: TabId,
+        formatter: Arc<dyn Fn(&str) -> String + Sync + Send>,
+    },
+    /// Close `tab_id` even though a program is running in it
+    CloseTab(TabId),
+    /// Close the window even though a program is running in one of its tabs
+    CloseWindow,
+    /// Quit even though a program is running in some tab
+    Quit,
+}
+
+/// Yes/no prompt shown over the terminal (Enter accepts, Escape declines)
+#[derive(Default)]
+pub struct ConfirmPrompt {
+    /// Question shown to the user
+    pub message: String,
+    /// Action to run if the user accepts (None when no prompt is showing)
+    pub action: Option<ConfirmAction>,
+}
+
+impl ConfirmPrompt {
+    /// Show a prompt for the given action
+    pub fn show(&mut self, message: impl Into<String>, action: ConfirmAction) {
+        self.message = message.into();
+        self.action = Some(action);
+    }
+
+    /// Check if a prompt is waiting for an answer
+    pub fn is_active(&self) -> bool {
+        self.action.is_some()
+    }
+
+    /// Key hint shown after the question
+    pub fn hint(&self) -> &'static str {
+        match self.action {
+            Some(ConfirmAction::ClipboardRead { .. }) | None => "[Enter] Allow  [Esc] Deny",
+            Some(ConfirmAction::CloseTab(_) | ConfirmAction::CloseWindow) => {
+                "[Enter] Close  [Esc] Cancel"
+            }
+            Some(ConfirmAction::Quit) => "[Enter] Quit  [Esc] Cancel",
+        }
+    }
+
+    /// Close the prompt, returning its pending action
+    pub fn take(&mut self) -> Option<ConfirmAction> {
+        self.message.clear();
+        self.action.take()
+    }
+}
+
 /// Bell visual flash state
 #[derive(Debug, Clone)]
 pub struct BellState {
@@ -134,12 +191,17 @@ pub struct UiState {
     pub toast: Toast,
     /// Window rename input state
     pub window_rename: WindowRenameState,
+    /// Pending yes/no confirmation (e.g. OSC 52 clipboard read)
+    pub confirm: ConfirmPrompt,
     /// Active theme overrides from events (bell, command success/fail, focus)
     pub overrides: OverrideState,
     /// Pending theme change from context menu (processed by main loop)
     pub pending_theme: Option<String>,
 }
 
+/// How long a toast takes to fade out at the end of its display time
+const TOAST_FADE: Duration = Duration::from_secs(1);
+
 /// Toast notification for errors and status messages
 #[derive(Debug, Clone, Default)]
 pub struct Toast {
@@ -158,7 +220,6 @@ pub struct Toast {
 pub enum ToastType {
     #[default]
     Info,
-    #[allow(dead_code)]
     Warning,
     Error,
 }
@@ -179,6 +240,12 @@ impl Toast {
             .unwrap_or(false)
     }
 
+    /// When the fade-out begins, while the toast is still fully opaque
+    pub fn fade_starts_at(&self) -> Option<Instant> {
+        let fade_start = self.triggered_at? + self.display_duration.saturating_sub(TOAST_FADE);
+        (Instant::now() < fade_start).then_some(fade_start)
+    }
+
     /// Get the opacity (fades out over last 1s)
     pub fn opacity(&self) -> f32 {
         let Some(triggered_at) = self.triggered_at else {
@@ -188,13 +255,12 @@ impl Toast {
         if elapsed >= self.display_duration {
             return 0.0;
         }
-        let fade_start = self.display_duration.saturating_sub(Duration::from_secs(1));
+        let fade_start = self.display_duration.saturating_sub(TOAST_FADE);
         if elapsed < fade_start {
             1.0
         } else {
             let fade_elapsed = elapsed - fade_start;
-            let fade_duration = Duration::from_secs(1);
-            1.0 - (fade_elapsed.as_secs_f32() / fade_duration.as_secs_f32())
+            1.0 - (fade_elapsed.as_secs_f32() / TOAST_FADE.as_secs_f32())
         }
     }
 }
@@ -10,7 +10,7 @@
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use crt_core::{ShellTerminal, Size, Terminal};
+use crt_core::{ShellEvent, ShellTerminal, Size, SpawnOptions, Terminal};
 use tempfile::TempDir;
 
 /// Test environment with isolated config directory
@@ -210,6 +210,21 @@ impl ShellTestHarness {
         })
     }
 
+    /// Create with the bundled shell integration (OSC 133/7) for `shell_path`
+    pub fn with_integration(cols: usize, lines: usize, shell_path: &str) -> anyhow::Result<Self> {
+        let options = SpawnOptions {
+            shell: Some(shell_path.to_string()),
+            semantic_prompts: true,
+            shell_assets_dir: Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/shell")),
+            ..Default::default()
+        };
+        let shell = ShellTerminal::with_options(Size::new(cols, lines), options)?;
+        Ok(Self {
+            shell,
+            timeout: Duration::from_secs(5),
+        })
+    }
+
     /// Create with default size (80x24)
     pub fn default_size() -> anyhow::Result<Self> {
         Self::new(80, 24)
@@ -312,6 +327,31 @@ impl ShellTestHarness {
         Ok(accumulated)
     }
 
+    /// Wait for the next command success/fail event from OSC 133;D
+    pub fn wait_for_command_event(&mut self) -> anyhow::Result<ShellEvent> {
+        let start = Instant::now();
+
+        loop {
+            self.process_output();
+
+            let events = self.shell.terminal_mut().take_shell_events();
+            if let Some(event) = events.into_iter().find(|event| {
+                matches!(
+                    event,
+                    ShellEvent::CommandSuccess | ShellEvent::CommandFail(_)
+                )
+            }) {
+                return Ok(event);
+            }
+
+            if start.elapsed() > self.timeout {
+                anyhow::bail!("Timeout waiting for a command event");
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
     /// Wait for specific text to appear in terminal output
     pub fn wait_for_text(&mut self, expected: &str) -> anyhow::Result<()> {
         let start = Instant::now();
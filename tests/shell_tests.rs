@@ -6,6 +6,7 @@
 mod common;
 
 use common::ShellTestHarness;
+use crt_core::ShellEvent;
 use std::time::Duration;
 
 // === Basic shell tests ===
@@ -329,3 +330,66 @@ fn test_shell_long_line() {
     let content = harness.all_content();
     assert!(content.contains("AAAA"), "Should contain the long line");
 }
+
+// === Shell integration tests (OSC 133 / OSC 7) ===
+
+/// Locate an installed shell; integration tests skip shells that aren't present
+fn find_shell(name: &str) -> Option<String> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().into_owned())
+    })
+}
+
+/// Run `true` then `false` under the bundled integration and check the events
+fn assert_integration_reports_commands(name: &str) {
+    let Some(shell) = find_shell(name) else {
+        eprintln!("{} not installed, skipping", name);
+        return;
+    };
+    let mut harness =
+        ShellTestHarness::with_integration(80, 24, &shell).expect("Failed to spawn shell");
+
+    harness.wait_for_ready();
+
+    harness.send("true\n");
+    let event = harness
+        .wait_for_command_event()
+        .expect("No event for `true`");
+    assert_eq!(event, ShellEvent::CommandSuccess);
+
+    harness.send("false\n");
+    let event = harness
+        .wait_for_command_event()
+        .expect("No event for `false`");
+    assert_eq!(event, ShellEvent::CommandFail(1));
+
+    // Every prompt also reports the working directory
+    assert!(harness.shell().terminal().reported_cwd().is_some());
+
+    // Both commands are on record as typed
+    let commands = harness.shell().commands();
+    let summary: Vec<_> = commands
+        .iter()
+        .map(|command| (command.command.as_str(), command.exit_code))
+        .collect();
+    assert_eq!(summary, vec![("true", Some(0)), ("false", Some(1))]);
+    assert!(commands.iter().all(|command| command.cwd.is_some()));
+}
+
+#[test]
+fn test_bash_integration_command_events() {
+    assert_integration_reports_commands("bash");
+}
+
+#[test]
+fn test_zsh_integration_command_events() {
+    assert_integration_reports_commands("zsh");
+}
+
+#[test]
+fn test_fish_integration_command_events() {
+    assert_integration_reports_commands("fish");
+}
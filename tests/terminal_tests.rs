@@ -786,3 +786,23 @@ fn test_unicode_combining() {
     let lines = harness.visible_lines();
     assert!(lines[0].contains("caf"));
 }
+
+// === OSC 8 hyperlink tests ===
+
+#[test]
+fn test_osc8_hyperlink_attached_to_cells() {
+    use crt_core::{Column, Line, Point};
+
+    let mut harness = TerminalTestHarness::default_size();
+
+    harness.input_str("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ plain");
+
+    harness.assert_line_contains(0, "link plain");
+    let grid = harness.terminal().inner().grid();
+    let linked = grid[Point::new(Line(0), Column(0))].hyperlink();
+    assert_eq!(
+        linked.as_ref().map(|l| l.uri()),
+        Some("https://example.com")
+    );
+    assert!(grid[Point::new(Line(0), Column(5))].hyperlink().is_none());
+}